@@ -0,0 +1,198 @@
+use std::fs;
+
+use chrono::Local;
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::project_archive::{extract_project_folder, get_archive_file_path, read_project_archive, write_project_archive}, businesslogic::project_file::{get_folder_path_suffix, get_project_directory_path, remove_project_directory}, models::{project::{Project, ProjectStatus}, project_archive::ProjectArchive, task::Task, time_entry::TimeEntry}}, repositories::{repository_trait::{Repository, StatusHistoryRepository}, unit_of_work::UnitOfWork}};
+use crate::tr;
+
+use super::interface::ArchiveProjectOption;
+use super::task_usecase::fetch_tasks;
+use super::time_entry_usecase::fetch_time_entries;
+
+/// 終わったプロジェクトを、フォルダと関連するデータごとarchiveフォルダのzipにまとめ、アーカイブ済みにする。
+/// フォルダを残すかどうかはoptionで指定する。
+pub fn archive_project(project_repo: &dyn Repository<Project>, task_repo: &dyn Repository<Task>, time_entry_repo: &dyn Repository<TimeEntry>, status_history_repo: &dyn StatusHistoryRepository, id: &str, option: ArchiveProjectOption) -> Result<(), AppError> {
+    let mut project = project_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+    if project.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.in_trash", project.title)));
+    }
+    if project.archived_at.is_some() {
+        return Err(AppError::validation(tr!("archive.already_archived", project.title)));
+    }
+    if !matches!(project.status, ProjectStatus::Completed | ProjectStatus::Invoiced | ProjectStatus::Cancelled) {
+        return Err(AppError::validation(tr!("archive.project_not_finished", project.title)));
+    }
+    let time_entries = fetch_time_entries(time_entry_repo, id)?;
+    if time_entries.iter().any(|entry| entry.ended_at.is_none()) {
+        return Err(AppError::validation(tr!("archive.timer_running")));
+    }
+
+    let archived_at = Local::now();
+    project.archived_at = Some(archived_at);
+    let project_path = get_project_directory_path(&get_folder_path_suffix(&project));
+    let archive = ProjectArchive {
+        archived_at,
+        project: project.clone(),
+        tasks: fetch_tasks(task_repo, id)?,
+        time_entries,
+        status_history: status_history_repo.fetch_by_project(id)?,
+        includes_folder: project_path.exists(),
+    };
+
+    // 保存に失敗したらアーカイブのファイルを消す
+    let archive_file_path = get_archive_file_path(&project);
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.run(
+        &tr!("step.create_archive"),
+        || write_project_archive(&archive_file_path, &archive, &project_path),
+        |_| fs::remove_file(&archive_file_path).map_err(AppError::from),
+    )?;
+    unit_of_work.check(project_repo.update(project))?;
+    unit_of_work.commit();
+
+    // 行を更新した後にフォルダを消す。フォルダの削除に失敗してもアーカイブには残っている
+    if option == ArchiveProjectOption::RemoveFolder && archive.includes_folder {
+        remove_project_directory(project_path)?;
+    }
+    Ok(())
+}
+
+/// アーカイブからプロジェクトのフォルダを戻し、アーカイブ済みを解除する。
+/// フォルダを残してアーカイブしていた時は、今のフォルダをそのまま使う。
+/// idは使い回されるので、アーカイブのファイルがこのプロジェクトをアーカイブした時のものかを確かめてから戻す
+pub fn unarchive_project(project_repo: &dyn Repository<Project>, id: &str) -> Result<(), AppError> {
+    let mut project = project_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+    if project.archived_at.is_none() {
+        return Err(AppError::validation(tr!("archive.not_archived", project.title)));
+    }
+    let archive_file_path = get_archive_file_path(&project);
+    if !archive_file_path.exists() {
+        return Err(AppError::validation(tr!("archive.file_missing", archive_file_path.display())));
+    }
+    let archive = read_project_archive(&archive_file_path)?;
+    if !is_archive_of(&archive, &project) {
+        return Err(AppError::validation(tr!("archive.project_mismatch", archive_file_path.display())));
+    }
+
+    // 保存に失敗したら展開したフォルダを消す
+    let project_path = get_project_directory_path(&get_folder_path_suffix(&project));
+    let mut unit_of_work = UnitOfWork::new();
+    if !project_path.exists() && archive.includes_folder {
+        unit_of_work.run(
+            &tr!("step.restore_folder_from_archive"),
+            || extract_project_folder(&archive_file_path, &project_path),
+            |_| remove_project_directory(project_path.clone()),
+        )?;
+    }
+    project.archived_at = None;
+    unit_of_work.check(project_repo.update(project))?;
+    unit_of_work.commit();
+
+    // 戻し終わったアーカイブのファイルは消す
+    fs::remove_file(&archive_file_path).map_err(|e| AppError::io(tr!("file.remove_file_failed", archive_file_path.display(), e)))
+}
+
+// 保存する時に秒より細かい部分が落ちることがあるので、アーカイブした日時は秒で比べる
+fn is_archive_of(archive: &ProjectArchive, project: &Project) -> bool {
+    archive.project.id == project.id && project.archived_at.map(|archived_at| archived_at.timestamp()) == Some(archive.archived_at.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::interface::DeleteProjectOption;
+    use crate::application::usecase::delete_project;
+    use crate::domain::businesslogic::project_file::get_current_project_directory_path;
+    use crate::repositories::file_repository::{ProjectFileRepository, StatusHistoryFileRepository, TaskFileRepository, TimeEntryFileRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    struct Fixture {
+        _root: TestDataRoot,
+        project_repo: ProjectFileRepository,
+        task_repo: TaskFileRepository,
+        time_entry_repo: TimeEntryFileRepository,
+        status_history_repo: StatusHistoryFileRepository,
+    }
+
+    fn fixture() -> Fixture {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let status_history_repo = StatusHistoryFileRepository::new(&project_repo.get_self_status_history_file_path());
+        Fixture {
+            task_repo: TaskFileRepository::new(root.path()).unwrap(),
+            time_entry_repo: TimeEntryFileRepository::new(root.path()).unwrap(),
+            _root: root,
+            project_repo,
+            status_history_repo,
+        }
+    }
+
+    // キャンセルしたプロジェクトを追加し、フォルダにファイルを置く
+    fn add_cancelled_project(fixture: &Fixture, title: &str) -> String {
+        let id = fixture.project_repo.add(sample_project(title, sample_client("", "取引先"))).unwrap();
+        let mut project = fixture.project_repo.get(&id).unwrap().unwrap();
+        project.status = ProjectStatus::Cancelled;
+        fixture.project_repo.update(project).unwrap();
+        let project_path = get_current_project_directory_path(&fixture.project_repo.get(&id).unwrap().unwrap());
+        fs::write(project_path.join("documents").join("見積書.txt"), title).unwrap();
+        id
+    }
+
+    fn archive(fixture: &Fixture, id: &str) {
+        archive_project(&fixture.project_repo, &fixture.task_repo, &fixture.time_entry_repo, &fixture.status_history_repo, id, ArchiveProjectOption::RemoveFolder).unwrap();
+    }
+
+    #[test]
+    fn archive_and_unarchive_round_trips_folder() {
+        let fixture = fixture();
+        let id = add_cancelled_project(&fixture, "案件A");
+        let project_path = get_current_project_directory_path(&fixture.project_repo.get(&id).unwrap().unwrap());
+
+        archive(&fixture, &id);
+        let project = fixture.project_repo.get(&id).unwrap().unwrap();
+        assert!(project.archived_at.is_some());
+        assert!(!project_path.exists());
+        let archive = read_project_archive(&get_archive_file_path(&project)).unwrap();
+        assert_eq!(archive.project.title, "案件A");
+        assert_eq!(archive.status_history.len(), 2);
+        assert!(archive.includes_folder);
+
+        unarchive_project(&fixture.project_repo, &id).unwrap();
+        let project = fixture.project_repo.get(&id).unwrap().unwrap();
+        assert!(project.archived_at.is_none());
+        assert_eq!(fs::read_to_string(project_path.join("documents").join("見積書.txt")).unwrap(), "案件A");
+        assert!(project_path.join("works").is_dir());
+        assert!(!get_archive_file_path(&project).exists());
+    }
+
+    #[test]
+    fn unarchive_rejects_archive_of_another_project() {
+        let fixture = fixture();
+        let first = add_cancelled_project(&fixture, "案件A");
+        let second = add_cancelled_project(&fixture, "案件B");
+        archive(&fixture, &first);
+        archive(&fixture, &second);
+        let first_project = fixture.project_repo.get(&first).unwrap().unwrap();
+        let second_project = fixture.project_repo.get(&second).unwrap().unwrap();
+        fs::copy(get_archive_file_path(&second_project), get_archive_file_path(&first_project)).unwrap();
+
+        let result = unarchive_project(&fixture.project_repo, &first);
+
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        assert!(fixture.project_repo.get(&first).unwrap().unwrap().archived_at.is_some());
+        assert!(!get_current_project_directory_path(&first_project).exists());
+    }
+
+    #[test]
+    fn deleting_archived_project_removes_archive_file() {
+        let fixture = fixture();
+        let id = add_cancelled_project(&fixture, "案件A");
+        archive(&fixture, &id);
+        let archive_file_path = get_archive_file_path(&fixture.project_repo.get(&id).unwrap().unwrap());
+        assert!(archive_file_path.exists());
+
+        delete_project(&fixture.project_repo, &fixture.task_repo, &fixture.time_entry_repo, &id, DeleteProjectOption::RemoveFolder).unwrap();
+
+        assert!(!archive_file_path.exists());
+    }
+}
@@ -0,0 +1,22 @@
+use crate::{domain::{error::AppError, businesslogic::project_date::parse_optional_date, models::audit_log::AuditLogEntry}, repositories::repository_trait::AuditLogRepository};
+
+use super::interface::AuditLogQuery;
+
+/// 条件に合う監査ログを新しい順に返す
+pub fn fetch_audit_log(audit_log_repo: &dyn AuditLogRepository, query: &AuditLogQuery) -> Result<Vec<AuditLogEntry>, AppError> {
+    let date_from = parse_optional_date("date_from", query.date_from.as_deref())?;
+    let date_to = parse_optional_date("date_to", query.date_to.as_deref())?;
+    let mut entries = audit_log_repo
+        .fetch()?
+        .into_iter()
+        .filter(|entry| query.entity.is_none_or(|entity| entry.entity == entity))
+        .filter(|entry| query.entity_id.as_ref().is_none_or(|id| &entry.entity_id == id))
+        .filter(|entry| {
+            let date = entry.timestamp.date_naive();
+            date_from.is_none_or(|from| from <= date) && date_to.is_none_or(|to| date <= to)
+        })
+        .collect::<Vec<AuditLogEntry>>();
+    // 追記した順に並んでいるので、逆にすれば新しい順になる
+    entries.reverse();
+    Ok(entries)
+}
@@ -0,0 +1,43 @@
+use chrono::{Duration, Local};
+
+use crate::{domain::{error::AppError, models::{backup::{Backup, BackupReason}, settings::BackupSettings}}, repositories::{backup_repository::BackupFileRepository, settings_repository::SettingsFileRepository}};
+use crate::tr;
+
+/// 自動バックアップが有効で、前回のバックアップからinterval_hoursが経っていればバックアップを作る。
+/// 作った時はそのバックアップを返す
+pub fn run_scheduled_backup(backup_repo: &BackupFileRepository, settings: &BackupSettings) -> Result<Option<Backup>, AppError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let interval = Duration::hours(i64::from(settings.interval_hours));
+    let latest = backup_repo.list()?.into_iter().next();
+    if latest.is_some_and(|backup| Local::now() - backup.created_at < interval) {
+        return Ok(None);
+    }
+    create_backup(backup_repo, settings, BackupReason::Scheduled).map(Some)
+}
+
+/// 終了時のバックアップ。on_exitがfalseなら何もしない
+pub fn run_exit_backup(backup_repo: &BackupFileRepository, settings: &BackupSettings) -> Result<Option<Backup>, AppError> {
+    if !settings.enabled || !settings.on_exit {
+        return Ok(None);
+    }
+    create_backup(backup_repo, settings, BackupReason::Exit).map(Some)
+}
+
+/// バックアップを作り、keep_countを超えた古いバックアップを消す
+pub fn create_backup(backup_repo: &BackupFileRepository, settings: &BackupSettings, reason: BackupReason) -> Result<Backup, AppError> {
+    let backup = backup_repo.create(reason, settings.include_project_folders)?;
+    backup_repo.rotate(settings.keep_count)?;
+    Ok(backup)
+}
+
+/// バックアップの設定を確かめて保存する
+pub fn save_backup_settings(settings_repo: &SettingsFileRepository, backup_settings: BackupSettings) -> Result<(), AppError> {
+    if backup_settings.interval_hours == 0 || backup_settings.keep_count == 0 {
+        return Err(AppError::validation(tr!("backup.invalid_settings")));
+    }
+    let mut settings = settings_repo.load()?;
+    settings.backup = backup_settings;
+    settings_repo.save(&settings)
+}
@@ -0,0 +1,324 @@
+use std::sync::Mutex;
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::audit_log::{changed_fields, changed_project_fields}, businesslogic::edit_history::{Edit, EditHistory}, models::{audit_log::AuditEntity, client::Client, project::Project}}, repositories::{repository_trait::{Repository, UpdatedItem}, unit_of_work::UnitOfWork}};
+use crate::tr;
+
+use super::interface::UndoRedoState;
+
+/// プロジェクトの更新を元に戻せるように記録する。updatedはrepositoryが更新と一緒に読み込んだ前後のプロジェクト
+pub fn record_project_edit(history: &Mutex<EditHistory>, updated: UpdatedItem<Project>) -> Result<(), AppError> {
+    if changed_project_fields(&updated.before, &updated.after)?.is_empty() {
+        return Ok(());
+    }
+    history.lock()?.record(Edit::Project {
+        target: Box::new(updated.before),
+        expected: Box::new(updated.after),
+        created_client_id: updated.created_client_id,
+    });
+    Ok(())
+}
+
+/// クライアントの更新を元に戻せるように記録する。updatedはrepositoryが更新と一緒に読み込んだ前後のクライアント
+pub fn record_client_edit(history: &Mutex<EditHistory>, updated: UpdatedItem<Client>) -> Result<(), AppError> {
+    if changed_fields(&updated.before, &updated.after)?.is_empty() {
+        return Ok(());
+    }
+    history.lock()?.record(Edit::Client { target: Box::new(updated.before), expected: Box::new(updated.after) });
+    Ok(())
+}
+
+/// ゴミ箱・アーカイブ・フォルダのつなぎ直しなど、記録しない操作で変えたプロジェクトの履歴を捨てる
+pub fn forget_project_edits(history: &Mutex<EditHistory>, project_id: &str) -> Result<(), AppError> {
+    history.lock()?.forget(AuditEntity::Project, project_id);
+    Ok(())
+}
+
+/// 削除したクライアントの履歴を捨てる
+pub fn forget_client_edits(history: &Mutex<EditHistory>, client_id: &str) -> Result<(), AppError> {
+    history.lock()?.forget(AuditEntity::Client, client_id);
+    Ok(())
+}
+
+/// 最後の変更を取り消す。フォルダの名前を変えていれば、フォルダの名前も元に戻す
+pub fn undo(history: &Mutex<EditHistory>, project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>) -> Result<UndoRedoState, AppError> {
+    let mut history = history.lock()?;
+    let Some(edit) = history.pop_undo() else {
+        return Ok(to_state(&history, None));
+    };
+    match apply_edit(project_repo, client_repo, &edit) {
+        Ok(reverse) => {
+            history.push_redo(reverse);
+            Ok(to_state(&history, Some(&edit)))
+        }
+        Err(e) => {
+            if is_retryable(&e) {
+                history.push_undo(edit);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// 取り消した変更をもう一度行う
+pub fn redo(history: &Mutex<EditHistory>, project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>) -> Result<UndoRedoState, AppError> {
+    let mut history = history.lock()?;
+    let Some(edit) = history.pop_redo() else {
+        return Ok(to_state(&history, None));
+    };
+    match apply_edit(project_repo, client_repo, &edit) {
+        Ok(reverse) => {
+            history.push_undo(reverse);
+            Ok(to_state(&history, Some(&edit)))
+        }
+        Err(e) => {
+            if is_retryable(&e) {
+                history.push_redo(edit);
+            }
+            Err(e)
+        }
+    }
+}
+
+pub fn fetch_undo_redo_state(history: &Mutex<EditHistory>) -> Result<UndoRedoState, AppError> {
+    let history = history.lock()?;
+    Ok(to_state(&history, None))
+}
+
+/// 記録した状態を書き戻し、その逆の変更を返す。
+/// 変更した後に他で更新・削除されていたら、上書きしないようにエラーにする
+fn apply_edit(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, edit: &Edit) -> Result<Edit, AppError> {
+    match edit {
+        Edit::Project { target, expected, created_client_id } => {
+            let current = project_repo.get(&expected.id)?.ok_or_else(|| AppError::not_found(Entity::Project, &expected.id))?;
+            if !changed_project_fields(expected, &current)?.is_empty() {
+                return Err(AppError::conflict(Entity::Project, &expected.id));
+            }
+            // 読み込んだ後に他で更新されていたら、revisionが合わずにrevertが失敗する
+            let mut project = Project::clone(target);
+            project.revision = current.revision;
+            let mut unit_of_work = UnitOfWork::new();
+            let reverted = unit_of_work.run(
+                &tr!("step.revert_project"),
+                || project_repo.revert(project),
+                |reverted| {
+                    let mut project = current.clone();
+                    project.revision = reverted.after.revision;
+                    project_repo.revert(project).map(|_| ())
+                },
+            )?;
+            // 変更と一緒に追加したクライアントは、プロジェクトを書き戻した後に削除する
+            if let Some(client_id) = created_client_id {
+                unit_of_work.check(delete_unused_client(project_repo, client_repo, client_id))?;
+            }
+            unit_of_work.commit();
+            Ok(Edit::Project { target: Box::new(current), expected: Box::new(reverted.after), created_client_id: reverted.created_client_id })
+        }
+        Edit::Client { target, expected } => {
+            let current = client_repo.get(&expected.id)?.ok_or_else(|| AppError::not_found(Entity::Client, &expected.id))?;
+            if !changed_fields(expected.as_ref(), &current)?.is_empty() {
+                return Err(AppError::conflict(Entity::Client, &expected.id));
+            }
+            let reverted = client_repo.revert(Client::clone(target))?;
+            // 比べた後、書き戻す前に他で更新されていたら、書き戻す前の状態が比べたものと違う
+            if !changed_fields(&current, &reverted.before)?.is_empty() {
+                client_repo.revert(reverted.before)?;
+                return Err(AppError::conflict(Entity::Client, &expected.id));
+            }
+            Ok(Edit::Client { target: Box::new(current), expected: Box::new(reverted.after) })
+        }
+    }
+}
+
+/// クライアントを削除する。他のプロジェクトで使われるようになっていたり、既に削除されていたりすれば何もしない
+fn delete_unused_client(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, client_id: &str) -> Result<(), AppError> {
+    if project_repo.fetch()?.iter().any(|project| project.client.id == client_id) {
+        return Ok(());
+    }
+    match client_repo.delete(client_id) {
+        Err(AppError::NotFound { .. }) => Ok(()),
+        result => result,
+    }
+}
+
+/// 他で更新・削除されて書き戻せなくなった変更は履歴から捨てる。それ以外の失敗はもう一度試せるように残す
+fn is_retryable(e: &AppError) -> bool {
+    !matches!(e, AppError::Conflict { .. } | AppError::NotFound { .. })
+}
+
+fn to_state(history: &EditHistory, edit: Option<&Edit>) -> UndoRedoState {
+    UndoRedoState {
+        can_undo: history.can_undo(),
+        can_redo: history.can_redo(),
+        entity: edit.map(Edit::entity),
+        entity_id: edit.map(|edit| edit.entity_id().to_string()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::usecase::{delete_project, update_project};
+    use crate::application::interface::DeleteProjectOption;
+    use crate::repositories::file_repository::{ClientFileRepository, ProjectFileRepository, TaskFileRepository, TimeEntryFileRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    struct Fixture {
+        root: TestDataRoot,
+        project_repo: ProjectFileRepository,
+        client_repo: ClientFileRepository,
+        history: Mutex<EditHistory>,
+    }
+
+    fn fixture() -> Fixture {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let client_repo = ClientFileRepository::new(&project_repo.get_self_client_file_path());
+        Fixture { root, project_repo, client_repo, history: Mutex::new(EditHistory::default()) }
+    }
+
+    impl Fixture {
+        // プロジェクトの題名を変えて、履歴に記録する
+        fn rename(&self, id: &str, title: &str) {
+            let mut project = self.project_repo.get(id).unwrap().unwrap();
+            project.title = title.to_string();
+            let updated = update_project(&self.project_repo, project).unwrap();
+            record_project_edit(&self.history, updated).unwrap();
+        }
+
+        fn title(&self, id: &str) -> String {
+            self.project_repo.get(id).unwrap().unwrap().title
+        }
+
+        fn undo(&self) -> Result<UndoRedoState, AppError> {
+            undo(&self.history, &self.project_repo, &self.client_repo)
+        }
+
+        fn redo(&self) -> Result<UndoRedoState, AppError> {
+            redo(&self.history, &self.project_repo, &self.client_repo)
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_project_edit() {
+        let fixture = fixture();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        fixture.rename(&id, "案件B");
+
+        let state = fixture.undo().unwrap();
+        assert_eq!(fixture.title(&id), "案件A");
+        assert!(!state.can_undo && state.can_redo);
+
+        let state = fixture.redo().unwrap();
+        assert_eq!(fixture.title(&id), "案件B");
+        assert!(state.can_undo && !state.can_redo);
+    }
+
+    #[test]
+    fn undo_after_other_update_is_a_conflict() {
+        let fixture = fixture();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        fixture.rename(&id, "案件B");
+        // 記録していない更新(他の画面など)
+        let mut project = fixture.project_repo.get(&id).unwrap().unwrap();
+        project.description = "他で変更".to_string();
+        fixture.project_repo.update(project).unwrap();
+
+        assert_eq!(fixture.undo().unwrap_err(), AppError::conflict(Entity::Project, &id));
+
+        // 上書きせず、書き戻せない変更は履歴から捨てる
+        assert_eq!(fixture.title(&id), "案件B");
+        let state = fetch_undo_redo_state(&fixture.history).unwrap();
+        assert!(!state.can_undo && !state.can_redo);
+    }
+
+    #[test]
+    fn redo_after_other_update_is_a_conflict() {
+        let fixture = fixture();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        fixture.rename(&id, "案件B");
+        fixture.undo().unwrap();
+        let mut project = fixture.project_repo.get(&id).unwrap().unwrap();
+        project.description = "他で変更".to_string();
+        fixture.project_repo.update(project).unwrap();
+
+        assert_eq!(fixture.redo().unwrap_err(), AppError::conflict(Entity::Project, &id));
+        assert_eq!(fixture.title(&id), "案件A");
+        assert!(!fetch_undo_redo_state(&fixture.history).unwrap().can_redo);
+    }
+
+    #[test]
+    fn undo_removes_client_created_by_the_edit() {
+        let fixture = fixture();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut project = fixture.project_repo.get(&id).unwrap().unwrap();
+        project.client = sample_client("", "新しい取引先");
+        let updated = update_project(&fixture.project_repo, project).unwrap();
+        let created_client_id = updated.created_client_id.clone().unwrap();
+        record_project_edit(&fixture.history, updated).unwrap();
+
+        fixture.undo().unwrap();
+        assert_eq!(fixture.project_repo.get(&id).unwrap().unwrap().client.name, "取引先");
+        assert!(fixture.client_repo.get(&created_client_id).unwrap().is_none());
+
+        // やり直すとクライアントも追加し直す
+        fixture.redo().unwrap();
+        let project = fixture.project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(project.client.name, "新しい取引先");
+        assert!(fixture.client_repo.get(&project.client.id).unwrap().is_some());
+        fixture.undo().unwrap();
+        assert_eq!(fixture.client_repo.fetch().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn created_client_used_by_another_project_is_kept() {
+        let fixture = fixture();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut project = fixture.project_repo.get(&id).unwrap().unwrap();
+        project.client = sample_client("", "新しい取引先");
+        let updated = update_project(&fixture.project_repo, project).unwrap();
+        let created_client_id = updated.created_client_id.clone().unwrap();
+        record_project_edit(&fixture.history, updated).unwrap();
+        let created_client = fixture.client_repo.get(&created_client_id).unwrap().unwrap();
+        fixture.project_repo.add(sample_project("案件B", created_client)).unwrap();
+
+        fixture.undo().unwrap();
+
+        assert!(fixture.client_repo.get(&created_client_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn trash_forgets_project_edits() {
+        let fixture = fixture();
+        let task_repo = TaskFileRepository::new(fixture.root.path()).unwrap();
+        let time_entry_repo = TimeEntryFileRepository::new(fixture.root.path()).unwrap();
+        let id = fixture.project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let other_id = fixture.project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap();
+        fixture.rename(&id, "案件A2");
+        fixture.rename(&other_id, "案件B2");
+
+        delete_project(&fixture.project_repo, &task_repo, &time_entry_repo, &id, DeleteProjectOption::MoveToTrash).unwrap();
+        forget_project_edits(&fixture.history, &id).unwrap();
+
+        // 他のプロジェクトの変更だけが残る
+        let state = fixture.undo().unwrap();
+        assert_eq!(state.entity_id.as_deref(), Some(other_id.as_str()));
+        assert!(!state.can_undo);
+        assert_eq!(fixture.title(&id), "案件A2");
+        assert!(fixture.project_repo.get(&id).unwrap().unwrap().deleted_at.is_some());
+    }
+
+    #[test]
+    fn undo_client_edit_after_other_update_is_a_conflict() {
+        let fixture = fixture();
+        let id = fixture.client_repo.add(sample_client("", "取引先")).unwrap();
+        let mut client = fixture.client_repo.get(&id).unwrap().unwrap();
+        client.name = "新しい名前".to_string();
+        record_client_edit(&fixture.history, fixture.client_repo.update_with_history(client.clone()).unwrap()).unwrap();
+        client.notes = "他で変更".to_string();
+        fixture.client_repo.update(client).unwrap();
+
+        assert_eq!(fixture.undo().unwrap_err(), AppError::conflict(Entity::Client, &id));
+        assert_eq!(fixture.client_repo.get(&id).unwrap().unwrap().name, "新しい名前");
+    }
+}
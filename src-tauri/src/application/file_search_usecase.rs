@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use walkdir::WalkDir;
+
+use crate::{domain::{error::AppError, businesslogic::project_file::{get_current_project_directory_path}, businesslogic::search_index::{FileKey, FileState, IndexedFile, SearchIndex}, businesslogic::text_extraction::extract_text, models::project::Project}, repositories::repository_trait::Repository};
+
+use super::interface::FileSearchHit;
+use super::usecase::fetch_active_projects;
+
+/// 索引を作るプロジェクトのフォルダ
+pub struct IndexTarget {
+    project_id: String,
+    project_directory: PathBuf,
+}
+
+/// 索引に反映する変更
+#[derive(Default)]
+pub struct IndexChanges {
+    updated: Vec<IndexedFile>,
+    removed: Vec<FileKey>,
+}
+
+impl IndexChanges {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn apply(self, index: &mut SearchIndex) {
+        for (project_id, relative_path) in self.removed {
+            index.remove(&project_id, &relative_path);
+        }
+        for file in self.updated {
+            index.upsert(file);
+        }
+    }
+}
+
+/// ゴミ箱以外のプロジェクトのフォルダを返す
+pub fn collect_index_targets(project_repo: &dyn Repository<Project>) -> Result<Vec<IndexTarget>, AppError> {
+    Ok(fetch_active_projects(project_repo)?
+        .iter()
+        .map(|project| IndexTarget {
+            project_id: project.id.clone(),
+            project_directory: get_current_project_directory_path(project),
+        })
+        .collect())
+}
+
+/// プロジェクトのフォルダを見て回り、索引と比べて追加・更新・削除されたファイルを調べる。
+/// 中身の読み込みに時間がかかるので、repositoryや索引のロックを持たずに呼ぶ。
+pub fn scan_index_changes(targets: &[IndexTarget], known: &HashMap<FileKey, FileState>) -> IndexChanges {
+    let mut changes = IndexChanges::default();
+    let mut found = HashSet::new();
+    for target in targets {
+        let entries = WalkDir::new(&target.project_directory)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.file_name().to_str().unwrap_or("")))
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file());
+        for entry in entries {
+            let Some(relative_path) = relative_path(&target.project_directory, entry.path()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let state = (modified_seconds(&metadata), metadata.len());
+            let key = (target.project_id.clone(), relative_path);
+            if known.get(&key) != Some(&state) {
+                // 読めない形式や壊れたファイルも空の本文で登録し、次回は読み直さないようにする
+                let text = extract_text(entry.path()).ok().flatten();
+                changes.updated.push(IndexedFile {
+                    project_id: key.0.clone(),
+                    relative_path: key.1.clone(),
+                    modified: state.0,
+                    size: state.1,
+                    text: text.unwrap_or_default(),
+                });
+            }
+            found.insert(key);
+        }
+    }
+    changes.removed = known.keys().filter(|key| !found.contains(*key)).cloned().collect();
+    changes
+}
+
+/// 索引からファイルを検索する。語が多く出てくるファイルから順に、limit件まで返す
+pub fn search_files(index: &SearchIndex, query: &str, limit: usize) -> Vec<FileSearchHit> {
+    let mut found = index.search(query);
+    // 出てくる回数が同じ時はプロジェクトとパスで並べて、検索のたびに順番が変わらないようにする
+    found.sort_by(|a, b| {
+        b.hit_count
+            .cmp(&a.hit_count)
+            .then_with(|| a.file.project_id.cmp(&b.file.project_id))
+            .then_with(|| a.file.relative_path.cmp(&b.file.relative_path))
+    });
+    found
+        .into_iter()
+        .take(limit)
+        .map(|found| FileSearchHit {
+            project_id: found.file.project_id.clone(),
+            relative_path: found.file.relative_path.clone(),
+            snippet: found.snippet,
+        })
+        .collect()
+}
+
+// .DS_Storeなどの隠しファイルと、Officeが開いている間に作る~$のファイルは除く
+fn is_hidden(file_name: &str) -> bool {
+    file_name.starts_with('.') || file_name.starts_with("~$")
+}
+
+fn relative_path(project_directory: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(project_directory).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    Some(components.join("/"))
+}
+
+fn modified_seconds(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(project_id: &str, relative_path: &str, text: &str) -> IndexedFile {
+        IndexedFile {
+            project_id: project_id.to_string(),
+            relative_path: relative_path.to_string(),
+            modified: 0,
+            size: 0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn search_files_ranks_by_hits_before_limit() {
+        let mut index = SearchIndex::default();
+        index.upsert(file("1", "a.txt", "ロゴ"));
+        index.upsert(file("1", "b.txt", "ロゴ ロゴ ロゴ"));
+        index.upsert(file("2", "c.txt", "ロゴ ロゴ"));
+        index.upsert(file("2", "d.txt", "ロゴ ロゴ"));
+
+        let hits = search_files(&index, "ロゴ", 3);
+        let paths = hits.iter().map(|hit| hit.relative_path.as_str()).collect::<Vec<&str>>();
+        assert_eq!(paths, vec!["b.txt", "c.txt", "d.txt"]);
+    }
+
+    #[test]
+    fn scan_finds_added_changed_and_removed_files() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::create_dir(directory.path().join("資料")).unwrap();
+        std::fs::write(directory.path().join("資料").join("memo.txt"), "打ち合わせメモ").unwrap();
+        std::fs::write(directory.path().join(".DS_Store"), "").unwrap();
+        // 壊れたPDFは空の本文で登録する
+        std::fs::write(directory.path().join("broken.pdf"), "not a pdf").unwrap();
+        let targets = vec![IndexTarget { project_id: "1".to_string(), project_directory: directory.path().to_path_buf() }];
+
+        let mut index = SearchIndex::default();
+        let changes = scan_index_changes(&targets, &index.file_states());
+        changes.apply(&mut index);
+        let mut paths = index.file_states().into_keys().map(|(_, path)| path).collect::<Vec<String>>();
+        paths.sort();
+        assert_eq!(paths, vec!["broken.pdf", "資料/memo.txt"]);
+        assert_eq!(search_files(&index, "メモ", 10).len(), 1);
+
+        // 変わっていなければ何もしない
+        assert!(scan_index_changes(&targets, &index.file_states()).is_empty());
+
+        std::fs::remove_file(directory.path().join("broken.pdf")).unwrap();
+        let changes = scan_index_changes(&targets, &index.file_states());
+        changes.apply(&mut index);
+        assert_eq!(index.file_states().len(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{domain::{error::{AppError, Entity}, businesslogic::folder_template::{default_folder_template, is_default_folder_template, validate_folder_template}, models::{folder_template::FolderTemplate, project::Project}}, repositories::repository_trait::Repository};
+use crate::tr;
+
+/// 標準のテンプレートを先頭にして、テンプレートの一覧を返す
+pub fn fetch_folder_templates(template_repo: &dyn Repository<FolderTemplate>) -> Result<Vec<FolderTemplate>, AppError> {
+    let mut templates = vec![default_folder_template()];
+    templates.extend(template_repo.fetch()?);
+    Ok(templates)
+}
+
+/// テンプレートを保存する。idが空なら追加し、保存したテンプレートを返す。
+/// プロジェクトのフォルダを削除する時はテンプレートから作ったものを探すので、使用中のテンプレートは名前しか変えられない
+pub fn save_folder_template(project_repo: &dyn Repository<Project>, template_repo: &dyn Repository<FolderTemplate>, mut template: FolderTemplate) -> Result<FolderTemplate, AppError> {
+    if !template.id.is_empty() && is_default_folder_template(&template.id) {
+        return Err(AppError::validation(tr!("folder_template.builtin")));
+    }
+    template.name = template.name.trim().to_string();
+    validate_folder_template(&template)?;
+    if template.id.is_empty() {
+        template.id = template_repo.add(template.clone())?;
+        return Ok(template);
+    }
+
+    let saved = template_repo
+        .get(&template.id)?
+        .ok_or_else(|| AppError::not_found(Entity::FolderTemplate, &template.id))?;
+    if saved.directories != template.directories || saved.files != template.files {
+        let referenced_titles = referenced_project_titles(project_repo, &template.id)?;
+        if !referenced_titles.is_empty() {
+            return Err(AppError::validation(tr!("folder_template.in_use_edit", referenced_titles.join(", "))));
+        }
+    }
+    template_repo.update(template.clone())?;
+    Ok(template)
+}
+
+/// テンプレートを削除する。プロジェクトが使っているテンプレートは、フォルダを削除する時に必要なので削除しない
+pub fn delete_folder_template(project_repo: &dyn Repository<Project>, template_repo: &dyn Repository<FolderTemplate>, id: &str) -> Result<(), AppError> {
+    if is_default_folder_template(id) {
+        return Err(AppError::validation(tr!("folder_template.builtin")));
+    }
+    let referenced_titles = referenced_project_titles(project_repo, id)?;
+    if !referenced_titles.is_empty() {
+        return Err(AppError::validation(tr!("folder_template.in_use", referenced_titles.join(", "))));
+    }
+    template_repo.delete(id)
+}
+
+// ゴミ箱やアーカイブのプロジェクトも、戻す時や削除する時にテンプレートを使うので含める
+fn referenced_project_titles(project_repo: &dyn Repository<Project>, id: &str) -> Result<Vec<String>, AppError> {
+    Ok(project_repo
+        .fetch()?
+        .into_iter()
+        .filter(|project| project.template_id.as_deref() == Some(id))
+        .map(|project| project.title)
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::file_repository::{FolderTemplateFileRepository, ProjectFileRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn template(name: &str, directories: &[&str]) -> FolderTemplate {
+        FolderTemplate {
+            id: String::new(),
+            name: name.to_string(),
+            directories: directories.iter().map(|directory| directory.to_string()).collect(),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn template_in_use_can_only_be_renamed() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let template_repo = FolderTemplateFileRepository::new(root.path()).unwrap();
+        let saved = save_folder_template(&project_repo, &template_repo, template(" デザイン ", &["資料"])).unwrap();
+        assert_eq!(saved.name, "デザイン");
+        let mut project = sample_project("案件A", sample_client("", "取引先"));
+        project.template_id = Some(saved.id.clone());
+        project_repo.add(project).unwrap();
+
+        let mut renamed = saved.clone();
+        renamed.name = "デザイン案件".to_string();
+        save_folder_template(&project_repo, &template_repo, renamed).unwrap();
+
+        let mut edited = saved.clone();
+        edited.directories.push("納品".to_string());
+        let result = save_folder_template(&project_repo, &template_repo, edited);
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        let stored = template_repo.get(&saved.id).unwrap().unwrap();
+        assert_eq!(stored.name, "デザイン案件");
+        assert_eq!(stored.directories, vec!["資料"]);
+
+        let result = delete_folder_template(&project_repo, &template_repo, &saved.id);
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn unused_template_can_be_edited_and_deleted() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let template_repo = FolderTemplateFileRepository::new(root.path()).unwrap();
+        let mut saved = save_folder_template(&project_repo, &template_repo, template("デザイン", &["資料"])).unwrap();
+
+        saved.directories = vec!["納品".to_string()];
+        save_folder_template(&project_repo, &template_repo, saved.clone()).unwrap();
+        assert_eq!(template_repo.get(&saved.id).unwrap().unwrap().directories, vec!["納品"]);
+
+        delete_folder_template(&project_repo, &template_repo, &saved.id).unwrap();
+        assert!(template_repo.get(&saved.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn builtin_template_cannot_be_changed() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let template_repo = FolderTemplateFileRepository::new(root.path()).unwrap();
+        let result = save_folder_template(&project_repo, &template_repo, default_folder_template());
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        assert_eq!(fetch_folder_templates(&template_repo).unwrap().len(), 1);
+    }
+}
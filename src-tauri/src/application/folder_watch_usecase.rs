@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{domain::{error::AppError, businesslogic::project_file::get_project_manage_path, models::project::Project}, repositories::repository_trait::Repository};
+use crate::tr;
+
+use super::interface::ProjectFolderReport;
+use super::project_folder_sync_usecase::check_project_folders;
+
+/// まとめて届くフォルダの変更を待つ時間
+const FOLDER_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+/// データフォルダの変更に気づくまでの間隔
+const FOLDER_WATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// プロジェクト管理フォルダを監視する。変更が落ち着いたら、届いたイベントをon_eventsに渡す。
+/// 監視の途中のエラーはon_errorに渡して監視を続ける。監視を始められなかった時だけエラーを返す
+pub fn watch_project_folders(mut on_events: impl FnMut(&Path, &[Event]), on_error: impl Fn(AppError)) -> Result<(), AppError> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|e| AppError::io(tr!("folder_watch.start_failed", e)))?;
+    let mut watched_path: Option<PathBuf> = None;
+    loop {
+        // データフォルダが変わったら、新しいプロジェクト管理フォルダを監視する
+        let manage_path = get_project_manage_path();
+        if watched_path.as_ref() != Some(&manage_path) {
+            if let Some(old_path) = watched_path.take() {
+                let _ = watcher.unwatch(&old_path);
+            }
+            if let Err(e) = watcher.watch(&manage_path, RecursiveMode::NonRecursive) {
+                on_error(AppError::io(tr!("folder_watch.watch_failed", manage_path.display(), e)));
+            }
+            watched_path = Some(manage_path.clone());
+        }
+
+        let mut events = match receiver.recv_timeout(FOLDER_WATCH_POLL_INTERVAL) {
+            Ok(Ok(event)) => vec![event],
+            Ok(Err(e)) => {
+                on_error(AppError::io(tr!("folder_watch.event_failed", e)));
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        // 名前の変更などはいくつかのイベントに分かれて届くので、落ち着いてから一度だけ確かめる
+        while let Ok(result) = receiver.recv_timeout(FOLDER_WATCH_DEBOUNCE) {
+            events.extend(result.ok());
+        }
+        on_events(&manage_path, &events);
+    }
+}
+
+/// 監視のイベントからプロジェクトのフォルダの変更を拾う。プロジェクトのフォルダに関係なければNoneを返す。
+/// renamesには見つけたフォルダの名前の変更(元の名前 -> 新しい名前)を足していく
+pub fn handle_folder_events(project_repo: &dyn Repository<Project>, renames: &mut HashMap<String, String>, manage_path: &Path, events: &[Event]) -> Result<Option<ProjectFolderReport>, AppError> {
+    // プロジェクトのフォルダの中のファイルの変更や、ゴミ箱などの隠しフォルダは無視する
+    let folder_name = |path: &Path| {
+        path.parent()
+            .filter(|parent| *parent == manage_path)
+            .and_then(|_| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .filter(|name| !name.starts_with('.'))
+    };
+    let relevant = events.iter().any(|event| event.paths.iter().any(|path| folder_name(path).is_some()));
+    if !relevant {
+        return Ok(None);
+    }
+
+    for event in events {
+        if let (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) = (&event.kind, event.paths.as_slice()) {
+            if let (Some(from), Some(to)) = (folder_name(from), folder_name(to)) {
+                // 続けて名前を変えた時は、最初の名前から最後の名前をたどれるようにする
+                for renamed in renames.values_mut().filter(|renamed| **renamed == from) {
+                    *renamed = to.clone();
+                }
+                renames.insert(from, to);
+            }
+        }
+    }
+    check_project_folders(project_repo, renames).map(Some)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use notify::event::CreateKind;
+
+    use super::*;
+    use crate::repositories::file_repository::ProjectFileRepository;
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn rename_event(from: PathBuf, to: PathBuf) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both))).add_path(from).add_path(to)
+    }
+
+    #[test]
+    fn ignores_changes_inside_project_folders_and_hidden_folders() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let manage_path = get_project_manage_path();
+        let mut renames = HashMap::new();
+        let events = vec![
+            Event::new(EventKind::Create(CreateKind::File)).add_path(manage_path.join("1").join("メモ.txt")),
+            Event::new(EventKind::Create(CreateKind::Folder)).add_path(manage_path.join(".trash")),
+        ];
+
+        assert!(handle_folder_events(&project_repo, &mut renames, &manage_path, &events).unwrap().is_none());
+    }
+
+    #[test]
+    fn follows_renamed_project_folder() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let manage_path = get_project_manage_path();
+        // 2回続けて名前を変える
+        fs::rename(manage_path.join(&id), manage_path.join("案件A")).unwrap();
+        fs::rename(manage_path.join("案件A"), manage_path.join("案件A_完了")).unwrap();
+        let events = vec![
+            rename_event(manage_path.join(&id), manage_path.join("案件A")),
+            rename_event(manage_path.join("案件A"), manage_path.join("案件A_完了")),
+        ];
+        let mut renames = HashMap::new();
+
+        let report = handle_folder_events(&project_repo, &mut renames, &manage_path, &events).unwrap().unwrap();
+
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].project_id, id);
+        assert_eq!(report.missing[0].rename_candidate.as_deref(), Some("案件A_完了"));
+        assert_eq!(report.unlinked_folders, vec!["案件A_完了"]);
+        assert_eq!(renames.get(&id).map(String::as_str), Some("案件A_完了"));
+    }
+}
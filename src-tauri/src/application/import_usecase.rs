@@ -0,0 +1,372 @@
+use std::{collections::{HashMap, HashSet}, path::Path};
+
+use crate::{domain::{error::AppError, businesslogic::{folder_template::resolve_folder_template, import_table::{read_import_table, ImportTable, TableRow}, project_date::{parse_date, parse_optional_date, validate_project_dates}, project_file::{get_project_directory_path, validate_file_name}, project_status::parse_status_label}, models::{client::{BillingDetails, Client}, folder_template::FolderTemplate, project::{Project, ProjectStatus}}}, repositories::repository_trait::Repository};
+use crate::tr;
+
+use super::interface::{ImportColumnMapping, ImportFileInfo, ImportPreview, ImportRequest, ImportRowPreview, ImportSummary};
+use super::usecase::convert_project_to_frontend;
+
+/// 読み込んだ1行分。エラーがなければprojectが入る
+struct ImportRow {
+    row_number: usize,
+    project: Option<Project>,
+    client_key: Option<String>, // 新しく作るクライアントの時だけ入る
+    errors: Vec<String>,
+}
+
+/// 取り込みの準備ができた行と、新しく作るクライアント
+struct PreparedImport {
+    rows: Vec<ImportRow>,
+    new_clients: Vec<Client>, // 名前の比較用のキーの順に並ぶ
+    new_client_keys: Vec<String>,
+}
+
+/// 列の番号。必須でない項目は列がなければNone
+struct ColumnIndexes {
+    title: usize,
+    description: Option<usize>,
+    order_date: usize,
+    due_date: usize,
+    completion_date: Option<usize>,
+    status: Option<usize>,
+    client_name: usize,
+    contact_person: Option<usize>,
+    folder_path_suffix: Option<usize>,
+}
+
+/// ファイルのシート名と列の見出しを返す。列の対応を選ぶ画面で使う
+pub fn read_import_file(file_path: &str, sheet_name: Option<&str>) -> Result<ImportFileInfo, AppError> {
+    let table = read_import_table(Path::new(file_path), sheet_name)?;
+    Ok(ImportFileInfo {
+        row_count: table.rows.len(),
+        sheet_names: table.sheet_names,
+        columns: table.columns,
+    })
+}
+
+/// ファイルを読み、取り込んだ時のプロジェクトと行ごとのエラーを返す。何も保存しない
+pub fn preview_import(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, folder_template_repo: &dyn Repository<FolderTemplate>, request: &ImportRequest) -> Result<ImportPreview, AppError> {
+    let prepared = prepare_import(project_repo, client_repo, folder_template_repo, request)?;
+    let error_count = prepared.rows.iter().filter(|row| !row.errors.is_empty()).count();
+    let rows = prepared
+        .rows
+        .into_iter()
+        .map(|row| ImportRowPreview {
+            row_number: row.row_number,
+            new_client: row.client_key.is_some(),
+            project: row.project.map(|project| convert_project_to_frontend(project, None)),
+            errors: row.errors,
+        })
+        .collect::<Vec<_>>();
+    Ok(ImportPreview {
+        valid_count: rows.len() - error_count,
+        error_count,
+        rows,
+        new_clients: prepared.new_clients.into_iter().map(|client| client.name).collect(),
+    })
+}
+
+/// ファイルのプロジェクトをまとめて取り込む。
+/// エラーのある行が1つでもあれば何もしない。repositoryがロックを持ったまままとめて追加し、
+/// 途中で失敗したら、作ったクライアント・プロジェクト・フォルダと監査ログをすべて取り消す
+pub fn import_projects(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, folder_template_repo: &dyn Repository<FolderTemplate>, request: &ImportRequest) -> Result<ImportSummary, AppError> {
+    let prepared = prepare_import(project_repo, client_repo, folder_template_repo, request)?;
+    let error_count = prepared.rows.iter().filter(|row| !row.errors.is_empty()).count();
+    if error_count > 0 {
+        return Err(AppError::validation(tr!("import.has_errors", error_count)));
+    }
+    if prepared.rows.is_empty() {
+        return Err(AppError::validation(tr!("import.no_rows")));
+    }
+
+    // 同じ新しいクライアントの行には、同じクライアントを入れておく。repositoryは同じ名前の新しいクライアントを1つだけ追加する
+    let new_clients: HashMap<String, Client> = prepared.new_client_keys.into_iter().zip(prepared.new_clients).collect();
+    let mut client_keys = Vec::new();
+    let mut projects = Vec::new();
+    for row in prepared.rows {
+        let Some(mut project) = row.project else { continue };
+        if let Some(client) = row.client_key.as_ref().and_then(|key| new_clients.get(key)) {
+            project.client = client.clone();
+        }
+        client_keys.push(row.client_key);
+        projects.push(project);
+    }
+    let project_ids = project_repo.add_all(projects)?;
+
+    // 作ったクライアントを、そのクライアントの最初のプロジェクトから読み込む
+    let mut created_clients: Vec<Client> = Vec::new();
+    for (project_id, client_key) in project_ids.iter().zip(client_keys) {
+        if client_key.is_none() {
+            continue;
+        }
+        let Some(project) = project_repo.get(project_id)? else { continue };
+        if !created_clients.iter().any(|client| client.id == project.client.id) {
+            created_clients.push(project.client);
+        }
+    }
+    Ok(ImportSummary { project_ids, created_clients })
+}
+
+/// ファイルを読み、行ごとにプロジェクトを作って確かめる
+fn prepare_import(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, folder_template_repo: &dyn Repository<FolderTemplate>, request: &ImportRequest) -> Result<PreparedImport, AppError> {
+    // テンプレートがなければ1行も取り込めないので、行を見る前に確かめる
+    resolve_folder_template(folder_template_repo.fetch()?, request.template_id.as_deref())?;
+    let table = read_import_table(Path::new(&request.file_path), request.sheet_name.as_deref())?;
+    let columns = resolve_columns(&table, &request.mapping)?;
+
+    let clients: HashMap<String, Client> = client_repo
+        .fetch()?
+        .into_iter()
+        .map(|client| (client_key(&client.name), client))
+        .collect();
+    let mut used_folder_names: HashSet<String> = project_repo
+        .fetch()?
+        .into_iter()
+        .filter_map(|project| project.folder_path_suffix)
+        .collect();
+
+    let mut prepared = PreparedImport { rows: Vec::new(), new_clients: Vec::new(), new_client_keys: Vec::new() };
+    for row in &table.rows {
+        let mut import_row = ImportRow { row_number: row.row_number, project: None, client_key: None, errors: Vec::new() };
+        match parse_row(row, &columns, request) {
+            Ok(mut project) => {
+                if let Err(e) = check_folder_name(&project, &mut used_folder_names) {
+                    import_row.errors.push(e.to_string());
+                }
+                let key = client_key(&project.client.name);
+                match clients.get(&key) {
+                    Some(client) => project.client = client.clone(),
+                    None => {
+                        if !prepared.new_client_keys.contains(&key) {
+                            prepared.new_client_keys.push(key.clone());
+                            prepared.new_clients.push(project.client.clone());
+                        }
+                        import_row.client_key = Some(key);
+                    }
+                }
+                if import_row.errors.is_empty() {
+                    import_row.project = Some(project);
+                }
+            }
+            Err(errors) => import_row.errors = errors,
+        }
+        prepared.rows.push(import_row);
+    }
+    Ok(prepared)
+}
+
+/// 指定された見出しを列の番号にする。必須の項目が指定されていないか、ファイルにない見出しならエラー
+fn resolve_columns(table: &ImportTable, mapping: &ImportColumnMapping) -> Result<ColumnIndexes, AppError> {
+    let optional = |column: &Option<String>| -> Result<Option<usize>, AppError> {
+        match column.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(column) => table
+                .column_index(column)
+                .map(Some)
+                .ok_or_else(|| AppError::validation(tr!("import.column_not_found", column))),
+        }
+    };
+    let required = |field: &str, column: &Option<String>| -> Result<usize, AppError> {
+        optional(column)?.ok_or_else(|| AppError::validation(tr!("import.column_required", field)))
+    };
+    Ok(ColumnIndexes {
+        title: required("title", &mapping.title)?,
+        description: optional(&mapping.description)?,
+        order_date: required("order_date", &mapping.order_date)?,
+        due_date: required("due_date", &mapping.due_date)?,
+        completion_date: optional(&mapping.completion_date)?,
+        status: optional(&mapping.status)?,
+        client_name: required("client_name", &mapping.client_name)?,
+        contact_person: optional(&mapping.contact_person)?,
+        folder_path_suffix: optional(&mapping.folder_path_suffix)?,
+    })
+}
+
+/// 1行をプロジェクトにする。行のエラーはすべて集めて返す
+fn parse_row(row: &TableRow, columns: &ColumnIndexes, request: &ImportRequest) -> Result<Project, Vec<String>> {
+    let value = |index: usize| row.values.get(index).map(|value| value.trim()).unwrap_or("");
+    let optional_value = |index: Option<usize>| index.map(value).filter(|value| !value.is_empty());
+    let mut errors = Vec::new();
+
+    let title = value(columns.title);
+    if title.is_empty() {
+        errors.push(tr!("import.required_value", "title"));
+    }
+    let client_name = value(columns.client_name);
+    if client_name.is_empty() {
+        errors.push(tr!("import.required_value", "client_name"));
+    }
+    let order_date = parse_date("order_date", value(columns.order_date)).map_err(|e| errors.push(e.to_string())).ok();
+    let due_date = parse_date("due_date", value(columns.due_date)).map_err(|e| errors.push(e.to_string())).ok();
+    let completion_date = parse_optional_date("completion_date", optional_value(columns.completion_date))
+        .map_err(|e| errors.push(e.to_string()))
+        .ok()
+        .flatten();
+    let status = match optional_value(columns.status) {
+        None => Some(ProjectStatus::NotStarted),
+        Some(label) => {
+            let status = parse_status_label(label);
+            if status.is_none() {
+                errors.push(tr!("import.invalid_status", label));
+            }
+            status
+        }
+    };
+    let folder_path_suffix = optional_value(columns.folder_path_suffix).map(str::to_string);
+    if let Some(Err(e)) = folder_path_suffix.as_deref().map(validate_file_name) {
+        errors.push(e.to_string());
+    }
+
+    let (Some(order_date), Some(due_date), Some(status)) = (order_date, due_date, status) else {
+        return Err(errors);
+    };
+    let project = Project {
+        id: String::new(),
+        title: title.to_string(),
+        description: optional_value(columns.description).unwrap_or("").to_string(),
+        order_date,
+        due_date,
+        completion_date,
+        client: Client {
+            id: String::new(),
+            name: client_name.to_string(),
+            contact_person: optional_value(columns.contact_person).unwrap_or("").to_string(),
+            email: String::new(),
+            phone: String::new(),
+            postal_code: String::new(),
+            address: String::new(),
+            billing: BillingDetails::default(),
+            notes: String::new(),
+            contacts: Vec::new(),
+        },
+        status,
+        folder_path_suffix,
+        deleted_at: None,
+        archived_at: None,
+        revision: 0,
+        template_id: request.template_id.clone(),
+    };
+    if let Err(e) = validate_project_dates(&project) {
+        errors.push(e.to_string());
+    }
+    if errors.is_empty() {
+        Ok(project)
+    } else {
+        Err(errors)
+    }
+}
+
+/// フォルダ名が既存のプロジェクトやファイル内の他の行と重なっていないか確かめ、使った名前を覚えておく
+fn check_folder_name(project: &Project, used_folder_names: &mut HashSet<String>) -> Result<(), AppError> {
+    let Some(folder_path_suffix) = project.folder_path_suffix.as_deref() else {
+        return Ok(());
+    };
+    if !used_folder_names.insert(folder_path_suffix.to_string()) || get_project_directory_path(folder_path_suffix).exists() {
+        return Err(AppError::validation(tr!("import.folder_in_use", folder_path_suffix)));
+    }
+    Ok(())
+}
+
+/// クライアント名の比較に使うキー。前後の空白と大文字小文字を無視する
+fn client_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::audit_log::AuditEntity;
+    use crate::repositories::file_repository::{AuditLogFileRepository, ClientFileRepository, FolderTemplateFileRepository, ProjectFileRepository};
+    use crate::repositories::repository_trait::AuditLogRepository;
+    use crate::repositories::test_support::TestDataRoot;
+
+    struct Fixture {
+        root: TestDataRoot,
+        project_repo: ProjectFileRepository,
+        client_repo: ClientFileRepository,
+        folder_template_repo: FolderTemplateFileRepository,
+        audit_log: AuditLogFileRepository,
+    }
+
+    fn fixture() -> Fixture {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let client_repo = ClientFileRepository::new(&project_repo.get_self_client_file_path());
+        let folder_template_repo = FolderTemplateFileRepository::new(root.path()).unwrap();
+        let audit_log = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        Fixture { root, project_repo, client_repo, folder_template_repo, audit_log }
+    }
+
+    impl Fixture {
+        fn request(&self, csv: &str) -> ImportRequest {
+            let file_path = self.root.path().join("import.csv");
+            std::fs::write(&file_path, csv).unwrap();
+            ImportRequest {
+                file_path: file_path.to_string_lossy().to_string(),
+                sheet_name: None,
+                mapping: ImportColumnMapping {
+                    title: Some("題名".to_string()),
+                    order_date: Some("受注日".to_string()),
+                    due_date: Some("納期".to_string()),
+                    client_name: Some("クライアント".to_string()),
+                    ..Default::default()
+                },
+                template_id: None,
+            }
+        }
+
+        fn import(&self, csv: &str) -> Result<ImportSummary, AppError> {
+            import_projects(&self.project_repo, &self.client_repo, &self.folder_template_repo, &self.request(csv))
+        }
+    }
+
+    const HEADER: &str = "題名,受注日,納期,クライアント\n";
+
+    #[test]
+    fn rows_with_same_new_client_share_one_client() {
+        let fixture = fixture();
+        let csv = format!("{}案件A,2024-01-01,2024-02-01,新規\n案件B,2024-01-01,2024-02-01, 新規 \n", HEADER);
+
+        let summary = fixture.import(&csv).unwrap();
+
+        assert_eq!(summary.project_ids.len(), 2);
+        assert_eq!(summary.created_clients.len(), 1);
+        let clients = fixture.client_repo.fetch().unwrap();
+        assert_eq!(clients.len(), 1);
+        let projects = fixture.project_repo.fetch().unwrap();
+        assert!(projects.iter().all(|project| project.client.id == clients[0].id));
+        let entities = fixture.audit_log.fetch().unwrap().into_iter().map(|entry| entry.entity).collect::<Vec<AuditEntity>>();
+        assert_eq!(entities, vec![AuditEntity::Client, AuditEntity::Project, AuditEntity::Project]);
+    }
+
+    #[test]
+    fn failed_import_leaves_no_rows_folders_or_audit_entries() {
+        let fixture = fixture();
+        // 2行目のプロジェクトのフォルダ名になるidのフォルダを先に作り、取り込みの途中で失敗させる
+        std::fs::create_dir_all(get_project_directory_path("2")).unwrap();
+        let csv = format!("{}案件A,2024-01-01,2024-02-01,新規\n案件B,2024-01-01,2024-02-01,新規\n", HEADER);
+
+        assert!(fixture.import(&csv).is_err());
+
+        assert!(fixture.project_repo.fetch().unwrap().is_empty());
+        assert!(fixture.client_repo.fetch().unwrap().is_empty());
+        assert!(fixture.audit_log.fetch().unwrap().is_empty());
+        assert!(!get_project_directory_path("1").exists());
+    }
+
+    #[test]
+    fn row_errors_point_at_file_lines_and_nothing_is_imported() {
+        let fixture = fixture();
+        let csv = format!("{}\"案件\nA\",2024-01-01,2024-02-01,新規\n案件B,日付でない,2024-02-01,新規\n", HEADER);
+        let request = fixture.request(&csv);
+
+        let preview = preview_import(&fixture.project_repo, &fixture.client_repo, &fixture.folder_template_repo, &request).unwrap();
+        assert_eq!(preview.rows.iter().map(|row| row.row_number).collect::<Vec<usize>>(), vec![2, 4]);
+        assert_eq!((preview.valid_count, preview.error_count), (1, 1));
+        assert!(preview.rows[1].errors.iter().any(|error| error.contains("order_date")));
+
+        assert!(matches!(fixture.import(&csv), Err(AppError::Validation(_))));
+        assert!(fixture.project_repo.fetch().unwrap().is_empty());
+    }
+}
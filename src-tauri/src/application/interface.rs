@@ -1,4 +1,4 @@
-use crate::domain::models::{client::Client, project::ProjectStatus};
+use crate::domain::models::{audit_log::AuditEntity, client::Client, invoice::InvoiceLineItem, project::ProjectStatus};
 use serde::{Deserialize, Serialize};
 /// フロントエンドのProject
 /// 
@@ -13,6 +13,243 @@ pub struct ProjectFrontEnd{
         pub client: Client,
         pub status: ProjectStatus,
         pub folder_path:Option<String>, 
-        pub folder_path_suffix:Option<String>
+        pub folder_path_suffix:Option<String>,
+        pub deleted_at:Option<String>,
+        #[serde(default)]
+        pub archived_at:Option<String>,
+        #[serde(default)]
+        pub revision:u64,
+        #[serde(default)]
+        pub template_id:Option<String>, // 追加する時に使うフォルダテンプレート
+        pub task_completion_percentage:Option<f64>, // タスクがないプロジェクトはNone
     
-}
\ No newline at end of file
+}
+
+/// クライアントの詳細と、そのクライアントのプロジェクト
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientDetail{
+        pub client: Client,
+        pub projects: Vec<ProjectFrontEnd>,
+}
+
+/// プロジェクト削除時のフォルダの扱い
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DeleteProjectOption{
+        KeepFolder,
+        RemoveFolder,
+        MoveToTrash,
+}
+
+
+/// プロジェクトごとの合計作業時間
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectTimeTotal{
+        pub project_id: String,
+        pub title: String,
+        pub client_id: String,
+        pub total_seconds: i64,
+}
+
+/// クライアントごとの合計作業時間
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientTimeTotal{
+        pub client_id: String,
+        pub client_name: String,
+        pub total_seconds: i64,
+}
+
+/// 請求書の金額の決め方
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum InvoicePricing{
+        Hourly{ hourly_rate: i64 }, // 作業時間 × 時間単価
+        Fixed{ amount: i64 },       // 固定額
+}
+
+/// 請求書作成のリクエスト
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateInvoiceRequest{
+        pub project_id: String,
+        pub pricing: InvoicePricing,
+        pub tax_rate: f64,
+        pub issue_date: Option<String>, // 省略時は今日
+        pub payment_due_date: Option<String>, // 省略時は発行日の30日後
+        #[serde(default)]
+        pub extra_items: Vec<InvoiceLineItem>,
+}
+
+/// プロジェクト検索の並び順の項目
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ProjectSortKey{
+        Title,
+        ClientName,
+        OrderDate,
+        #[default]
+        DueDate,
+        Status,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortOrder{
+        #[default]
+        Asc,
+        Desc,
+}
+
+/// プロジェクト検索の条件。指定しなかった項目では絞り込まない
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ProjectSearchQuery{
+        pub text: Option<String>, // タイトル・説明・クライアント名。空白区切りですべての語を含むものを返す
+        pub statuses: Vec<ProjectStatus>, // 空なら全ステータス
+        pub client_id: Option<String>,
+        pub order_date_from: Option<String>,
+        pub order_date_to: Option<String>,
+        pub due_date_from: Option<String>,
+        pub due_date_to: Option<String>,
+        pub overdue_only: bool,
+        pub sort_key: ProjectSortKey,
+        pub sort_order: SortOrder,
+        pub page: usize, // 0から始まる
+        pub page_size: Option<usize>, // Noneなら全件
+}
+
+/// プロジェクト検索の結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectSearchResult{
+        pub projects: Vec<ProjectFrontEnd>,
+        pub total_count: usize, // ページに分ける前の件数
+        pub page: usize,
+        pub page_size: Option<usize>,
+}
+
+/// プロジェクトのフォルダのファイル検索で一致したファイル
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileSearchHit{
+        pub project_id: String,
+        pub relative_path: String, // プロジェクトフォルダからのパス。例: documents/見積書.pdf
+        pub snippet: String, // 一致した語の前後
+}
+
+/// プロジェクトのフォルダの中のファイルとフォルダ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectFileEntry{
+        pub name: String,
+        pub relative_path: String, // プロジェクトフォルダからのパス。区切りは'/'
+        pub kind: ProjectFileKind,
+        pub size: u64, // フォルダは0
+        pub modified: Option<String>, // 更新日時。取得できなければNone
+        pub children: Vec<ProjectFileEntry>, // フォルダの中身。ファイルは空
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ProjectFileKind{
+        File,
+        Directory,
+}
+
+/// フォルダが見つからないプロジェクト
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MissingProjectFolder{
+        pub project_id: String,
+        pub title: String,
+        pub folder_path_suffix: String,
+        pub rename_candidate: Option<String>, // 名前が変えられたと思われるフォルダ
+}
+
+/// プロジェクトとプロジェクト管理フォルダの中のフォルダの対応を確かめた結果
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProjectFolderReport{
+        pub missing: Vec<MissingProjectFolder>,
+        pub unlinked_folders: Vec<String>, // どのプロジェクトのフォルダでもないフォルダ
+}
+
+/// フォルダが見つからないプロジェクトの直し方
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum FolderReconcileAction{
+        Relink{ folder_name: String }, // 既にあるフォルダをプロジェクトのフォルダにする
+        Recreate, // テンプレートからフォルダを作り直す
+}
+
+/// アーカイブした後のプロジェクトのフォルダの扱い
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ArchiveProjectOption{
+        KeepFolder,
+        RemoveFolder,
+}
+
+/// 監査ログの絞り込み条件。指定しなかった項目では絞り込まない
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AuditLogQuery{
+        pub entity: Option<AuditEntity>,
+        pub entity_id: Option<String>,
+        pub date_from: Option<String>, // YYYY-MM-DD。この日を含む
+        pub date_to: Option<String>,
+}
+
+/// 元に戻す・やり直しの後の状態。entityとentity_idは書き戻したデータで、何もしなかった時はNone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UndoRedoState{
+        pub can_undo: bool,
+        pub can_redo: bool,
+        pub entity: Option<AuditEntity>,
+        pub entity_id: Option<String>,
+}
+
+/// ファイルからプロジェクトを取り込む時の、項目と列の見出しの対応。Noneの項目は読み込まない
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ImportColumnMapping{
+        pub title: Option<String>, // 必須
+        pub description: Option<String>,
+        pub order_date: Option<String>, // 必須
+        pub due_date: Option<String>, // 必須
+        pub completion_date: Option<String>,
+        pub status: Option<String>, // 省略したら未着手
+        pub client_name: Option<String>, // 必須。同じ名前のクライアントがなければ作る
+        pub contact_person: Option<String>,
+        pub folder_path_suffix: Option<String>, // 省略したらプロジェクトのid
+}
+
+/// preview_importとimport_projectsに渡す取り込みの指定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportRequest{
+        pub file_path: String,
+        #[serde(default)]
+        pub sheet_name: Option<String>, // 省略したら最初のシート。csvでは使わない
+        pub mapping: ImportColumnMapping,
+        #[serde(default)]
+        pub template_id: Option<String>, // フォルダを作るテンプレート。省略したら標準のテンプレート
+}
+
+/// read_import_fileの結果。列の対応を選ぶのに使う
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportFileInfo{
+        pub sheet_names: Vec<String>, // csvの時は空
+        pub columns: Vec<String>,
+        pub row_count: usize,
+}
+
+/// 取り込む前の確認。エラーのある行が1つでもあれば取り込まない
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportPreview{
+        pub rows: Vec<ImportRowPreview>,
+        pub valid_count: usize,
+        pub error_count: usize,
+        pub new_clients: Vec<String>, // 取り込むと新しく作るクライアントの名前
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportRowPreview{
+        pub row_number: usize, // ファイルでの行番号
+        pub project: Option<ProjectFrontEnd>, // エラーがあればNone
+        pub new_client: bool,
+        pub errors: Vec<String>,
+}
+
+/// import_projectsの結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportSummary{
+        pub project_ids: Vec<String>,
+        pub created_clients: Vec<Client>,
+}
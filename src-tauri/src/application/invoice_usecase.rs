@@ -0,0 +1,102 @@
+use chrono::{Duration, Local};
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::invoice::{hourly_line_item, render_invoice_html, validate_invoice}, businesslogic::project_date::parse_optional_date, businesslogic::project_file::write_deliverable_file, models::{client::Client, invoice::{Invoice, InvoiceLineItem, LineItemKind}, project::{Project, ProjectStatus}, time_entry::TimeEntry}}, repositories::{repository_trait::Repository, unit_of_work::UnitOfWork}};
+use crate::tr;
+
+use super::interface::{CreateInvoiceRequest, InvoicePricing};
+use super::time_entry_usecase::fetch_time_entries;
+
+/// 支払期限を省略した時の発行日からの日数
+const DEFAULT_PAYMENT_TERM_DAYS: i64 = 30;
+
+/// プロジェクトの請求書を発行日順で返す
+pub fn fetch_invoices(invoice_repo: &dyn Repository<Invoice>, project_id: &str) -> Result<Vec<Invoice>, AppError> {
+    let mut invoices = invoice_repo
+        .fetch()?
+        .into_iter()
+        .filter(|invoice| invoice.project_id == project_id)
+        .collect::<Vec<Invoice>>();
+    invoices.sort_by(|a, b| a.issue_date.cmp(&b.issue_date).then_with(|| a.invoice_number.cmp(&b.invoice_number)));
+    Ok(invoices)
+}
+
+/// 完了したプロジェクトから請求書を作成し、deliverablesフォルダにhtmlを書き出す。
+/// 作成後、プロジェクトのステータスを請求済みにする。
+pub fn create_invoice(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, time_entry_repo: &dyn Repository<TimeEntry>, invoice_repo: &dyn Repository<Invoice>, request: CreateInvoiceRequest) -> Result<Invoice, AppError> {
+    let project = project_repo
+        .get(&request.project_id)?
+        .ok_or_else(|| AppError::not_found(Entity::Project, &request.project_id))?;
+    if project.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.in_trash", project.title)));
+    }
+    if project.status != ProjectStatus::Completed {
+        return Err(AppError::validation(tr!("invoice.project_not_completed", project.title)));
+    }
+    let client = client_repo
+        .get(&project.client.id)?
+        .unwrap_or_else(|| project.client.clone());
+
+    let issue_date = parse_optional_date("issue_date", request.issue_date.as_deref())?.unwrap_or_else(|| Local::now().date_naive());
+    let payment_due_date = parse_optional_date("payment_due_date", request.payment_due_date.as_deref())?
+        .unwrap_or(issue_date + Duration::days(DEFAULT_PAYMENT_TERM_DAYS));
+
+    let mut line_items = vec![build_main_line_item(time_entry_repo, &project, &request.pricing)?];
+    line_items.extend(request.extra_items);
+
+    let invoice = Invoice {
+        id: String::new(),
+        invoice_number: String::new(), // 保存する時に振る
+        project_id: project.id.clone(),
+        client_id: client.id.clone(),
+        issue_date,
+        payment_due_date,
+        line_items,
+        tax_rate: request.tax_rate,
+        file_path: None,
+    };
+    validate_invoice(&invoice)?;
+
+    // ステータスを更新できなければ、請求書のファイルと保存した請求書も取り消す
+    let mut unit_of_work = UnitOfWork::new();
+    // 請求書番号は保存先がロックの中で振るので、先に保存してから番号を使ってファイルを作る
+    let invoice_id = unit_of_work.run(
+        &tr!("step.save_invoice"),
+        || invoice_repo.add(invoice),
+        |invoice_id| invoice_repo.delete(invoice_id),
+    )?;
+    let mut invoice = unit_of_work.check(invoice_repo.get(&invoice_id).and_then(|invoice| invoice.ok_or_else(|| AppError::not_found(Entity::Invoice, &invoice_id))))?;
+
+    let file_name = format!("invoice_{}.html", invoice.invoice_number);
+    let file_path = unit_of_work.run(
+        &tr!("step.create_invoice_file"),
+        || write_deliverable_file(&project, &file_name, &render_invoice_html(&invoice, &project, &client)),
+        |file_path| std::fs::remove_file(file_path).map_err(AppError::from),
+    )?;
+    invoice.file_path = Some(file_path.to_string_lossy().into());
+    unit_of_work.check(invoice_repo.update(invoice.clone()))?;
+
+    let mut invoiced_project = project;
+    invoiced_project.status = ProjectStatus::Invoiced;
+    unit_of_work.check(project_repo.update(invoiced_project))?;
+    unit_of_work.commit();
+    Ok(invoice)
+}
+
+/// 料金体系に応じたメインの明細を作る
+fn build_main_line_item(time_entry_repo: &dyn Repository<TimeEntry>, project: &Project, pricing: &InvoicePricing) -> Result<InvoiceLineItem, AppError> {
+    match pricing {
+        InvoicePricing::Hourly { hourly_rate } => {
+            let entries = fetch_time_entries(time_entry_repo, &project.id)?;
+            if entries.iter().any(|entry| entry.ended_at.is_none()) {
+                return Err(AppError::validation(tr!("invoice.timer_running")));
+            }
+            Ok(hourly_line_item(&entries, *hourly_rate))
+        }
+        InvoicePricing::Fixed { amount } => Ok(InvoiceLineItem {
+            description: project.title.clone(),
+            kind: LineItemKind::Fixed,
+            quantity: 1.0,
+            unit_price: *amount,
+        }),
+    }
+}
@@ -1,2 +1,18 @@
 pub mod usecase;
-pub mod interface;
\ No newline at end of file
+pub mod interface;
+pub mod task_usecase;
+pub mod time_entry_usecase;
+pub mod invoice_usecase;
+pub mod settings_usecase;
+pub mod project_search_usecase;
+pub mod file_search_usecase;
+
+pub mod folder_template_usecase;
+pub mod project_file_usecase;
+pub mod project_folder_sync_usecase;
+pub mod archive_usecase;
+pub mod backup_usecase;
+pub mod audit_log_usecase;
+pub mod edit_history_usecase;
+pub mod import_usecase;
+pub mod folder_watch_usecase;
\ No newline at end of file
@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::project_date::format_datetime, businesslogic::project_file::{copy_path, get_current_project_directory_path, get_folder_path_suffix, get_project_directory_path, move_path, remove_path, resolve_project_file_path, unique_file_path, validate_file_name}, models::project::Project}, repositories::repository_trait::Repository};
+use crate::tr;
+
+use super::interface::{ProjectFileEntry, ProjectFileKind};
+
+/// プロジェクトのフォルダの中をフォルダごとに返す。relative_pathが空ならプロジェクトのフォルダ全体
+pub fn list_project_files(project_repo: &dyn Repository<Project>, project_id: &str, relative_path: &str) -> Result<Vec<ProjectFileEntry>, AppError> {
+    let project = fetch_project(project_repo, project_id)?;
+    // ゴミ箱のプロジェクトも中身は見られるようにする
+    let project_path = get_current_project_directory_path(&project);
+    if !project_path.exists() {
+        return Ok(Vec::new());
+    }
+    let directory = resolve_project_file_path(&project_path, relative_path)?;
+    if !directory.is_dir() {
+        return Err(AppError::validation(tr!("file.not_directory", relative_path)));
+    }
+    read_entries(&project_path, &directory)
+}
+
+/// プロジェクトの外のファイルやフォルダを、プロジェクトのフォルダの中にコピーする。
+/// 同じ名前があれば番号をつけてコピーし、コピー先のパスを返す
+pub fn import_project_files(project_repo: &dyn Repository<Project>, project_id: &str, sources: &[String], destination: &str) -> Result<Vec<String>, AppError> {
+    let project_path = writable_project_directory(project_repo, project_id)?;
+    let destination_path = resolve_project_file_path(&project_path, destination)?;
+    fs::create_dir_all(&destination_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", destination_path.display(), e)))?;
+
+    let canonical_destination = fs::canonicalize(&destination_path).map_err(|e| AppError::io(tr!("file.read_directory_failed", destination_path.display(), e)))?;
+
+    let mut copied = Vec::new();
+    for source in sources {
+        let file_name = Path::new(source)
+            .file_name()
+            .ok_or_else(|| AppError::validation(tr!("file.not_found", source)))?;
+        // 選ばれたものがシンボリックリンクなら、リンク先をコピーする
+        let source_path = fs::canonicalize(source).map_err(|_| AppError::validation(tr!("file.not_found", source)))?;
+        // フォルダを自分の中にコピーすると終わらないので受け付けない
+        if canonical_destination.starts_with(&source_path) {
+            return Err(AppError::validation(tr!("file.copy_into_itself", source)));
+        }
+        let target_path = unique_file_path(&destination_path, &file_name.to_string_lossy());
+        copy_path(&source_path, &target_path)?;
+        copied.push(to_relative_path(&project_path, &target_path));
+    }
+    Ok(copied)
+}
+
+/// ファイルやフォルダの名前を変え、新しいパスを返す
+pub fn rename_project_file(project_repo: &dyn Repository<Project>, project_id: &str, relative_path: &str, new_name: &str) -> Result<String, AppError> {
+    validate_file_name(new_name)?;
+    let project_path = writable_project_directory(project_repo, project_id)?;
+    let path = resolve_existing_entry(&project_path, relative_path)?;
+    let new_path = path.with_file_name(new_name);
+    move_path(&path, &new_path)?;
+    Ok(to_relative_path(&project_path, &new_path))
+}
+
+/// ファイルやフォルダをプロジェクトの中の別のフォルダへ移動し、新しいパスを返す
+pub fn move_project_file(project_repo: &dyn Repository<Project>, project_id: &str, relative_path: &str, destination_directory: &str) -> Result<String, AppError> {
+    let project_path = writable_project_directory(project_repo, project_id)?;
+    let path = resolve_existing_entry(&project_path, relative_path)?;
+    let destination_path = resolve_project_file_path(&project_path, destination_directory)?;
+    if !destination_path.is_dir() {
+        return Err(AppError::validation(tr!("file.not_directory", destination_directory)));
+    }
+    // ファイル名は変えずに移動する。resolve_existing_entryでプロジェクトのフォルダ自体でないことは確かめてある
+    let Some(file_name) = path.file_name() else {
+        return Err(AppError::validation(tr!("file.project_root")));
+    };
+    let new_path = destination_path.join(file_name);
+    move_path(&path, &new_path)?;
+    Ok(to_relative_path(&project_path, &new_path))
+}
+
+/// ファイルまたはフォルダを中身ごと削除する
+pub fn delete_project_file(project_repo: &dyn Repository<Project>, project_id: &str, relative_path: &str) -> Result<(), AppError> {
+    let project_path = writable_project_directory(project_repo, project_id)?;
+    let path = resolve_existing_entry(&project_path, relative_path)?;
+    remove_path(&path)
+}
+
+fn fetch_project(project_repo: &dyn Repository<Project>, project_id: &str) -> Result<Project, AppError> {
+    project_repo.get(project_id)?.ok_or_else(|| AppError::not_found(Entity::Project, project_id))
+}
+
+/// 変更してよいプロジェクトのフォルダ。ゴミ箱のプロジェクトは変更しない
+fn writable_project_directory(project_repo: &dyn Repository<Project>, project_id: &str) -> Result<PathBuf, AppError> {
+    let project = fetch_project(project_repo, project_id)?;
+    if project.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.in_trash", project.title)));
+    }
+    let project_path = get_project_directory_path(&get_folder_path_suffix(&project));
+    if !project_path.exists() {
+        return Err(AppError::validation(tr!("file.not_found", project_path.display())));
+    }
+    Ok(project_path)
+}
+
+/// 名前の変更・移動・削除の対象。プロジェクトのフォルダ自体は対象にしない
+fn resolve_existing_entry(project_path: &Path, relative_path: &str) -> Result<PathBuf, AppError> {
+    let path = resolve_project_file_path(project_path, relative_path)?;
+    if path == project_path {
+        return Err(AppError::validation(tr!("file.project_root")));
+    }
+    if !path.exists() {
+        return Err(AppError::validation(tr!("file.not_found", relative_path)));
+    }
+    Ok(path)
+}
+
+/// フォルダの中身をフォルダ、ファイルの順に名前で並べて返す
+fn read_entries(project_path: &Path, directory: &Path) -> Result<Vec<ProjectFileEntry>, AppError> {
+    let read_error = |e: std::io::Error| AppError::io(tr!("file.read_directory_failed", directory.display(), e));
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(directory).map_err(read_error)? {
+        let entry = entry.map_err(read_error)?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // ロックファイルなどの隠しファイルは表示しない
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        // シンボリックリンクはたどると外のフォルダや自分自身に戻ってしまうことがあるので表示しない
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.file_type().is_symlink() {
+            continue;
+        }
+        let modified = metadata.modified().ok().map(|modified| format_datetime(&DateTime::<Local>::from(modified)));
+        let (kind, size, children) = if metadata.is_dir() {
+            (ProjectFileKind::Directory, 0, read_entries(project_path, &path)?)
+        } else {
+            (ProjectFileKind::File, metadata.len(), Vec::new())
+        };
+        entries.push(ProjectFileEntry {
+            name,
+            relative_path: to_relative_path(project_path, &path),
+            kind,
+            size,
+            modified,
+            children,
+        });
+    }
+    entries.sort_by(|a, b| (a.kind != ProjectFileKind::Directory).cmp(&(b.kind != ProjectFileKind::Directory)).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+fn to_relative_path(project_path: &Path, path: &Path) -> String {
+    path.strip_prefix(project_path)
+        .map(|relative| relative.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        .unwrap_or_default()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::file_repository::ProjectFileRepository;
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn add_project(root: &TestDataRoot) -> (ProjectFileRepository, String, PathBuf) {
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let project_path = get_current_project_directory_path(&project_repo.get(&id).unwrap().unwrap());
+        (project_repo, id, project_path)
+    }
+
+    fn names(entries: &[ProjectFileEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn list_project_files_sorts_folders_first_and_hides_dot_files() {
+        let root = TestDataRoot::new();
+        let (project_repo, id, project_path) = add_project(&root);
+        fs::write(project_path.join("a.txt"), "abc").unwrap();
+        fs::write(project_path.join(".lock"), "").unwrap();
+
+        let entries = list_project_files(&project_repo, &id, "").unwrap();
+        assert_eq!(names(&entries), vec!["deliverables", "documents", "works", "a.txt"]);
+        assert_eq!(entries[3].relative_path, "a.txt");
+        assert_eq!(entries[3].size, 3);
+
+        let result = list_project_files(&project_repo, &id, "../");
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_project_files_skips_links() {
+        let root = TestDataRoot::new();
+        let (project_repo, id, project_path) = add_project(&root);
+        let outside = root.path().join("外");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("秘密.txt"), "").unwrap();
+        std::os::unix::fs::symlink(&outside, project_path.join("documents").join("外")).unwrap();
+        std::os::unix::fs::symlink(&project_path, project_path.join("works").join("ループ")).unwrap();
+
+        let entries = list_project_files(&project_repo, &id, "").unwrap();
+        assert!(entries.iter().all(|entry| entry.children.is_empty()));
+    }
+
+    #[test]
+    fn import_project_files_copies_with_new_name_and_rejects_copy_into_itself() {
+        let root = TestDataRoot::new();
+        let (project_repo, id, project_path) = add_project(&root);
+        let source = root.path().join("見積書.txt");
+        fs::write(&source, "見積").unwrap();
+        fs::write(project_path.join("documents").join("見積書.txt"), "").unwrap();
+
+        let copied = import_project_files(&project_repo, &id, &[source.to_string_lossy().to_string()], "documents").unwrap();
+        assert_eq!(copied, vec!["documents/見積書 (2).txt"]);
+        assert_eq!(fs::read_to_string(project_path.join("documents").join("見積書 (2).txt")).unwrap(), "見積");
+
+        let documents = project_path.join("documents").to_string_lossy().to_string();
+        let result = import_project_files(&project_repo, &id, &[documents], "documents/下");
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn rename_move_and_delete_stay_in_project() {
+        let root = TestDataRoot::new();
+        let (project_repo, id, project_path) = add_project(&root);
+        fs::write(project_path.join("documents").join("メモ.txt"), "").unwrap();
+
+        assert_eq!(rename_project_file(&project_repo, &id, "documents/メモ.txt", "議事録.txt").unwrap(), "documents/議事録.txt");
+        assert_eq!(move_project_file(&project_repo, &id, "documents/議事録.txt", "works").unwrap(), "works/議事録.txt");
+        assert!(rename_project_file(&project_repo, &id, "works/議事録.txt", "../議事録.txt").is_err());
+        assert!(move_project_file(&project_repo, &id, "works/議事録.txt", "..").is_err());
+        assert!(matches!(delete_project_file(&project_repo, &id, ""), Err(AppError::Validation(_))));
+
+        delete_project_file(&project_repo, &id, "works/議事録.txt").unwrap();
+        assert!(!project_path.join("works").join("議事録.txt").exists());
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::folder_template::resolve_folder_template, businesslogic::project_file::{create_project_directories, get_folder_path_suffix, get_project_directory_path, is_project_folder_missing, list_project_folder_names, validate_file_name}, models::{folder_template::FolderTemplate, project::Project}}, repositories::repository_trait::Repository};
+use crate::tr;
+
+use super::interface::{FolderReconcileAction, MissingProjectFolder, ProjectFolderReport};
+use super::usecase::fetch_active_projects;
+
+/// プロジェクト管理フォルダの中を見て、フォルダがなくなったプロジェクトと、どのプロジェクトのものでもないフォルダを返す。
+/// renamesは監視で見つけたフォルダの名前の変更(元の名前 -> 新しい名前)
+pub fn check_project_folders(project_repo: &dyn Repository<Project>, renames: &HashMap<String, String>) -> Result<ProjectFolderReport, AppError> {
+    let projects = fetch_active_projects(project_repo)?;
+    let linked = projects.iter().map(get_folder_path_suffix).collect::<HashSet<String>>();
+    let unlinked_folders = list_project_folder_names()?
+        .into_iter()
+        .filter(|name| !linked.contains(name))
+        .collect::<Vec<String>>();
+
+    let missing_projects = projects.iter().filter(|project| is_project_folder_missing(project)).collect::<Vec<&Project>>();
+    let missing = missing_projects
+        .iter()
+        .map(|project| {
+            let folder_path_suffix = get_folder_path_suffix(project);
+            let rename_candidate = renames
+                .get(&folder_path_suffix)
+                .filter(|name| unlinked_folders.contains(name))
+                .cloned()
+                .or_else(|| guess_rename_candidate(project, missing_projects.len(), &unlinked_folders));
+            MissingProjectFolder {
+                project_id: project.id.clone(),
+                title: project.title.clone(),
+                folder_path_suffix,
+                rename_candidate,
+            }
+        })
+        .collect();
+    Ok(ProjectFolderReport { missing, unlinked_folders })
+}
+
+/// 監視で見つけられなかった名前の変更を推測する。
+/// 元の名前かタイトルを含むフォルダが1つだけならそれを、なくなったフォルダと新しいフォルダが1つずつならその組を候補にする
+fn guess_rename_candidate(project: &Project, missing_count: usize, unlinked_folders: &[String]) -> Option<String> {
+    let folder_path_suffix = get_folder_path_suffix(project);
+    let title = project.title.trim();
+    let similar = unlinked_folders
+        .iter()
+        .filter(|name| name.contains(folder_path_suffix.as_str()) || (!title.is_empty() && name.contains(title)))
+        .collect::<Vec<&String>>();
+    match similar.as_slice() {
+        [name] => Some((*name).clone()),
+        _ if missing_count == 1 && unlinked_folders.len() == 1 => unlinked_folders.first().cloned(),
+        _ => None,
+    }
+}
+
+/// フォルダが見つからないプロジェクトを、既にあるフォルダにつなぎ直すか、フォルダを作り直す
+pub fn reconcile_project_folder(project_repo: &dyn Repository<Project>, template_repo: &dyn Repository<FolderTemplate>, project_id: &str, action: FolderReconcileAction, renames: &HashMap<String, String>) -> Result<ProjectFolderReport, AppError> {
+    let mut project = project_repo.get(project_id)?.ok_or_else(|| AppError::not_found(Entity::Project, project_id))?;
+    if project.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.in_trash", project.title)));
+    }
+    match action {
+        FolderReconcileAction::Relink { folder_name } => {
+            validate_file_name(&folder_name)?;
+            if !get_project_directory_path(&folder_name).is_dir() {
+                return Err(AppError::validation(tr!("file.not_found", folder_name)));
+            }
+            let in_use = fetch_active_projects(project_repo)?
+                .iter()
+                .any(|other| other.id != project.id && get_folder_path_suffix(other) == folder_name);
+            if in_use {
+                return Err(AppError::duplicate_folder(folder_name));
+            }
+            // 元のフォルダがなければ、updateはフォルダを動かさずにsuffixだけを付け替える
+            project.folder_path_suffix = Some(folder_name);
+            project_repo.update(project)?;
+        }
+        FolderReconcileAction::Recreate => {
+            let folder_path_suffix = get_folder_path_suffix(&project);
+            let project_path = get_project_directory_path(&folder_path_suffix);
+            if project_path.exists() {
+                return Err(AppError::duplicate_folder(folder_path_suffix));
+            }
+            let template = resolve_folder_template(template_repo.fetch()?, project.template_id.as_deref())?;
+            create_project_directories(project_path, &template, &project)?;
+        }
+    }
+    check_project_folders(project_repo, renames)
+}
@@ -0,0 +1,248 @@
+use std::cmp::Ordering;
+
+use chrono::{Local, NaiveDate};
+
+use crate::{domain::{error::AppError, businesslogic::project_date::parse_optional_date, businesslogic::project_status::is_overdue, models::{project::Project, task::Task}}, repositories::repository_trait::Repository};
+
+use super::interface::{ProjectSearchQuery, ProjectSearchResult, ProjectSortKey, SortOrder};
+use super::usecase::{convert_projects_to_frontend, fetch_active_projects};
+
+/// 日付の文字列を変換した検索条件
+struct ProjectFilter<'a> {
+    query: &'a ProjectSearchQuery,
+    terms: Vec<String>,
+    order_date_from: Option<NaiveDate>,
+    order_date_to: Option<NaiveDate>,
+    due_date_from: Option<NaiveDate>,
+    due_date_to: Option<NaiveDate>,
+    today: NaiveDate,
+}
+
+impl<'a> ProjectFilter<'a> {
+    fn new(query: &'a ProjectSearchQuery) -> Result<Self, AppError> {
+        let terms = query
+            .text
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        Ok(ProjectFilter {
+            query,
+            terms,
+            order_date_from: parse_optional_date("order_date_from", query.order_date_from.as_deref())?,
+            order_date_to: parse_optional_date("order_date_to", query.order_date_to.as_deref())?,
+            due_date_from: parse_optional_date("due_date_from", query.due_date_from.as_deref())?,
+            due_date_to: parse_optional_date("due_date_to", query.due_date_to.as_deref())?,
+            today: Local::now().date_naive(),
+        })
+    }
+
+    fn matches(&self, project: &Project) -> bool {
+        if !self.query.statuses.is_empty() && !self.query.statuses.contains(&project.status) {
+            return false;
+        }
+        if let Some(client_id) = &self.query.client_id {
+            if &project.client.id != client_id {
+                return false;
+            }
+        }
+        if !in_range(project.order_date, self.order_date_from, self.order_date_to)
+            || !in_range(project.due_date, self.due_date_from, self.due_date_to)
+        {
+            return false;
+        }
+        if self.query.overdue_only && !is_overdue(project, self.today) {
+            return false;
+        }
+        self.matches_text(project)
+    }
+
+    // 全ての語が、タイトル・説明・クライアント名のどれかに含まれていればよい
+    fn matches_text(&self, project: &Project) -> bool {
+        if self.terms.is_empty() {
+            return true;
+        }
+        let haystack = format!("{}\n{}\n{}", project.title, project.description, project.client.name).to_lowercase();
+        self.terms.iter().all(|term| haystack.contains(term.as_str()))
+    }
+}
+
+fn in_range(date: NaiveDate, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    from.is_none_or(|from| from <= date) && to.is_none_or(|to| date <= to)
+}
+
+fn compare_projects(a: &Project, b: &Project, sort_key: ProjectSortKey) -> Ordering {
+    match sort_key {
+        ProjectSortKey::Title => a.title.cmp(&b.title),
+        ProjectSortKey::ClientName => a.client.name.cmp(&b.client.name),
+        ProjectSortKey::OrderDate => a.order_date.cmp(&b.order_date),
+        ProjectSortKey::DueDate => a.due_date.cmp(&b.due_date),
+        // enumの宣言順(未着手→進行中→…)で並べる
+        ProjectSortKey::Status => (a.status.clone() as u8).cmp(&(b.status.clone() as u8)),
+    }
+}
+
+/// 条件に合うゴミ箱以外のプロジェクトを、並べ替えてページに分けて返す。
+/// 今はrepositoryの全件から絞り込むが、データベースの時はクエリに置き換えられるように条件はここにまとめる。
+pub fn search_projects(project_repo: &dyn Repository<Project>, task_repo: &dyn Repository<Task>, query: &ProjectSearchQuery) -> Result<ProjectSearchResult, AppError> {
+    let filter = ProjectFilter::new(query)?;
+    let mut projects = fetch_active_projects(project_repo)?
+        .into_iter()
+        .filter(|project| filter.matches(project))
+        .collect::<Vec<Project>>();
+
+    // 同じ値の時はidで並べて、ページをまたいでも順番が変わらないようにする
+    projects.sort_by(|a, b| {
+        let ordering = compare_projects(a, b, query.sort_key).then_with(|| a.id.cmp(&b.id));
+        match query.sort_order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+
+    let total_count = projects.len();
+    let projects = match query.page_size {
+        Some(page_size) => projects.into_iter().skip(query.page.saturating_mul(page_size)).take(page_size).collect(),
+        None => projects,
+    };
+    Ok(ProjectSearchResult {
+        projects: convert_projects_to_frontend(projects, task_repo)?,
+        total_count,
+        page: query.page,
+        page_size: query.page_size,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::project::ProjectStatus;
+    use crate::repositories::file_repository::{ProjectFileRepository, TaskFileRepository};
+    use crate::repositories::test_support::{date, sample_client, sample_project, TestDataRoot};
+
+    struct Fixture {
+        _root: TestDataRoot,
+        project_repo: ProjectFileRepository,
+        task_repo: TaskFileRepository,
+    }
+
+    // 受注日・納期・ステータス・クライアントの違うプロジェクトを3件追加する
+    fn fixture() -> Fixture {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+
+        let mut logo = sample_project("ロゴ制作", sample_client("", "山田商店"));
+        logo.description = "新店舗のロゴ".to_string();
+        logo.order_date = date("2024-01-10");
+        logo.due_date = date("2024-02-01");
+        let id = project_repo.add(logo).unwrap();
+        let yamada = project_repo.get(&id).unwrap().unwrap().client;
+
+        let mut site = sample_project("Webサイト", yamada);
+        site.order_date = date("2024-03-01");
+        site.due_date = date("2099-12-31");
+        let id = project_repo.add(site).unwrap();
+        let mut site = project_repo.get(&id).unwrap().unwrap();
+        site.status = ProjectStatus::InProgress;
+        project_repo.update(site).unwrap();
+
+        let mut flyer = sample_project("チラシ", sample_client("", "佐藤工務店"));
+        flyer.order_date = date("2024-05-01");
+        flyer.due_date = date("2024-05-20");
+        let id = project_repo.add(flyer).unwrap();
+        let mut flyer = project_repo.get(&id).unwrap().unwrap();
+        flyer.status = ProjectStatus::InProgress;
+        project_repo.update(flyer).unwrap();
+        let mut flyer = project_repo.get(&id).unwrap().unwrap();
+        flyer.status = ProjectStatus::Delivered;
+        project_repo.update(flyer).unwrap();
+
+        Fixture { _root: root, project_repo, task_repo }
+    }
+
+    fn search_titles(fixture: &Fixture, query: &ProjectSearchQuery) -> Vec<String> {
+        search_projects(&fixture.project_repo, &fixture.task_repo, query)
+            .unwrap()
+            .projects
+            .into_iter()
+            .map(|project| project.title)
+            .collect()
+    }
+
+    #[test]
+    fn text_matches_all_terms_in_title_description_or_client() {
+        let fixture = fixture();
+        let query = |text: &str| ProjectSearchQuery { text: Some(text.to_string()), sort_key: ProjectSortKey::Title, ..Default::default() };
+
+        assert_eq!(search_titles(&fixture, &query("山田")), vec!["Webサイト", "ロゴ制作"]);
+        assert_eq!(search_titles(&fixture, &query("山田 新店舗")), vec!["ロゴ制作"]);
+        assert_eq!(search_titles(&fixture, &query("webサイト")), vec!["Webサイト"]);
+        assert!(search_titles(&fixture, &query("山田 チラシ")).is_empty());
+    }
+
+    #[test]
+    fn filters_by_status_and_client() {
+        let fixture = fixture();
+        let query = ProjectSearchQuery { statuses: vec![ProjectStatus::InProgress, ProjectStatus::Delivered], ..Default::default() };
+        assert_eq!(search_titles(&fixture, &query), vec!["チラシ", "Webサイト"]);
+
+        let client_id = fixture.project_repo.get("1").unwrap().unwrap().client.id;
+        let query = ProjectSearchQuery { client_id: Some(client_id), statuses: vec![ProjectStatus::InProgress], ..Default::default() };
+        assert_eq!(search_titles(&fixture, &query), vec!["Webサイト"]);
+    }
+
+    #[test]
+    fn date_ranges_include_both_ends() {
+        let fixture = fixture();
+        let query = ProjectSearchQuery {
+            order_date_from: Some("2024-03-01".to_string()),
+            order_date_to: Some("2024/05/01".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search_titles(&fixture, &query), vec!["チラシ", "Webサイト"]);
+
+        let query = ProjectSearchQuery { due_date_to: Some("2024-02-01".to_string()), ..Default::default() };
+        assert_eq!(search_titles(&fixture, &query), vec!["ロゴ制作"]);
+    }
+
+    #[test]
+    fn invalid_date_is_a_parse_error() {
+        let fixture = fixture();
+        let query = ProjectSearchQuery { due_date_from: Some("来週".to_string()), ..Default::default() };
+        let result = search_projects(&fixture.project_repo, &fixture.task_repo, &query);
+        assert!(matches!(result, Err(AppError::Parse(_))));
+    }
+
+    #[test]
+    fn overdue_only_skips_finished_projects() {
+        let fixture = fixture();
+        // チラシは納期を過ぎているが納品済み
+        let query = ProjectSearchQuery { overdue_only: true, ..Default::default() };
+        assert_eq!(search_titles(&fixture, &query), vec!["ロゴ制作"]);
+    }
+
+    #[test]
+    fn trashed_projects_are_not_returned() {
+        let fixture = fixture();
+        let mut logo = fixture.project_repo.get("1").unwrap().unwrap();
+        logo.deleted_at = Some(Local::now());
+        fixture.project_repo.update(logo).unwrap();
+
+        assert_eq!(search_titles(&fixture, &ProjectSearchQuery::default()), vec!["チラシ", "Webサイト"]);
+    }
+
+    #[test]
+    fn sorts_descending_and_pages_with_total_count() {
+        let fixture = fixture();
+        let query = ProjectSearchQuery { sort_key: ProjectSortKey::OrderDate, sort_order: SortOrder::Desc, page: 1, page_size: Some(2), ..Default::default() };
+        let result = search_projects(&fixture.project_repo, &fixture.task_repo, &query).unwrap();
+        assert_eq!(result.total_count, 3);
+        assert_eq!(result.projects.iter().map(|project| project.title.as_str()).collect::<Vec<_>>(), vec!["ロゴ制作"]);
+
+        let query = ProjectSearchQuery { sort_key: ProjectSortKey::Status, ..Default::default() };
+        assert_eq!(search_titles(&fixture, &query), vec!["ロゴ制作", "Webサイト", "チラシ"]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use crate::{domain::error::AppError, domain::i18n::{set_locale, Locale}, domain::businesslogic::data_directory::{migrate_data_directory, validate_data_directory}, repositories::{csv_migration::migrate_csv_schema, settings_repository::SettingsFileRepository}};
+
+/// 新しいデータフォルダを使える状態にする。migrateがtrueなら今のデータをコピーする。
+pub fn prepare_data_directory(current_root: &Path, new_directory: &str, migrate: bool) -> Result<PathBuf, AppError> {
+    let new_root = PathBuf::from(new_directory.trim());
+    validate_data_directory(&new_root)?;
+    if migrate {
+        migrate_data_directory(current_root, &new_root)?;
+        // 古いバージョンのcsvには元のフォルダの絶対パスが残っているので、コピーした先でスキーマを最新にしておく。
+        // 元のデータは書き換えないので、失敗しても今のフォルダのまま使える
+        let csv_directory = new_root.join("files");
+        if csv_directory.exists() {
+            migrate_csv_schema(&csv_directory)?;
+        }
+    }
+    Ok(new_root)
+}
+
+/// データフォルダを設定に保存する
+pub fn save_data_directory(settings_repo: &SettingsFileRepository, data_root: &Path) -> Result<(), AppError> {
+    let mut settings = settings_repo.load()?;
+    settings.data_directory = Some(data_root.to_string_lossy().into());
+    settings_repo.save(&settings)
+}
+
+/// 言語を切り替えて設定に保存する
+pub fn save_locale(settings_repo: &SettingsFileRepository, locale: Locale) -> Result<(), AppError> {
+    let mut settings = settings_repo.load()?;
+    settings.locale = locale;
+    settings_repo.save(&settings)?;
+    set_locale(locale);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn migrated_copy_has_relative_folder_paths() {
+        let current_root = tempfile::tempdir().unwrap();
+        let new_root = tempfile::tempdir().unwrap();
+        let csv_directory = current_root.path().join("files");
+        fs::create_dir_all(&csv_directory).unwrap();
+        let old_folder = current_root.path().join("project").join("案件A");
+        let legacy_csv = format!(
+            "id,title,description,order_date,due_date,completion_date,client_id,status,folder_path\n1,案件A,,2024/4/1,2024/4/30,,1,NotStarted,{}\n",
+            old_folder.display()
+        );
+        fs::write(csv_directory.join("projects.csv"), &legacy_csv).unwrap();
+
+        prepare_data_directory(current_root.path(), &new_root.path().to_string_lossy(), true).unwrap();
+
+        let migrated = fs::read_to_string(new_root.path().join("files").join("projects.csv")).unwrap();
+        let mut lines = migrated.lines();
+        assert!(lines.next().unwrap().split(',').any(|column| column == "folder_path_suffix"));
+        assert_eq!(lines.next().unwrap(), "1,案件A,,2024-04-01,2024-04-30,,1,NotStarted,案件A,,0,,");
+        // 元のデータはそのまま残す
+        assert_eq!(fs::read_to_string(csv_directory.join("projects.csv")).unwrap(), legacy_csv);
+    }
+
+    #[test]
+    fn migration_refuses_a_destination_with_data() {
+        let current_root = tempfile::tempdir().unwrap();
+        let new_root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(current_root.path().join("files")).unwrap();
+        fs::create_dir_all(new_root.path().join("project")).unwrap();
+
+        let result = prepare_data_directory(current_root.path(), &new_root.path().to_string_lossy(), true);
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{domain::{error::{AppError, Entity}, businesslogic::task_progress::{collect_descendant_ids, validate_task_parent}, models::{project::Project, task::Task}}, repositories::{repository_trait::Repository, unit_of_work::UnitOfWork}};
+use crate::tr;
+
+/// プロジェクトのタスクを並び順で返す
+pub fn fetch_tasks(task_repo: &dyn Repository<Task>, project_id: &str) -> Result<Vec<Task>, AppError> {
+    let mut tasks = task_repo
+        .fetch()?
+        .into_iter()
+        .filter(|task| task.project_id == project_id)
+        .collect::<Vec<Task>>();
+    tasks.sort_by_key(|task| task.sort_order);
+    Ok(tasks)
+}
+
+/// タスクを追加する。並び順は同じ親を持つタスクの最後にする
+pub fn add_task(project_repo: &dyn Repository<Project>, task_repo: &dyn Repository<Task>, mut new_task: Task) -> Result<String, AppError> {
+    if project_repo.get(&new_task.project_id)?.is_none() {
+        return Err(AppError::not_found(Entity::Project, &new_task.project_id));
+    }
+    let tasks = fetch_tasks(task_repo, &new_task.project_id)?;
+    validate_task_parent(&tasks, &new_task)?;
+
+    new_task.sort_order = tasks
+        .iter()
+        .filter(|task| task.parent_id == new_task.parent_id)
+        .map(|task| task.sort_order + 1)
+        .max()
+        .unwrap_or(0);
+    task_repo.add(new_task)
+}
+
+pub fn update_task(task_repo: &dyn Repository<Task>, updated_task: Task) -> Result<(), AppError> {
+    let old_task = task_repo.get(&updated_task.id)?.ok_or_else(|| AppError::not_found(Entity::Task, &updated_task.id))?;
+    if old_task.project_id != updated_task.project_id {
+        return Err(AppError::validation(tr!("task.move_to_other_project")));
+    }
+    let tasks = fetch_tasks(task_repo, &updated_task.project_id)?;
+    validate_task_parent(&tasks, &updated_task)?;
+    task_repo.update(updated_task)
+}
+
+/// 同じ親を持つタスクを、task_idsの順番に並び順を振り直す。parent_idがNoneの時は最上位のタスクを並べ替える。
+/// task_idsにはその親の子タスクをすべて1回ずつ指定する。一部だけだと他のタスクと並び順が重なるのでエラーにする
+pub fn reorder_tasks(task_repo: &dyn Repository<Task>, project_id: &str, parent_id: Option<&str>, task_ids: Vec<String>) -> Result<(), AppError> {
+    let siblings = fetch_tasks(task_repo, project_id)?
+        .into_iter()
+        .filter(|task| task.parent_id.as_deref() == parent_id)
+        .collect::<Vec<Task>>();
+    let mut sibling_ids = siblings.iter().map(|task| task.id.as_str()).collect::<Vec<&str>>();
+    let mut requested_ids = task_ids.iter().map(String::as_str).collect::<Vec<&str>>();
+    sibling_ids.sort_unstable();
+    requested_ids.sort_unstable();
+    if sibling_ids != requested_ids {
+        return Err(AppError::validation(tr!("task.reorder_mismatch")));
+    }
+
+    for (sort_order, task_id) in task_ids.iter().enumerate() {
+        let Some(task) = siblings.iter().find(|task| &task.id == task_id) else {
+            continue;
+        };
+        if task.sort_order != sort_order as i32 {
+            let mut task = task.clone();
+            task.sort_order = sort_order as i32;
+            task_repo.update(task)?;
+        }
+    }
+    Ok(())
+}
+
+/// タスクの完了状態を変更する。完了にした時はサブタスクもすべて完了にする
+pub fn complete_task(task_repo: &dyn Repository<Task>, id: &str, done: bool) -> Result<(), AppError> {
+    let task = task_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Task, id))?;
+    let tasks = fetch_tasks(task_repo, &task.project_id)?;
+    let mut target_ids = vec![task.id.clone()];
+    if done {
+        target_ids.extend(collect_descendant_ids(&tasks, &task.id));
+    }
+    for mut target in tasks.into_iter().filter(|target| target_ids.contains(&target.id)) {
+        if target.done != done {
+            target.done = done;
+            task_repo.update(target)?;
+        }
+    }
+    Ok(())
+}
+
+/// タスクをサブタスクごと削除する
+pub fn delete_task(task_repo: &dyn Repository<Task>, id: &str) -> Result<(), AppError> {
+    let task = task_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Task, id))?;
+    let tasks = fetch_tasks(task_repo, &task.project_id)?;
+    delete_task_tree(task_repo, &tasks, id)
+}
+
+/// プロジェクトのタスクをすべて削除する。後の書き込みが失敗したら、unit_of_workが同じidで書き戻す
+pub fn delete_project_tasks<'a>(unit_of_work: &mut UnitOfWork<'a>, task_repo: &'a dyn Repository<Task>, project_id: &str) -> Result<(), AppError> {
+    let tasks = unit_of_work.check(fetch_tasks(task_repo, project_id))?;
+    // 子から順に削除する。取り消しは逆順なので、親から書き戻される
+    for task in tasks.iter().filter(|task| task.parent_id.is_none()) {
+        let mut ids = collect_descendant_ids(&tasks, &task.id);
+        ids.reverse();
+        ids.push(task.id.clone());
+        for id in ids {
+            let Some(deleted) = tasks.iter().find(|task| task.id == id).cloned() else { continue };
+            unit_of_work.run(&tr!("step.delete_task"), || task_repo.delete(&id), move |_| task_repo.restore(deleted))?;
+        }
+    }
+    Ok(())
+}
+
+// 子から順に削除する。親を先に消すと、sqliteでは子がカスケード削除されて見つからなくなる
+fn delete_task_tree(task_repo: &dyn Repository<Task>, tasks: &[Task], id: &str) -> Result<(), AppError> {
+    for descendant_id in collect_descendant_ids(tasks, id).iter().rev() {
+        task_repo.delete(descendant_id)?;
+    }
+    task_repo.delete(id)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::file_repository::TaskFileRepository;
+    use crate::repositories::test_support::TestDataRoot;
+
+    fn sample_task(parent_id: Option<&str>, title: &str, sort_order: i32) -> Task {
+        Task {
+            id: String::new(),
+            project_id: "1".to_string(),
+            parent_id: parent_id.map(str::to_string),
+            title: title.to_string(),
+            description: String::new(),
+            due_date: None,
+            done: false,
+            sort_order,
+        }
+    }
+
+    fn titles(task_repo: &dyn Repository<Task>, parent_id: Option<&str>) -> Vec<String> {
+        fetch_tasks(task_repo, "1")
+            .unwrap()
+            .into_iter()
+            .filter(|task| task.parent_id.as_deref() == parent_id)
+            .map(|task| task.title)
+            .collect()
+    }
+
+    #[test]
+    fn reorder_tasks_only_touches_the_given_parent() {
+        let root = TestDataRoot::new();
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+        let first = task_repo.add(sample_task(None, "A", 0)).unwrap();
+        let second = task_repo.add(sample_task(None, "B", 1)).unwrap();
+        let child_first = task_repo.add(sample_task(Some(&first), "A-1", 0)).unwrap();
+        let child_second = task_repo.add(sample_task(Some(&first), "A-2", 1)).unwrap();
+
+        reorder_tasks(&task_repo, "1", None, vec![second.clone(), first.clone()]).unwrap();
+        assert_eq!(titles(&task_repo, None), vec!["B", "A"]);
+        assert_eq!(titles(&task_repo, Some(&first)), vec!["A-1", "A-2"]);
+
+        reorder_tasks(&task_repo, "1", Some(&first), vec![child_second, child_first]).unwrap();
+        assert_eq!(titles(&task_repo, Some(&first)), vec!["A-2", "A-1"]);
+        assert_eq!(titles(&task_repo, None), vec!["B", "A"]);
+    }
+
+    #[test]
+    fn reorder_tasks_requires_exactly_the_siblings() {
+        let root = TestDataRoot::new();
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+        let first = task_repo.add(sample_task(None, "A", 0)).unwrap();
+        let second = task_repo.add(sample_task(None, "B", 1)).unwrap();
+        let child = task_repo.add(sample_task(Some(&first), "A-1", 0)).unwrap();
+
+        let is_validation = |result: Result<(), AppError>| matches!(result, Err(AppError::Validation(_)));
+        // 一部だけ
+        assert!(is_validation(reorder_tasks(&task_repo, "1", None, vec![second.clone()])));
+        // 同じタスクを2回
+        assert!(is_validation(reorder_tasks(&task_repo, "1", None, vec![second.clone(), second.clone()])));
+        // 別の親のタスクが混ざっている
+        assert!(is_validation(reorder_tasks(&task_repo, "1", None, vec![second.clone(), first.clone(), child])));
+        // 存在しないタスク
+        assert!(is_validation(reorder_tasks(&task_repo, "1", None, vec![second, "99".to_string()])));
+
+        assert_eq!(titles(&task_repo, None), vec!["A", "B"]);
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::Local;
+
+use crate::{domain::{error::{AppError, Entity}, businesslogic::time_tracking::{find_running_entry, total_seconds_by_project}, models::{client::Client, project::Project, time_entry::TimeEntry}}, repositories::{repository_trait::Repository, unit_of_work::UnitOfWork}};
+use crate::tr;
+
+use super::interface::{ClientTimeTotal, ProjectTimeTotal};
+use super::usecase::fetch_active_projects;
+
+/// プロジェクトの作業時間の記録を開始時刻順で返す
+pub fn fetch_time_entries(time_entry_repo: &dyn Repository<TimeEntry>, project_id: &str) -> Result<Vec<TimeEntry>, AppError> {
+    let mut entries = time_entry_repo
+        .fetch()?
+        .into_iter()
+        .filter(|entry| entry.project_id == project_id)
+        .collect::<Vec<TimeEntry>>();
+    entries.sort_by_key(|entry| entry.started_at);
+    Ok(entries)
+}
+
+pub fn fetch_running_timer(time_entry_repo: &dyn Repository<TimeEntry>) -> Result<Option<TimeEntry>, AppError> {
+    let entries = time_entry_repo.fetch()?;
+    Ok(find_running_entry(&entries).cloned())
+}
+
+/// タイマーを開始する。計測中のタイマーは1つだけなので、既にあればエラーにする。
+/// 他のウィンドウと同時に開始しても2つにならないように、確認は保存先がロックの中で行う
+pub fn start_timer(project_repo: &dyn Repository<Project>, time_entry_repo: &dyn Repository<TimeEntry>, project_id: &str, note: String) -> Result<TimeEntry, AppError> {
+    ensure_active_project(project_repo, project_id)?;
+    let mut entry = TimeEntry {
+        id: String::new(),
+        project_id: project_id.to_string(),
+        started_at: Local::now(),
+        ended_at: None,
+        note,
+    };
+    entry.id = time_entry_repo.add(entry.clone())?;
+    Ok(entry)
+}
+
+/// 計測中のタイマーを止めて、止めた記録を返す
+pub fn stop_timer(time_entry_repo: &dyn Repository<TimeEntry>) -> Result<TimeEntry, AppError> {
+    let mut entry = fetch_running_timer(time_entry_repo)?.ok_or_else(|| AppError::validation(tr!("timer.not_running")))?;
+    entry.ended_at = Some(Local::now());
+    time_entry_repo.update(entry.clone())?;
+    Ok(entry)
+}
+
+/// 作業時間を手入力で追加する
+pub fn add_time_entry(project_repo: &dyn Repository<Project>, time_entry_repo: &dyn Repository<TimeEntry>, new_entry: TimeEntry) -> Result<String, AppError> {
+    ensure_active_project(project_repo, &new_entry.project_id)?;
+    time_entry_repo.add(new_entry)
+}
+
+pub fn update_time_entry(project_repo: &dyn Repository<Project>, time_entry_repo: &dyn Repository<TimeEntry>, updated_entry: TimeEntry) -> Result<(), AppError> {
+    ensure_active_project(project_repo, &updated_entry.project_id)?;
+    time_entry_repo.update(updated_entry)
+}
+
+pub fn delete_time_entry(time_entry_repo: &dyn Repository<TimeEntry>, id: &str) -> Result<(), AppError> {
+    if time_entry_repo.get(id)?.is_none() {
+        return Err(AppError::not_found(Entity::TimeEntry, id));
+    }
+    time_entry_repo.delete(id)
+}
+
+/// プロジェクトの作業時間の記録をすべて削除する。後の書き込みが失敗したら、unit_of_workが同じidで書き戻す
+pub fn delete_project_time_entries<'a>(unit_of_work: &mut UnitOfWork<'a>, time_entry_repo: &'a dyn Repository<TimeEntry>, project_id: &str) -> Result<(), AppError> {
+    for entry in unit_of_work.check(fetch_time_entries(time_entry_repo, project_id))? {
+        let id = entry.id.clone();
+        unit_of_work.run(&tr!("step.delete_time_entry"), || time_entry_repo.delete(&id), move |_| time_entry_repo.restore(entry))?;
+    }
+    Ok(())
+}
+
+/// プロジェクトごとの合計作業時間。計測中の記録と、ゴミ箱に入っているプロジェクトは含めない
+pub fn fetch_project_time_totals(project_repo: &dyn Repository<Project>, time_entry_repo: &dyn Repository<TimeEntry>) -> Result<Vec<ProjectTimeTotal>, AppError> {
+    let totals = total_seconds_by_project(&time_entry_repo.fetch()?);
+    Ok(fetch_active_projects(project_repo)?
+        .into_iter()
+        .map(|project| ProjectTimeTotal {
+            total_seconds: totals.get(&project.id).copied().unwrap_or(0),
+            project_id: project.id,
+            title: project.title,
+            client_id: project.client.id,
+        })
+        .collect())
+}
+
+/// クライアントごとの合計作業時間
+pub fn fetch_client_time_totals(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, time_entry_repo: &dyn Repository<TimeEntry>) -> Result<Vec<ClientTimeTotal>, AppError> {
+    let project_totals = fetch_project_time_totals(project_repo, time_entry_repo)?;
+    Ok(client_repo
+        .fetch()?
+        .into_iter()
+        .map(|client| ClientTimeTotal {
+            total_seconds: project_totals
+                .iter()
+                .filter(|total| total.client_id == client.id)
+                .map(|total| total.total_seconds)
+                .sum(),
+            client_id: client.id,
+            client_name: client.name,
+        })
+        .collect())
+}
+
+// ゴミ箱に入っていない、アーカイブしていないプロジェクトにだけ時間を記録できる
+fn ensure_active_project(project_repo: &dyn Repository<Project>, project_id: &str) -> Result<(), AppError> {
+    match project_repo.get(project_id)? {
+        Some(project) if project.deleted_at.is_some() => Err(AppError::validation(tr!("timer.project_in_trash", project_id))),
+        Some(project) if project.archived_at.is_some() => Err(AppError::validation(tr!("timer.project_archived", project_id))),
+        Some(_) => Ok(()),
+        None => Err(AppError::not_found(Entity::Project, project_id)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::repositories::file_repository::{ClientFileRepository, ProjectFileRepository, TimeEntryFileRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn add_hour(time_entry_repo: &TimeEntryFileRepository, project_id: &str, hour: u32) {
+        let started_at = Local.with_ymd_and_hms(2024, 4, 1, hour, 0, 0).unwrap();
+        time_entry_repo
+            .add(TimeEntry {
+                id: String::new(),
+                project_id: project_id.to_string(),
+                started_at,
+                ended_at: Some(started_at + Duration::hours(1)),
+                note: String::new(),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn totals_exclude_projects_in_trash() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let client_repo = ClientFileRepository::new(&project_repo.get_self_client_file_path());
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        let active_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let client_id = project_repo.get(&active_id).unwrap().unwrap().client.id;
+        let trashed_id = project_repo.add(sample_project("案件B", sample_client(&client_id, "取引先"))).unwrap();
+        add_hour(&time_entry_repo, &active_id, 9);
+        add_hour(&time_entry_repo, &trashed_id, 10);
+        add_hour(&time_entry_repo, &trashed_id, 11);
+        let mut trashed = project_repo.get(&trashed_id).unwrap().unwrap();
+        trashed.deleted_at = Some(Local::now());
+        project_repo.update(trashed).unwrap();
+
+        let project_totals = fetch_project_time_totals(&project_repo, &time_entry_repo).unwrap();
+        assert_eq!(
+            project_totals.iter().map(|total| (total.project_id.clone(), total.total_seconds)).collect::<Vec<_>>(),
+            vec![(active_id, 3600)]
+        );
+        let client_totals = fetch_client_time_totals(&project_repo, &client_repo, &time_entry_repo).unwrap();
+        assert_eq!(client_totals.len(), 1);
+        assert_eq!(client_totals[0].total_seconds, 3600);
+    }
+
+    #[test]
+    fn delete_missing_time_entry_returns_not_found() {
+        let root = TestDataRoot::new();
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        add_hour(&time_entry_repo, "1", 9);
+
+        assert_eq!(delete_time_entry(&time_entry_repo, "99"), Err(AppError::not_found(Entity::TimeEntry, "99")));
+        delete_time_entry(&time_entry_repo, "1").unwrap();
+        assert!(time_entry_repo.fetch().unwrap().is_empty());
+    }
+
+    #[test]
+    fn time_cannot_be_recorded_on_trashed_or_archived_project() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        let active_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let client_id = project_repo.get(&active_id).unwrap().unwrap().client.id;
+        let trashed_id = project_repo.add(sample_project("案件B", sample_client(&client_id, "取引先"))).unwrap();
+        let archived_id = project_repo.add(sample_project("案件C", sample_client(&client_id, "取引先"))).unwrap();
+        let mut trashed = project_repo.get(&trashed_id).unwrap().unwrap();
+        trashed.deleted_at = Some(Local::now());
+        project_repo.update(trashed).unwrap();
+        let mut archived = project_repo.get(&archived_id).unwrap().unwrap();
+        archived.archived_at = Some(Local::now());
+        project_repo.update(archived).unwrap();
+
+        assert!(matches!(start_timer(&project_repo, &time_entry_repo, &archived_id, String::new()), Err(AppError::Validation(_))));
+        add_hour(&time_entry_repo, &active_id, 9);
+        for project_id in [&trashed_id, &archived_id] {
+            let mut entry = time_entry_repo.get("1").unwrap().unwrap();
+            entry.project_id = project_id.clone();
+            assert!(matches!(update_time_entry(&project_repo, &time_entry_repo, entry), Err(AppError::Validation(_))));
+        }
+        assert_eq!(time_entry_repo.get("1").unwrap().unwrap().project_id, active_id);
+    }
+
+    #[test]
+    fn only_one_timer_starts_from_several_windows() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let project_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        TimeEntryFileRepository::new(root.path()).unwrap();
+
+        let handles = (0..4)
+            .map(|_| {
+                let (data_root, project_id) = (root.path().to_path_buf(), project_id.clone());
+                std::thread::spawn(move || {
+                    let project_repo = ProjectFileRepository::new(&data_root).unwrap();
+                    let time_entry_repo = TimeEntryFileRepository::new(&data_root).unwrap();
+                    start_timer(&project_repo, &time_entry_repo, &project_id, String::new()).is_ok()
+                })
+            })
+            .collect::<Vec<_>>();
+        let started = handles.into_iter().map(|handle| handle.join().unwrap()).filter(|started| *started).count();
+
+        assert_eq!(started, 1);
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        assert_eq!(time_entry_repo.fetch().unwrap().len(), 1);
+    }
+}
@@ -1,48 +1,341 @@
-use crate::{domain::{businesslogic::project_file::get_project_directory_path, models::project::Project}, repositories::file_repository::ProjectFileRepository};
+use std::fs;
 
-use super::interface::ProjectFrontEnd;
+use chrono::Local;
 
-pub fn convert_ifrontend_to_project(input:ProjectFrontEnd)-> Project{
+use crate::{domain::{error::{AppError, Entity}, businesslogic::project_archive::get_archive_file_path, businesslogic::project_date::{format_date, format_datetime, parse_date, parse_optional_date, parse_optional_datetime}, businesslogic::project_file::{get_current_project_directory_path, get_folder_path_suffix, get_project_directory_path, get_trash_directory_path, move_project_directory, remove_project_directory}, businesslogic::task_progress::completion_percentages_by_project, models::{client::Client, project::Project, task::Task, time_entry::TimeEntry}}, repositories::{repository_trait::{Repository, UpdatedItem}, unit_of_work::UnitOfWork}};
+use crate::tr;
 
-    Project{
+use super::interface::{ClientDetail, DeleteProjectOption, ProjectFrontEnd};
+use super::task_usecase::delete_project_tasks;
+use super::time_entry_usecase::delete_project_time_entries;
+
+pub fn convert_ifrontend_to_project(input:ProjectFrontEnd)-> Result<Project, AppError>{
+
+    Ok(Project{
+        order_date:parse_date("order_date", &input.order_date)?,
+        due_date:parse_date("due_date", &input.due_date)?,
+        completion_date:parse_optional_date("completion_date", input.completion_date.as_deref())?,
+        deleted_at:parse_optional_datetime("deleted_at", input.deleted_at.as_deref())?,
+        archived_at:parse_optional_datetime("archived_at", input.archived_at.as_deref())?,
         id:input.id,
         title:input.title,
         description:input.description,
-        order_date:input.order_date,
-        due_date:input.due_date,
-        completion_date:input.completion_date,
         client:input.client,
         status:input.status,
-        folder_path_suffix:input.folder_path_suffix
-    }
+        folder_path_suffix:input.folder_path_suffix,
+        revision:input.revision,
+        template_id:input.template_id,
+    })
 }
 
 
-pub fn convert_project_to_frontend(input: Project) -> ProjectFrontEnd {
+pub fn convert_project_to_frontend(input: Project, task_completion_percentage: Option<f64>) -> ProjectFrontEnd {
 
-    let folder_path_suffix = input.folder_path_suffix.as_deref().unwrap_or(&input.id);
-    let folder_path = Some(get_project_directory_path(folder_path_suffix).to_string_lossy().into());
+    let folder_path = Some(get_current_project_directory_path(&input).to_string_lossy().into());
 
     // プロジェクトフロントエンドのインスタンスを作成
     ProjectFrontEnd {
         id: input.id,
         title: input.title,
         description: input.description,
-        order_date: input.order_date,
-        due_date: input.due_date,
-        completion_date: input.completion_date,
+        order_date: format_date(&input.order_date),
+        due_date: format_date(&input.due_date),
+        completion_date: input.completion_date.as_ref().map(format_date),
         client: input.client,
         status: input.status,
         folder_path,
         folder_path_suffix: input.folder_path_suffix,
+        deleted_at: input.deleted_at.as_ref().map(format_datetime),
+        archived_at: input.archived_at.as_ref().map(format_datetime),
+        revision: input.revision,
+        template_id: input.template_id,
+        task_completion_percentage,
+    }
+}
+
+/// タスクの完了率をつけてフロントエンドのProjectに変換する
+pub fn convert_projects_to_frontend(projects: Vec<Project>, task_repo: &dyn Repository<Task>) -> Result<Vec<ProjectFrontEnd>, AppError> {
+    let percentages = completion_percentages_by_project(&task_repo.fetch()?);
+    Ok(projects
+        .into_iter()
+        .map(|project| {
+            let percentage = percentages.get(&project.id).copied();
+            convert_project_to_frontend(project, percentage)
+        })
+        .collect())
+}
+
+
+/// フロントエンドで編集したプロジェクトを保存し、更新する前と後のプロジェクトを返す。
+/// ゴミ箱とアーカイブの状態はそれぞれの操作でだけ変えるので、フロントエンドから来た値は使わない。
+/// 読み込んだ後に他で更新されていたら、revisionが合わずに保存しない
+pub fn update_project(project_repo: &dyn Repository<Project>, mut project: Project) -> Result<UpdatedItem<Project>, AppError> {
+    let current = project_repo.get(&project.id)?.ok_or_else(|| AppError::not_found(Entity::Project, &project.id))?;
+    // ゴミ箱のプロジェクトはフォルダもゴミ箱にあり、フォルダ名を変えられないので、元に戻してから編集する
+    if current.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.in_trash", project.id)));
+    }
+    // アーカイブ中はフォルダがzipの中にあり、展開先も今のフォルダ名で決まるので、フォルダ名は変えられない
+    if current.archived_at.is_some() && get_folder_path_suffix(&project) != get_folder_path_suffix(&current) {
+        return Err(AppError::validation(tr!("archive.folder_rename_archived", project.id)));
+    }
+    project.deleted_at = current.deleted_at;
+    project.archived_at = current.archived_at;
+    project_repo.update_with_history(project)
+}
+
+/// ゴミ箱に入っていないプロジェクトを返す
+pub fn fetch_active_projects(project_repo: &dyn Repository<Project>) -> Result<Vec<Project>, AppError> {
+    let projects = project_repo.fetch()?;
+    Ok(projects.into_iter().filter(|project| project.deleted_at.is_none()).collect())
+}
+
+/// プロジェクトを削除する。プロジェクトのフォルダを残すかどうかはoptionで指定する。
+/// MoveToTrashの時は行を残したまま、フォルダと一緒にゴミ箱へ移動する。
+/// タスク・作業時間・プロジェクトの行はまとめて削除し、途中で失敗したら書き戻す。
+/// 請求書は発行した記録として残す。idは使い回さないので、後で追加したプロジェクトの請求書には見えない
+pub fn delete_project(project_repo: &dyn Repository<Project>, task_repo: &dyn Repository<Task>, time_entry_repo: &dyn Repository<TimeEntry>, id: &str, option: DeleteProjectOption) -> Result<(), AppError> {
+    let project = project_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+    if option == DeleteProjectOption::MoveToTrash {
+        return move_project_to_trash(project_repo, project);
+    }
+
+    let project_path = get_current_project_directory_path(&project);
+    let mut unit_of_work = UnitOfWork::new();
+    delete_project_tasks(&mut unit_of_work, task_repo, id)?;
+    delete_project_time_entries(&mut unit_of_work, time_entry_repo, id)?;
+    unit_of_work.check(project_repo.delete(id))?;
+    unit_of_work.commit();
+
+    // 行を消したプロジェクトのアーカイブは、もう展開できないので消しておく
+    let archive_file_path = get_archive_file_path(&project);
+    if archive_file_path.exists() {
+        fs::remove_file(&archive_file_path).map_err(|e| AppError::io(tr!("file.remove_file_failed", archive_file_path.display(), e)))?;
+    }
+
+    // 行を消した後にフォルダを消す。フォルダの削除に失敗してもデータの整合性は保たれる。
+    if option == DeleteProjectOption::RemoveFolder && project_path.exists() {
+        remove_project_directory(project_path)?;
     }
+    Ok(())
 }
 
+/// プロジェクトにdeleted_atをつけ、フォルダをゴミ箱へ移動する
+fn move_project_to_trash(project_repo: &dyn Repository<Project>, mut project: Project) -> Result<(), AppError> {
+    if project.deleted_at.is_some() {
+        return Err(AppError::validation(tr!("project.already_in_trash", project.id)));
+    }
+    let project_path = get_project_directory_path(&get_folder_path_suffix(&project));
+    let trash_path = get_trash_directory_path(&project);
+    // 保存に失敗したらフォルダを元に戻す
+    let mut unit_of_work = UnitOfWork::new();
+    if project_path.exists() {
+        unit_of_work.run(
+            &tr!("step.move_folder_to_trash"),
+            || move_project_directory(project_path.clone(), trash_path.clone()),
+            |_| move_project_directory(trash_path.clone(), project_path.clone()),
+        )?;
+    }
+
+    project.deleted_at = Some(Local::now());
+    unit_of_work.check(project_repo.update(project))?;
+    unit_of_work.commit();
+    Ok(())
+}
+
+/// ゴミ箱に入っているプロジェクトを返す
+pub fn list_trash(project_repo: &dyn Repository<Project>) -> Result<Vec<Project>, AppError> {
+    let projects = project_repo.fetch()?;
+    Ok(projects.into_iter().filter(|project| project.deleted_at.is_some()).collect())
+}
 
-// pub fn fetch_projects(repository:ProjectFileRepository)->Result<Vec<ProjectFrontEnd>,String>{
-//     let projects = repository.fetch().map_err(|e| e.to_string())?;
+/// ゴミ箱からプロジェクトを元に戻す
+pub fn restore_project(project_repo: &dyn Repository<Project>, id: &str) -> Result<(), AppError> {
+    let mut project = project_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+    if project.deleted_at.is_none() {
+        return Err(AppError::validation(tr!("project.not_in_trash", id)));
+    }
+    let folder_path_suffix = get_folder_path_suffix(&project);
+    let project_path = get_project_directory_path(&folder_path_suffix);
+    if project_path.exists() {
+        return Err(AppError::duplicate_folder(folder_path_suffix));
+    }
+    let trash_path = get_trash_directory_path(&project);
+    let mut unit_of_work = UnitOfWork::new();
+    if trash_path.exists() {
+        unit_of_work.run(
+            &tr!("step.restore_folder_from_trash"),
+            || move_project_directory(trash_path.clone(), project_path.clone()),
+            |_| move_project_directory(project_path.clone(), trash_path.clone()),
+        )?;
+    }
+
+    project.deleted_at = None;
+    unit_of_work.check(project_repo.update(project))?;
+    unit_of_work.commit();
+    Ok(())
+}
+
+/// ゴミ箱に入っているプロジェクトをフォルダごと完全に削除する
+pub fn purge_trash(project_repo: &dyn Repository<Project>, task_repo: &dyn Repository<Task>, time_entry_repo: &dyn Repository<TimeEntry>) -> Result<(), AppError> {
+    for project in list_trash(project_repo)? {
+        delete_project(project_repo, task_repo, time_entry_repo, &project.id, DeleteProjectOption::RemoveFolder)?;
+    }
+    Ok(())
+}
+
+/// クライアントの詳細を、ゴミ箱に入っていないプロジェクトと一緒に返す
+pub fn fetch_client_detail(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, task_repo: &dyn Repository<Task>, id: &str) -> Result<ClientDetail, AppError> {
+    let client = client_repo.get(id)?.ok_or_else(|| AppError::not_found(Entity::Client, id))?;
+    let projects = fetch_active_projects(project_repo)?
+        .into_iter()
+        .filter(|project| project.client.id == id)
+        .collect::<Vec<Project>>();
+    Ok(ClientDetail {
+        client,
+        projects: convert_projects_to_frontend(projects, task_repo)?,
+    })
+}
+
+/// クライアントを削除する。プロジェクトから参照されているクライアントは削除しない。
+pub fn delete_client(project_repo: &dyn Repository<Project>, client_repo: &dyn Repository<Client>, id: &str) -> Result<(), AppError> {
+    let referenced_titles = project_repo
+        .fetch()?
+        .into_iter()
+        .filter(|project| project.client.id == id)
+        .map(|project| project.title)
+        .collect::<Vec<String>>();
+    if !referenced_titles.is_empty() {
+        return Err(AppError::validation(tr!("client.in_use", referenced_titles.join(", "))));
+    }
+    client_repo.delete(id)
+}
+
+// pub fn fetch_projects(repository:ProjectFileRepository)->Result<Vec<ProjectFrontEnd>,AppError>{
+//     let projects = repository.fetch()?;
 //     let project_frontends = projects.into_iter().map(convert_project_to_frontend).collect::<Vec<ProjectFrontEnd>>();
 
 //     return Ok(project_frontends)
 
-// }
\ No newline at end of file
+// }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::file_repository::{ProjectFileRepository, TaskFileRepository, TimeEntryFileRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    /// 行の削除だけが失敗するプロジェクトの保存先
+    struct DeleteFails<'a>(&'a ProjectFileRepository);
+
+    impl Repository<Project> for DeleteFails<'_> {
+        fn add(&self, item: Project) -> Result<String, AppError> { self.0.add(item) }
+        fn get(&self, id: &str) -> Result<Option<Project>, AppError> { self.0.get(id) }
+        fn update(&self, item: Project) -> Result<(), AppError> { self.0.update(item) }
+        fn fetch(&self) -> Result<Vec<Project>, AppError> { self.0.fetch() }
+        fn delete(&self, _id: &str) -> Result<(), AppError> { Err(AppError::io("削除の失敗")) }
+    }
+
+    fn sample_task(project_id: &str, parent_id: Option<&str>) -> Task {
+        Task { id: String::new(), project_id: project_id.to_string(), parent_id: parent_id.map(str::to_string), title: "作業".to_string(), description: String::new(), due_date: None, done: false, sort_order: 0 }
+    }
+
+    #[test]
+    fn failed_project_delete_restores_tasks_and_time_entries() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let parent_id = task_repo.add(sample_task(&id, None)).unwrap();
+        task_repo.add(sample_task(&id, Some(&parent_id))).unwrap();
+        time_entry_repo.add(TimeEntry { id: String::new(), project_id: id.clone(), started_at: Local::now(), ended_at: Some(Local::now()), note: String::new() }).unwrap();
+        let task_ids = |repo: &TaskFileRepository| repo.fetch().unwrap().into_iter().map(|task| (task.id, task.parent_id)).collect::<Vec<_>>();
+        let tasks_before = task_ids(&task_repo);
+        let entries_before = time_entry_repo.fetch().unwrap().into_iter().map(|entry| entry.id).collect::<Vec<String>>();
+
+        let result = delete_project(&DeleteFails(&project_repo), &task_repo, &time_entry_repo, &id, DeleteProjectOption::KeepFolder);
+
+        assert_eq!(result, Err(AppError::io("削除の失敗")));
+        assert!(project_repo.get(&id).unwrap().is_some());
+        let mut tasks_after = task_ids(&task_repo);
+        tasks_after.sort();
+        let mut tasks_before = tasks_before;
+        tasks_before.sort();
+        assert_eq!(tasks_after, tasks_before);
+        assert_eq!(time_entry_repo.fetch().unwrap().into_iter().map(|entry| entry.id).collect::<Vec<String>>(), entries_before);
+
+        // 削除できた時は、消したプロジェクトのidを次のプロジェクトに使わない
+        delete_project(&project_repo, &task_repo, &time_entry_repo, &id, DeleteProjectOption::KeepFolder).unwrap();
+        assert!(task_repo.fetch().unwrap().is_empty());
+        assert!(time_entry_repo.fetch().unwrap().is_empty());
+        assert_ne!(project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap(), id);
+    }
+
+    #[test]
+    fn update_project_keeps_trash_and_archive_state() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.archived_at = Some(Local::now());
+        project_repo.update(project).unwrap();
+
+        // フロントエンドから来たプロジェクトは、ゴミ箱とアーカイブの状態を変えていても使わない
+        let mut edited = project_repo.get(&id).unwrap().unwrap();
+        edited.title = "案件B".to_string();
+        edited.deleted_at = Some(Local::now());
+        edited.archived_at = None;
+        let updated = update_project(&project_repo, edited).unwrap();
+
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.title, "案件B");
+        assert_eq!(updated.after.title, "案件B");
+        assert!(saved.deleted_at.is_none());
+        assert_eq!(saved.archived_at.map(|value| format_datetime(&value)), updated.before.archived_at.map(|value| format_datetime(&value)));
+    }
+
+    #[test]
+    fn archived_project_folder_cannot_be_renamed() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.archived_at = Some(Local::now());
+        project_repo.update(project).unwrap();
+
+        let mut edited = project_repo.get(&id).unwrap().unwrap();
+        edited.folder_path_suffix = Some("新しいフォルダ".to_string());
+
+        assert!(matches!(update_project(&project_repo, edited), Err(AppError::Validation(_))));
+        assert_eq!(project_repo.get(&id).unwrap().unwrap().folder_path_suffix, Some(id));
+    }
+
+    #[test]
+    fn project_in_trash_cannot_be_edited() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+        let time_entry_repo = TimeEntryFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        delete_project(&project_repo, &task_repo, &time_entry_repo, &id, DeleteProjectOption::MoveToTrash).unwrap();
+
+        let mut edited = project_repo.get(&id).unwrap().unwrap();
+        edited.folder_path_suffix = Some("新しいフォルダ".to_string());
+
+        assert!(matches!(update_project(&project_repo, edited), Err(AppError::Validation(_))));
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.folder_path_suffix, Some(id.clone()));
+        assert!(get_trash_directory_path(&saved).exists());
+    }
+
+    #[test]
+    fn update_missing_project_returns_not_found() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let mut project = sample_project("案件A", sample_client("", "取引先"));
+        project.id = "99".to_string();
+
+        assert_eq!(update_project(&project_repo, project).unwrap_err(), AppError::not_found(Entity::Project, "99"));
+    }
+}
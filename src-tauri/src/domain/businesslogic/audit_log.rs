@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use chrono::Local;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::domain::error::AppError;
+use crate::domain::models::audit_log::{AuditAction, AuditEntity, AuditLogEntry, FieldChange};
+use crate::domain::models::project::Project;
+
+/// 毎回変わるので差分に含めない項目
+const IGNORED_FIELDS: [&str; 1] = ["revision"];
+
+/// 変更前と変更後のデータから監査ログを作る。追加の時はbefore、削除の時はafterをNoneにする
+pub fn new_audit_log_entry<T: Serialize>(entity: AuditEntity, entity_id: &str, action: AuditAction, before: Option<&T>, after: Option<&T>) -> Result<AuditLogEntry, AppError> {
+    let before = before.map(serde_json::to_value).transpose()?;
+    let after = after.map(serde_json::to_value).transpose()?;
+    Ok(new_entry(entity, entity_id, action, diff_values(before.as_ref(), after.as_ref())))
+}
+
+/// プロジェクトの監査ログ。プロジェクトにはクライアントのidしか保存しないので、クライアントはidだけを比べる
+pub fn project_audit_log_entry(action: AuditAction, before: Option<&Project>, after: Option<&Project>) -> Result<AuditLogEntry, AppError> {
+    let entity_id = after.or(before).map(|project| project.id.clone()).unwrap_or_default();
+    let before = before.map(project_value).transpose()?;
+    let after = after.map(project_value).transpose()?;
+    Ok(new_entry(AuditEntity::Project, &entity_id, action, diff_values(before.as_ref(), after.as_ref())))
+}
+
+/// 2つのデータで値が違う項目を返す
+pub fn changed_fields<T: Serialize>(before: &T, after: &T) -> Result<Vec<FieldChange>, AppError> {
+    Ok(diff_values(Some(&serde_json::to_value(before)?), Some(&serde_json::to_value(after)?)))
+}
+
+/// 2つのプロジェクトで値が違う項目を返す。監査ログと同じく、クライアントはidだけを比べる
+pub fn changed_project_fields(before: &Project, after: &Project) -> Result<Vec<FieldChange>, AppError> {
+    Ok(diff_values(Some(&project_value(before)?), Some(&project_value(after)?)))
+}
+
+fn project_value(project: &Project) -> Result<Value, AppError> {
+    let mut value = serde_json::to_value(project)?;
+    if let Value::Object(map) = &mut value {
+        map.remove("client");
+        map.insert("client_id".to_string(), Value::String(project.client.id.clone()));
+    }
+    Ok(value)
+}
+
+/// プロジェクトのフォルダの名前を変えた時の監査ログ
+pub fn folder_rename_audit_log_entry(project_id: &str, old_suffix: &str, new_suffix: &str) -> AuditLogEntry {
+    let change = FieldChange {
+        field: "folder_path_suffix".to_string(),
+        before: Some(old_suffix.to_string()),
+        after: Some(new_suffix.to_string()),
+    };
+    new_entry(AuditEntity::Project, project_id, AuditAction::RenameFolder, vec![change])
+}
+
+fn new_entry(entity: AuditEntity, entity_id: &str, action: AuditAction, changes: Vec<FieldChange>) -> AuditLogEntry {
+    AuditLogEntry {
+        timestamp: Local::now(),
+        entity,
+        entity_id: entity_id.to_string(),
+        action,
+        changes,
+        user: current_os_user(),
+    }
+}
+
+/// アプリを使っているOSのユーザー名。取れなければ空にする
+pub fn current_os_user() -> String {
+    ["USERNAME", "USER", "LOGNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_default()
+}
+
+fn diff_values(before: Option<&Value>, after: Option<&Value>) -> Vec<FieldChange> {
+    diff_fields(&before.map(flatten_fields).unwrap_or_default(), &after.map(flatten_fields).unwrap_or_default())
+}
+
+/// 値が変わった項目だけを返す
+fn diff_fields(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Vec<FieldChange> {
+    let mut fields = before.keys().chain(after.keys()).collect::<Vec<&String>>();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| FieldChange {
+            field: field.clone(),
+            before: before.get(field).cloned(),
+            after: after.get(field).cloned(),
+        })
+        .collect()
+}
+
+/// データを「項目名 -> 値」にする。入れ子のオブジェクトは client.name のように展開し、nullの項目は含めない
+fn flatten_fields(value: &Value) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    flatten_value("", value, &mut fields);
+    fields
+}
+
+fn flatten_value(prefix: &str, value: &Value, fields: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if prefix.is_empty() && IGNORED_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                let field = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_value(&field, value, fields);
+            }
+        }
+        Value::Null => {}
+        Value::String(text) => {
+            fields.insert(prefix.to_string(), text.clone());
+        }
+        // 担当者の一覧などの配列は、まとめて1つの値として比べる
+        other => {
+            fields.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::{sample_client, sample_project};
+
+    fn change(field: &str, before: Option<&str>, after: Option<&str>) -> FieldChange {
+        FieldChange {
+            field: field.to_string(),
+            before: before.map(str::to_string),
+            after: after.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn only_changed_fields_are_listed() {
+        let before = sample_project("案件A", sample_client("1", "取引先"));
+        let mut after = before.clone();
+        after.title = "案件B".to_string();
+        after.revision += 1;
+
+        assert_eq!(changed_project_fields(&before, &after).unwrap(), vec![change("title", Some("案件A"), Some("案件B"))]);
+    }
+
+    #[test]
+    fn project_client_is_compared_by_id() {
+        let before = sample_project("案件A", sample_client("1", "取引先"));
+        let mut after = before.clone();
+        after.client.name = "名前だけ変更".to_string();
+        assert!(changed_project_fields(&before, &after).unwrap().is_empty());
+
+        after.client.id = "2".to_string();
+        assert_eq!(changed_project_fields(&before, &after).unwrap(), vec![change("client_id", Some("1"), Some("2"))]);
+    }
+
+    #[test]
+    fn nested_fields_are_joined_with_dot() {
+        let before = sample_client("1", "取引先");
+        let mut after = before.clone();
+        after.billing.billing_name = "請求先".to_string();
+
+        let changes = changed_fields(&before, &after).unwrap();
+        assert_eq!(changes, vec![change("billing.billing_name", Some(""), Some("請求先"))]);
+    }
+
+    #[test]
+    fn add_and_delete_list_every_field_on_one_side() {
+        let project = sample_project("案件A", sample_client("1", "取引先"));
+
+        let added = project_audit_log_entry(AuditAction::Add, None, Some(&project)).unwrap();
+        assert_eq!(added.entity_id, project.id);
+        assert!(added.changes.iter().all(|change| change.before.is_none() && change.after.is_some()));
+        assert!(added.changes.contains(&change("title", None, Some("案件A"))));
+        // nullの項目と毎回変わるrevisionは含めない
+        assert!(added.changes.iter().all(|change| change.field != "completion_date" && change.field != "revision"));
+
+        let deleted = project_audit_log_entry(AuditAction::Delete, Some(&project), None).unwrap();
+        assert!(deleted.changes.iter().all(|change| change.before.is_some() && change.after.is_none()));
+        assert_eq!(deleted.changes.len(), added.changes.len());
+    }
+}
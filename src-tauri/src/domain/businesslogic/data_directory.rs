@@ -0,0 +1,121 @@
+use std::{fs, path::{Path, PathBuf}, sync::RwLock};
+
+use crate::domain::error::AppError;
+use crate::domain::models::settings::Settings;
+use crate::tr;
+
+/// OSのアプリデータフォルダ・設定フォルダの下に作るフォルダ名
+pub const APP_DIRECTORY_NAME: &str = "task-manager-tauri";
+
+/// データフォルダの中で、アプリが管理するフォルダ
+const DATA_SUB_DIRECTORIES: [&str; 5] = ["files", "project", "archive", "backups", "audit"];
+
+/// 現在のデータフォルダ。起動時とset_data_directoryで設定する
+static DATA_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// 現在のデータフォルダを返す。まだ設定されていなければOSのアプリデータフォルダ
+pub fn get_data_root() -> PathBuf {
+    match DATA_ROOT.read() {
+        Ok(root) => root.clone().unwrap_or_else(default_data_root),
+        Err(_) => default_data_root(),
+    }
+}
+
+pub fn set_data_root(path: PathBuf) {
+    if let Ok(mut root) = DATA_ROOT.write() {
+        *root = Some(path);
+    }
+}
+
+/// OSのアプリデータフォルダ。取得できない環境では実行ファイルのフォルダ
+pub fn default_data_root() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIRECTORY_NAME))
+        .unwrap_or_else(fallback_app_directory)
+}
+
+/// 以前のバージョンが使っていた、実行ファイルのあるフォルダ。実行ファイルのパスを取得できなければNone
+pub fn legacy_data_root() -> Option<PathBuf> {
+    let exe_path = std::env::current_exe().ok()?;
+    exe_path.parent().map(Path::to_path_buf)
+}
+
+/// OSのフォルダを取得できない時に使うフォルダ。実行ファイルのフォルダ、それもなければ作業フォルダの下
+pub fn fallback_app_directory() -> PathBuf {
+    legacy_data_root().unwrap_or_else(|| {
+        std::env::current_dir()
+            .map(|dir| dir.join(APP_DIRECTORY_NAME))
+            .unwrap_or_else(|_| PathBuf::from(APP_DIRECTORY_NAME))
+    })
+}
+
+/// 設定からデータフォルダを決める。
+/// 設定がなく、以前のバージョンのデータだけがある時は、データが消えたように見えないよう実行ファイルのフォルダを使う。
+pub fn resolve_data_root(settings: &Settings) -> PathBuf {
+    if let Some(ref directory) = settings.data_directory {
+        return PathBuf::from(directory);
+    }
+    let default_root = default_data_root();
+    if !has_data(&default_root) {
+        if let Some(legacy_root) = legacy_data_root().filter(|root| has_data(root)) {
+            return legacy_root;
+        }
+    }
+    default_root
+}
+
+/// フォルダにアプリのデータがあるか？
+pub fn has_data(root: &Path) -> bool {
+    DATA_SUB_DIRECTORIES.iter().any(|dir| root.join(dir).exists())
+}
+
+/// データフォルダとして使えるか確認する。なければ作成する。
+pub fn validate_data_directory(path: &Path) -> Result<(), AppError> {
+    if !path.is_absolute() {
+        return Err(AppError::validation(tr!("data_directory.not_absolute", path.display())));
+    }
+    fs::create_dir_all(path).map_err(|e| AppError::io(tr!("file.create_directory_failed", path.display(), e)))?;
+    // 読み取り専用の場所を指定されていないか、実際に書き込んで確かめる
+    let probe_path = path.join(".write_test");
+    fs::write(&probe_path, b"").map_err(|e| AppError::io(tr!("data_directory.not_writable", path.display(), e)))?;
+    fs::remove_file(&probe_path)?;
+    Ok(())
+}
+
+/// データフォルダの中身を新しいフォルダにコピーする。元のデータは残す。
+pub fn migrate_data_directory(from: &Path, to: &Path) -> Result<(), AppError> {
+    if has_data(to) {
+        return Err(AppError::validation(tr!("data_directory.destination_not_empty", to.display())));
+    }
+    for dir in DATA_SUB_DIRECTORIES.iter() {
+        let source = from.join(dir);
+        if source.exists() {
+            if let Err(e) = copy_directory(&source, &to.join(dir)) {
+                // 途中までコピーしたものは消しておく
+                for dir in DATA_SUB_DIRECTORIES.iter() {
+                    let _ = fs::remove_dir_all(to.join(dir));
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// フォルダを中身ごとコピーする
+fn copy_directory(from: &Path, to: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(to).map_err(|e| AppError::io(tr!("file.create_directory_failed", to.display(), e)))?;
+    let entries = fs::read_dir(from).map_err(|e| AppError::io(tr!("file.read_directory_failed", from.display(), e)))?;
+    for entry in entries {
+        let entry = entry?;
+        let source = entry.path();
+        let destination = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_directory(&source, &destination)?;
+        } else {
+            fs::copy(&source, &destination)
+                .map_err(|e| AppError::io(tr!("file.copy_failed", source.display(), destination.display(), e)))?;
+        }
+    }
+    Ok(())
+}
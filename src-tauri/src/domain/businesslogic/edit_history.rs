@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use crate::domain::models::audit_log::AuditEntity;
+use crate::domain::models::client::Client;
+use crate::domain::models::project::Project;
+
+/// 元に戻せる数。古いものから捨てる
+const MAX_EDIT_HISTORY: usize = 50;
+
+/// 取り消せる変更。targetを書き戻すと変更を取り消せる。
+/// expectedは変更した直後の状態で、その後に他で変更されていたら書き戻さない。
+/// created_client_idは変更と一緒に追加したクライアントで、取り消す時に削除する
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Project { target: Box<Project>, expected: Box<Project>, created_client_id: Option<String> },
+    Client { target: Box<Client>, expected: Box<Client> },
+}
+
+impl Edit {
+    pub fn entity(&self) -> AuditEntity {
+        match self {
+            Edit::Project { .. } => AuditEntity::Project,
+            Edit::Client { .. } => AuditEntity::Client,
+        }
+    }
+
+    pub fn entity_id(&self) -> &str {
+        match self {
+            Edit::Project { expected, .. } => &expected.id,
+            Edit::Client { expected, .. } => &expected.id,
+        }
+    }
+}
+
+/// アプリを起動している間の、元に戻す・やり直しの履歴
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    undo_stack: VecDeque<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl EditHistory {
+    /// 新しい変更を記録する。新しく変更したら、やり直しの履歴は消す
+    pub fn record(&mut self, edit: Edit) {
+        self.push_undo(edit);
+        self.redo_stack.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Edit> {
+        self.undo_stack.pop_back()
+    }
+
+    /// やり直しの履歴を残したまま、元に戻せる変更を積む
+    pub fn push_undo(&mut self, edit: Edit) {
+        self.undo_stack.push_back(edit);
+        if self.undo_stack.len() > MAX_EDIT_HISTORY {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Edit> {
+        self.redo_stack.pop()
+    }
+
+    pub fn push_redo(&mut self, edit: Edit) {
+        self.redo_stack.push(edit);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// 記録していない方法(ゴミ箱やアーカイブなど)で変えたデータの履歴を捨てる。
+    /// 残しておくと、元に戻した時にその変更まで黙って巻き戻してしまう
+    pub fn forget(&mut self, entity: AuditEntity, entity_id: &str) {
+        let is_other = |edit: &Edit| edit.entity() != entity || edit.entity_id() != entity_id;
+        self.undo_stack.retain(is_other);
+        self.redo_stack.retain(is_other);
+    }
+}
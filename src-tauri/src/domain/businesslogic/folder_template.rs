@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::domain::error::{AppError, Entity};
+use crate::domain::models::folder_template::FolderTemplate;
+use crate::domain::models::project::Project;
+use crate::tr;
+
+use super::project_date::format_date;
+
+/// 標準のテンプレートのid。template_idがないプロジェクトもこのテンプレートで作られている
+pub const DEFAULT_FOLDER_TEMPLATE_ID: &str = "default";
+
+/// テンプレートの機能ができる前から使っているフォルダ構成
+pub fn default_folder_template() -> FolderTemplate {
+    FolderTemplate {
+        id: DEFAULT_FOLDER_TEMPLATE_ID.to_string(),
+        name: tr!("folder_template.default_name"),
+        directories: vec!["documents".to_string(), "deliverables".to_string(), "works".to_string()],
+        files: Vec::new(),
+    }
+}
+
+pub fn is_default_folder_template(id: &str) -> bool {
+    id.is_empty() || id == DEFAULT_FOLDER_TEMPLATE_ID
+}
+
+/// idのテンプレートを返す。idがなければ標準のテンプレートを返す
+pub fn resolve_folder_template(templates: Vec<FolderTemplate>, id: Option<&str>) -> Result<FolderTemplate, AppError> {
+    match id {
+        None => Ok(default_folder_template()),
+        Some(id) if is_default_folder_template(id) => Ok(default_folder_template()),
+        Some(id) => templates
+            .into_iter()
+            .find(|template| template.id == id)
+            .ok_or_else(|| AppError::not_found(Entity::FolderTemplate, id)),
+    }
+}
+
+/// テンプレートの名前とパスを確認する。パスはプロジェクトのフォルダの外を指してはいけない
+pub fn validate_folder_template(template: &FolderTemplate) -> Result<(), AppError> {
+    if template.name.trim().is_empty() {
+        return Err(AppError::validation(tr!("folder_template.empty_name")));
+    }
+    let mut paths = HashSet::new();
+    let all_paths = template.directories.iter().chain(template.files.iter().map(|file| &file.path));
+    for path in all_paths {
+        if !is_relative_path(path) {
+            return Err(AppError::validation(tr!("folder_template.invalid_path", path)));
+        }
+        if !paths.insert(normalize_path(path)) {
+            return Err(AppError::validation(tr!("folder_template.duplicate_path", path)));
+        }
+    }
+    Ok(())
+}
+
+/// プロジェクトのフォルダの中を指す相対パスか
+pub fn is_relative_path(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some() && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn normalize_path(path: &str) -> PathBuf {
+    Path::new(path).components().collect()
+}
+
+/// プロジェクトのフォルダの中でテンプレートが作る一番上のフォルダとファイル。削除する時はここから消す
+pub fn top_level_entries(template: &FolderTemplate, project: &Project) -> Vec<PathBuf> {
+    let mut entries = Vec::new();
+    let all_paths = template.directories.iter().chain(template.files.iter().map(|file| &file.path));
+    for path in all_paths {
+        let path = render_template_path(path, project);
+        if !is_relative_path(&path) {
+            continue;
+        }
+        if let Some(Component::Normal(name)) = Path::new(&path).components().next() {
+            let entry = PathBuf::from(name);
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+/// {{project_title}}などのプレースホルダーをプロジェクトの値に置き換える
+pub fn render_template_text(text: &str, project: &Project) -> String {
+    placeholders(project)
+        .iter()
+        .fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{{{}}}}}", name), value))
+}
+
+/// パスのプレースホルダーを置き換える。値に区切り文字が入っていてもフォルダが増えないように'_'にする
+pub fn render_template_path(path: &str, project: &Project) -> String {
+    placeholders(project).iter().fold(path.to_string(), |path, (name, value)| {
+        let value = value.replace(['/', '\\'], "_");
+        path.replace(&format!("{{{{{}}}}}", name), &value)
+    })
+}
+
+fn placeholders(project: &Project) -> Vec<(&'static str, String)> {
+    vec![
+        ("project_id", project.id.clone()),
+        ("project_title", project.title.clone()),
+        ("client_name", project.client.name.clone()),
+        ("contact_person", project.client.contact_person.clone()),
+        ("order_date", format_date(&project.order_date)),
+        ("due_date", format_date(&project.due_date)),
+    ]
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use encoding_rs::SHIFT_JIS;
+
+use crate::domain::error::AppError;
+use crate::tr;
+
+use super::project_date::format_date;
+
+/// 読み込んだ表。1行目を見出しとして扱う
+#[derive(Debug, Clone, Default)]
+pub struct ImportTable {
+    pub sheet_names: Vec<String>, // csvの時は空
+    pub columns: Vec<String>,
+    pub rows: Vec<TableRow>, // 空の行は含めない
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableRow {
+    pub row_number: usize, // ファイルでの行番号。1から始まる
+    pub values: Vec<String>, // 列が足りない行は空文字で埋める
+}
+
+impl ImportTable {
+    /// 見出しがcolumnの列の番号
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|name| name == column.trim())
+    }
+}
+
+/// csvまたは表計算ソフトのファイルを読み込む。sheet_nameを省略したら最初のシートを読む
+pub fn read_import_table(path: &Path, sheet_name: Option<&str>) -> Result<ImportTable, AppError> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let table = match extension.as_str() {
+        "csv" | "txt" => read_csv_table(path)?,
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => read_workbook_table(path, sheet_name)?,
+        _ => return Err(AppError::validation(tr!("import.unsupported_file", path.display()))),
+    };
+    if table.columns.iter().all(|column| column.is_empty()) {
+        return Err(AppError::validation(tr!("import.empty_file", path.display())));
+    }
+    Ok(table)
+}
+
+/// csvを読み込む。UTF-8として読めなければ、Excelで保存したcsvに多いShift_JISとして読む
+fn read_csv_table(path: &Path) -> Result<ImportTable, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(tr!("import.read_failed", path.display(), e)))?;
+    let bytes = bytes.strip_prefix("\u{feff}".as_bytes()).unwrap_or(&bytes);
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => SHIFT_JIS.decode(bytes).0.into_owned(),
+    };
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(text.as_bytes());
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::parse(tr!("import.read_failed", path.display(), e)))?;
+        // 改行を含む項目があると何件目とファイルの行がずれるので、その行が始まるファイルの行番号を使う
+        let row_number = record.position().map(|position| record_line(&text, position)).unwrap_or_default();
+        records.push((row_number, record.iter().map(|value| value.trim().to_string()).collect()));
+    }
+    Ok(to_table(Vec::new(), records))
+}
+
+/// 記録の位置は読み飛ばした空行の前を指すので、空行の分だけ進める
+fn record_line(text: &str, position: &csv::Position) -> usize {
+    let skipped_lines = text
+        .get(position.byte() as usize..)
+        .map(|rest| rest.chars().take_while(|c| *c == '\r' || *c == '\n').filter(|c| *c == '\n').count())
+        .unwrap_or(0);
+    position.line() as usize + skipped_lines
+}
+
+fn read_workbook_table(path: &Path, sheet_name: Option<&str>) -> Result<ImportTable, AppError> {
+    let mut workbook = open_workbook_auto(path)?;
+    let sheet_names = workbook.sheet_names().to_vec();
+    let sheet_name = match sheet_name {
+        Some(name) if sheet_names.iter().any(|sheet| sheet == name) => name.to_string(),
+        Some(name) => return Err(AppError::validation(tr!("import.sheet_not_found", name))),
+        None => sheet_names.first().cloned().ok_or_else(|| AppError::validation(tr!("import.empty_file", path.display())))?,
+    };
+    let range = workbook.worksheet_range(&sheet_name)?;
+    // 表が1行目から始まっていない時のために、行番号は表の開始位置から数える
+    let first_row = range.start().map(|(row, _)| row as usize).unwrap_or(0);
+    let records = range
+        .rows()
+        .enumerate()
+        .map(|(index, row)| (first_row + index + 1, row.iter().map(cell_to_string).collect()))
+        .collect();
+    Ok(to_table(sheet_names, records))
+}
+
+/// セルを文字列にする。日付のセルは保存と同じYYYY-MM-DDにする
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::DateTime(_) | Data::DateTimeIso(_) => cell.as_date().map(|date| format_date(&date)).unwrap_or_else(|| cell.to_string()),
+        other => other.to_string().trim().to_string(),
+    }
+}
+
+fn to_table(sheet_names: Vec<String>, records: Vec<(usize, Vec<String>)>) -> ImportTable {
+    let mut records = records.into_iter().filter(|(_, values)| values.iter().any(|value| !value.is_empty()));
+    let columns = records.next().map(|(_, values)| values).unwrap_or_default();
+    let rows = records
+        .map(|(row_number, mut values)| {
+            if values.len() < columns.len() {
+                values.resize(columns.len(), String::new());
+            }
+            TableRow { row_number, values }
+        })
+        .collect();
+    ImportTable { sheet_names, columns, rows }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(directory: &tempfile::TempDir, name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = directory.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn csv_row_number_is_file_line_with_multiline_field() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = write_file(&directory, "import.csv", "題名,説明\n案件A,\"1行目\n2行目\"\n\n\r\n案件B,説明\n".as_bytes());
+
+        let table = read_import_table(&path, None).unwrap();
+
+        assert_eq!(table.columns, vec!["題名", "説明"]);
+        assert_eq!(table.rows.iter().map(|row| row.row_number).collect::<Vec<usize>>(), vec![2, 6]);
+        assert_eq!(table.rows[0].values, vec!["案件A", "1行目\n2行目"]);
+    }
+
+    #[test]
+    fn shift_jis_csv_is_decoded_and_short_rows_are_padded() {
+        let directory = tempfile::tempdir().unwrap();
+        let (bytes, _, _) = SHIFT_JIS.encode("題名,説明\n案件A\n");
+        let path = write_file(&directory, "import.csv", &bytes);
+
+        let table = read_import_table(&path, None).unwrap();
+
+        assert_eq!(table.columns, vec!["題名", "説明"]);
+        assert_eq!(table.rows[0].values, vec!["案件A", ""]);
+        assert!(table.sheet_names.is_empty());
+    }
+
+    #[test]
+    fn unsupported_extension_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = write_file(&directory, "import.json", b"{}");
+        assert!(read_import_table(&path, None).is_err());
+    }
+}
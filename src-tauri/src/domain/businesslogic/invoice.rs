@@ -0,0 +1,288 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::domain::businesslogic::project_date::format_date;
+use crate::domain::businesslogic::time_tracking::duration_seconds;
+use crate::domain::error::AppError;
+use crate::domain::models::client::Client;
+use crate::domain::models::invoice::{Invoice, InvoiceLineItem, LineItemKind};
+use crate::domain::models::project::Project;
+use crate::domain::models::time_entry::TimeEntry;
+use crate::tr;
+
+/// 金額は浮動小数点の誤差が出ないように整数で計算する。
+/// 数量は小数第2位まで(100分の1単位)、税率は0.01%単位(1万分の1)の整数にしてから使う
+const QUANTITY_SCALE: i64 = 100;
+const TAX_RATE_SCALE: i64 = 10_000;
+
+fn quantity_hundredths(item: &InvoiceLineItem) -> i64 {
+    (item.quantity * QUANTITY_SCALE as f64).round() as i64
+}
+
+/// 税率を0.01%単位の整数にする。10%なら1000
+pub fn tax_rate_basis_points(invoice: &Invoice) -> i64 {
+    (invoice.tax_rate * TAX_RATE_SCALE as f64).round() as i64
+}
+
+/// 明細の金額。1円未満は四捨五入
+pub fn line_item_amount(item: &InvoiceLineItem) -> i64 {
+    (quantity_hundredths(item) * item.unit_price + QUANTITY_SCALE / 2) / QUANTITY_SCALE
+}
+
+pub fn subtotal(invoice: &Invoice) -> i64 {
+    invoice.line_items.iter().map(line_item_amount).sum()
+}
+
+/// 消費税額。小計に税率をかけ、1円未満は切り捨て
+pub fn tax_amount(invoice: &Invoice) -> i64 {
+    subtotal(invoice) * tax_rate_basis_points(invoice) / TAX_RATE_SCALE
+}
+
+pub fn total(invoice: &Invoice) -> i64 {
+    subtotal(invoice) + tax_amount(invoice)
+}
+
+/// 浮動小数点の値が、scale分の1単位の整数にちょうどなるか
+fn is_whole_units(value: f64, scale: i64) -> bool {
+    let scaled = value * scale as f64;
+    (scaled - scaled.round()).abs() < 1e-6
+}
+
+pub fn validate_invoice(invoice: &Invoice) -> Result<(), AppError> {
+    if !(0.0..=1.0).contains(&invoice.tax_rate) || !is_whole_units(invoice.tax_rate, TAX_RATE_SCALE) {
+        return Err(AppError::validation(tr!("invoice.invalid_tax_rate", invoice.tax_rate)));
+    }
+    if invoice.line_items.is_empty() {
+        return Err(AppError::validation(tr!("invoice.no_line_items")));
+    }
+    if invoice.line_items.iter().any(|item| item.quantity < 0.0 || item.unit_price < 0) {
+        return Err(AppError::validation(tr!("invoice.negative_line_item")));
+    }
+    if let Some(item) = invoice.line_items.iter().find(|item| !is_whole_units(item.quantity, QUANTITY_SCALE)) {
+        return Err(AppError::validation(tr!("invoice.invalid_quantity", item.quantity)));
+    }
+    if invoice.payment_due_date < invoice.issue_date {
+        return Err(AppError::validation(tr!("invoice.due_before_issue")));
+    }
+    Ok(())
+}
+
+/// 発行年ごとの連番で次の請求書番号を作る (INV-2024-0001)
+pub fn next_invoice_number(invoice_numbers: &[String], issue_date: &NaiveDate) -> String {
+    let prefix = format!("INV-{}-", issue_date.year());
+    let last_number = invoice_numbers
+        .iter()
+        .filter_map(|invoice_number| invoice_number.strip_prefix(&prefix))
+        .filter_map(|number| number.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("{}{:04}", prefix, last_number + 1)
+}
+
+/// 保存する請求書に番号を振る。番号が空なら次の番号にし、入っていれば既存の番号と重ならないか確かめる。
+/// 他のウィンドウと同じ番号にならないように、保存先がロックの中で呼ぶ
+pub fn assign_invoice_number(invoice_numbers: &[String], invoice: &mut Invoice) -> Result<(), AppError> {
+    if invoice.invoice_number.is_empty() {
+        invoice.invoice_number = next_invoice_number(invoice_numbers, &invoice.issue_date);
+    } else if invoice_numbers.contains(&invoice.invoice_number) {
+        return Err(AppError::validation(tr!("invoice.duplicate_number", invoice.invoice_number)));
+    }
+    Ok(())
+}
+
+/// 作業時間の記録から時間単価の明細を作る。時間は小数第2位までにする
+pub fn hourly_line_item(entries: &[TimeEntry], hourly_rate: i64) -> InvoiceLineItem {
+    let total_seconds: i64 = entries.iter().map(duration_seconds).sum();
+    let hours = (total_seconds as f64 / 3600.0 * 100.0).round() / 100.0;
+    InvoiceLineItem {
+        description: tr!("invoice.hours_line_item"),
+        kind: LineItemKind::Hourly,
+        quantity: hours,
+        unit_price: hourly_rate,
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 0.01%単位の税率を表示用の百分率にする。1000なら"10"、825なら"8.25"
+fn format_tax_percent(basis_points: i64) -> String {
+    let percent = format!("{}.{:02}", basis_points / 100, basis_points % 100);
+    percent.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn format_yen(amount: i64) -> String {
+    let digits = amount.abs().to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{}¥{}", if amount < 0 { "-" } else { "" }, grouped)
+}
+
+/// 請求先の名前。請求先が設定されていなければクライアント名
+fn billing_name(client: &Client) -> &str {
+    if client.billing.billing_name.is_empty() { &client.name } else { &client.billing.billing_name }
+}
+
+/// 請求先の住所。請求先が設定されていなければクライアントの住所
+fn billing_address(client: &Client) -> Option<&str> {
+    [&client.billing.billing_address, &client.address]
+        .into_iter()
+        .find(|address| !address.is_empty())
+        .map(|address| address.as_str())
+}
+
+/// 請求書をHTMLにする。ブラウザから印刷すればPDFにもできる
+pub fn render_invoice_html(invoice: &Invoice, project: &Project, client: &Client) -> String {
+    let rows = invoice
+        .line_items
+        .iter()
+        .map(|item| {
+            let quantity = match item.kind {
+                LineItemKind::Hourly => format!("{} 時間", item.quantity),
+                LineItemKind::Fixed => format!("{}", item.quantity),
+            };
+            format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape_html(&item.description),
+                quantity,
+                format_yen(item.unit_price),
+                format_yen(line_item_amount(item))
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>請求書 {number}</title>
+<style>
+body {{ font-family: sans-serif; margin: 40px; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #999; padding: 6px 10px; }}
+.number {{ text-align: right; }}
+</style>
+</head>
+<body>
+<h1>請求書</h1>
+<p>請求書番号: {number}<br>発行日: {issue_date}<br>お支払期限: {payment_due_date}</p>
+<p>{client_name} 御中<br>{client_address}ご担当: {contact_person} 様</p>
+<p>件名: {project_title}</p>
+<table>
+<thead><tr><th>内容</th><th>数量</th><th>単価</th><th>金額</th></tr></thead>
+<tbody>
+{rows}
+</tbody>
+<tfoot>
+<tr><td colspan="3">小計</td><td class="number">{subtotal}</td></tr>
+<tr><td colspan="3">消費税 ({tax_percent}%)</td><td class="number">{tax}</td></tr>
+<tr><td colspan="3">合計</td><td class="number">{total}</td></tr>
+</tfoot>
+</table>
+</body>
+</html>
+"#,
+        number = escape_html(&invoice.invoice_number),
+        issue_date = format_date(&invoice.issue_date),
+        payment_due_date = format_date(&invoice.payment_due_date),
+        client_name = escape_html(billing_name(client)),
+        client_address = billing_address(client).map(|address| format!("{}<br>", escape_html(address))).unwrap_or_default(),
+        contact_person = escape_html(&client.contact_person),
+        project_title = escape_html(&project.title),
+        rows = rows,
+        subtotal = format_yen(subtotal(invoice)),
+        tax_percent = format_tax_percent(tax_rate_basis_points(invoice)),
+        tax = format_yen(tax_amount(invoice)),
+        total = format_yen(total(invoice)),
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::date;
+
+    fn sample_invoice(invoice_number: &str, tax_rate: f64, line_items: Vec<InvoiceLineItem>) -> Invoice {
+        Invoice {
+            id: String::new(),
+            invoice_number: invoice_number.to_string(),
+            project_id: "1".to_string(),
+            client_id: "1".to_string(),
+            issue_date: date("2024-04-30"),
+            payment_due_date: date("2024-05-31"),
+            line_items,
+            tax_rate,
+            file_path: None,
+        }
+    }
+
+    fn item(kind: LineItemKind, quantity: f64, unit_price: i64) -> InvoiceLineItem {
+        InvoiceLineItem { description: "作業".to_string(), kind, quantity, unit_price }
+    }
+
+    #[test]
+    fn totals_use_integer_arithmetic() {
+        // 0.29 * 100 は浮動小数点だと 28.999... になる
+        let invoice = sample_invoice("INV-2024-0001", 0.29, vec![item(LineItemKind::Fixed, 1.0, 100)]);
+        assert_eq!(tax_amount(&invoice), 29);
+
+        // 0.35 * 10 は浮動小数点だと 3.4999... になる
+        let invoice = sample_invoice("INV-2024-0001", 0.1, vec![item(LineItemKind::Hourly, 0.35, 10), item(LineItemKind::Fixed, 1.0, 12345)]);
+        assert_eq!(subtotal(&invoice), 4 + 12345);
+        assert_eq!(tax_amount(&invoice), 1234);
+        assert_eq!(total(&invoice), 12349 + 1234);
+    }
+
+    #[test]
+    fn validate_invoice_rejects_fractional_units() {
+        let valid = sample_invoice("INV-2024-0001", 0.0825, vec![item(LineItemKind::Hourly, 1.25, 5000)]);
+        assert!(validate_invoice(&valid).is_ok());
+        assert_eq!(format_tax_percent(tax_rate_basis_points(&valid)), "8.25");
+
+        let invalid_rate = sample_invoice("INV-2024-0001", 0.08255, vec![item(LineItemKind::Fixed, 1.0, 5000)]);
+        assert!(matches!(validate_invoice(&invalid_rate), Err(AppError::Validation(_))));
+        let invalid_quantity = sample_invoice("INV-2024-0001", 0.1, vec![item(LineItemKind::Hourly, 1.255, 5000)]);
+        assert!(matches!(validate_invoice(&invalid_quantity), Err(AppError::Validation(_))));
+        let negative = sample_invoice("INV-2024-0001", 0.1, vec![item(LineItemKind::Fixed, 1.0, -1)]);
+        assert!(matches!(validate_invoice(&negative), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn next_invoice_number_counts_per_year() {
+        let invoice_numbers = ["INV-2023-0007", "INV-2024-0002", "INV-2024-0010", "手入力"].map(str::to_string);
+        assert_eq!(next_invoice_number(&invoice_numbers, &date("2024-12-31")), "INV-2024-0011");
+        assert_eq!(next_invoice_number(&invoice_numbers, &date("2025-01-01")), "INV-2025-0001");
+        assert_eq!(next_invoice_number(&[], &date("2024-01-01")), "INV-2024-0001");
+    }
+
+    #[test]
+    fn assign_invoice_number_fills_blank_and_rejects_duplicate() {
+        let invoice_numbers = vec!["INV-2024-0001".to_string()];
+        let mut invoice = sample_invoice("", 0.1, Vec::new());
+        assign_invoice_number(&invoice_numbers, &mut invoice).unwrap();
+        assert_eq!(invoice.invoice_number, "INV-2024-0002");
+
+        let mut duplicate = sample_invoice("INV-2024-0001", 0.1, Vec::new());
+        assert!(matches!(assign_invoice_number(&invoice_numbers, &mut duplicate), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn format_tax_percent_trims_zeros() {
+        assert_eq!(format_tax_percent(1000), "10");
+        assert_eq!(format_tax_percent(850), "8.5");
+        assert_eq!(format_tax_percent(0), "0");
+    }
+}
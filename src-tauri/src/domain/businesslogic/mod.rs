@@ -1 +1,15 @@
-pub mod project_file;
\ No newline at end of file
+pub mod project_file;
+pub mod project_date;
+pub mod project_status;
+pub mod task_progress;
+pub mod time_tracking;
+pub mod invoice;
+pub mod data_directory;
+pub mod text_extraction;
+pub mod search_index;
+pub mod folder_template;
+pub mod project_archive;
+pub mod zip_directory;
+pub mod audit_log;
+pub mod edit_history;
+pub mod import_table;
\ No newline at end of file
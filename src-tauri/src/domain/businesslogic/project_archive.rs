@@ -0,0 +1,57 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tempfile::NamedTempFile;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::domain::error::AppError;
+use crate::domain::models::project::Project;
+use crate::domain::models::project_archive::ProjectArchive;
+use crate::tr;
+
+use super::data_directory::get_data_root;
+use super::zip_directory::{add_directory_to_zip, extract_zip_directory, read_zip_text, zip_options};
+
+/// アーカイブの中のプロジェクトの情報のファイル名
+const METADATA_FILE_NAME: &str = "metadata.json";
+/// アーカイブの中でプロジェクトのフォルダを入れる場所
+const FOLDER_ENTRY_PREFIX: &str = "folder";
+
+// アーカイブのファイルを置く場所
+pub fn get_archive_path() -> PathBuf {
+    get_data_root().join("archive/")
+}
+
+/// プロジェクトのアーカイブのファイルのパス。フォルダ名は変えられるのでidで決める
+pub fn get_archive_file_path(project:&Project) -> PathBuf {
+    get_archive_path().join(format!("project_{}.zip", project.id))
+}
+
+/// プロジェクトの情報とフォルダをzipにまとめる。途中で失敗しても書きかけのファイルが残らないように一時ファイルに書いてから置き換える
+pub fn write_project_archive(archive_file_path:&Path, archive:&ProjectArchive, project_directory_path:&Path) -> Result<(), AppError> {
+    let archive_directory = archive_file_path.parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", archive_file_path.display())))?;
+    fs::create_dir_all(archive_directory).map_err(|e| AppError::io(tr!("file.create_directory_failed", archive_directory.display(), e)))?;
+    let temp_file = NamedTempFile::new_in(archive_directory)?;
+    let mut writer = ZipWriter::new(temp_file.reopen()?);
+    writer.start_file(METADATA_FILE_NAME, zip_options())?;
+    writer.write_all(&serde_json::to_vec_pretty(archive)?)?;
+    if archive.includes_folder {
+        add_directory_to_zip(&mut writer, project_directory_path, FOLDER_ENTRY_PREFIX, |_| false)?;
+    }
+    writer.finish()?;
+    temp_file.persist(archive_file_path)?;
+    Ok(())
+}
+
+/// アーカイブからプロジェクトの情報を読み込む
+pub fn read_project_archive(archive_file_path:&Path) -> Result<ProjectArchive, AppError> {
+    let mut zip = ZipArchive::new(File::open(archive_file_path)?)?;
+    Ok(serde_json::from_str(&read_zip_text(&mut zip, METADATA_FILE_NAME)?)?)
+}
+
+/// アーカイブの中のプロジェクトのフォルダをdestinationに展開する
+pub fn extract_project_folder(archive_file_path:&Path, destination:&Path) -> Result<(), AppError> {
+    let mut zip = ZipArchive::new(File::open(archive_file_path)?)?;
+    extract_zip_directory(&mut zip, FOLDER_ENTRY_PREFIX, destination)
+}
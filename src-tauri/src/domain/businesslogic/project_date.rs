@@ -0,0 +1,124 @@
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::domain::{error::AppError, models::project::Project};
+use crate::tr;
+
+/// 受け付ける日付の書式。先頭のISO-8601形式を保存とフロントエンドへの受け渡しに使う。
+/// 2つ目以降は古いデータ用。以前のフロントエンドはロケールを指定せずにtoLocaleDateStringを使っていたので、
+/// OSの言語によって 2024/1/5 (ja-JP)、1/5/2024 (en-US)、5.1.2024 (de-DE) のように書式が違う。
+/// 日と月の順番が逆の 5/1/2024 (en-GB) は en-US と区別できないので、en-US として読む
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%d.%m.%Y"];
+
+/// 日付の文字列を日付型に変換する。fieldはエラーメッセージに出す項目名
+pub fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    let value = value.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .ok_or_else(|| AppError::parse(tr!("date.invalid_date", field, value)))
+}
+
+/// 空文字はNoneとして扱う
+pub fn parse_optional_date(field: &str, value: Option<&str>) -> Result<Option<NaiveDate>, AppError> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => parse_date(field, value).map(Some),
+    }
+}
+
+pub fn format_date(date: &NaiveDate) -> String {
+    date.format(DATE_FORMATS[0]).to_string()
+}
+
+/// RFC3339形式の日時を変換する
+pub fn parse_datetime(field: &str, value: &str) -> Result<DateTime<Local>, AppError> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|datetime| datetime.with_timezone(&Local))
+        .map_err(|_| AppError::parse(tr!("date.invalid_datetime", field, value)))
+}
+
+pub fn parse_optional_datetime(field: &str, value: Option<&str>) -> Result<Option<DateTime<Local>>, AppError> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => parse_datetime(field, value).map(Some),
+    }
+}
+
+pub fn format_datetime(datetime: &DateTime<Local>) -> String {
+    datetime.to_rfc3339()
+}
+
+/// 納期や完了日が受注日より前になっていないか確認する
+pub fn validate_project_dates(project: &Project) -> Result<(), AppError> {
+    if project.due_date < project.order_date {
+        return Err(AppError::validation(tr!(
+            "project.due_before_order",
+            format_date(&project.due_date),
+            format_date(&project.order_date)
+        )));
+    }
+    if let Some(completion_date) = project.completion_date {
+        if completion_date < project.order_date {
+            return Err(AppError::validation(tr!(
+                "project.completed_before_order",
+                format_date(&completion_date),
+                format_date(&project.order_date)
+            )));
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::{date, sample_client, sample_project};
+
+    #[test]
+    fn parse_date_accepts_iso_and_legacy_formats() {
+        assert_eq!(parse_date("order_date", "2024-01-05").unwrap(), date("2024-01-05"));
+        assert_eq!(parse_date("order_date", " 2024/1/5 ").unwrap(), date("2024-01-05"));
+        assert_eq!(parse_date("order_date", "2024/01/05").unwrap(), date("2024-01-05"));
+        assert_eq!(parse_date("order_date", "1/5/2024").unwrap(), date("2024-01-05"));
+        assert_eq!(parse_date("order_date", "5.1.2024").unwrap(), date("2024-01-05"));
+    }
+
+    #[test]
+    fn parse_date_rejects_invalid_values() {
+        assert!(matches!(parse_date("order_date", ""), Err(AppError::Parse(_))));
+        assert!(matches!(parse_date("order_date", "2024-02-30"), Err(AppError::Parse(_))));
+        assert!(matches!(parse_date("order_date", "13/5/2024"), Err(AppError::Parse(_))));
+        assert!(matches!(parse_date("order_date", "来週"), Err(AppError::Parse(_))));
+    }
+
+    #[test]
+    fn parse_optional_date_treats_blank_as_none() {
+        assert_eq!(parse_optional_date("completion_date", None).unwrap(), None);
+        assert_eq!(parse_optional_date("completion_date", Some("  ")).unwrap(), None);
+        assert_eq!(parse_optional_date("completion_date", Some("2024/4/1")).unwrap(), Some(date("2024-04-01")));
+    }
+
+    #[test]
+    fn format_date_round_trips() {
+        let value = date("2024-12-31");
+        assert_eq!(parse_date("due_date", &format_date(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn validate_project_dates_checks_order() {
+        let mut project = sample_project("案件A", sample_client("1", "取引先"));
+        assert!(validate_project_dates(&project).is_ok());
+
+        project.due_date = date("2024-03-31");
+        assert!(matches!(validate_project_dates(&project), Err(AppError::Validation(_))));
+
+        project.due_date = date("2024-04-30");
+        project.completion_date = Some(date("2024-03-31"));
+        assert!(matches!(validate_project_dates(&project), Err(AppError::Validation(_))));
+
+        // 受注日と同じ日は受け付ける
+        project.completion_date = Some(date("2024-04-01"));
+        assert!(validate_project_dates(&project).is_ok());
+    }
+}
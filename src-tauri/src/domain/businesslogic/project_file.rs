@@ -1,39 +1,69 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::{Component, Path, PathBuf}};
 
+use crate::domain::error::AppError;
+use crate::domain::models::folder_template::FolderTemplate;
 use crate::domain::models::project::Project;
+use crate::tr;
+
+use super::data_directory::get_data_root;
+use super::folder_template::{is_relative_path, render_template_path, render_template_text, top_level_entries};
 
 // アプリが作成するプロジェクトのファイルのパス
 pub fn get_project_manage_path() -> PathBuf {
-    // 現在の実行ファイルのパスを取得
-    let mut exe_path = std::env::current_exe().expect("Failed to get current exe path");
-    
-    // 実行ファイルがあるディレクトリに移動
-    exe_path.pop();
-    
-    exe_path.push("project/");
-    
-    exe_path
+    get_data_root().join("project/")
 }
 
-// project用のディレクトリ作成
-pub fn create_project_directories(project_directory_path:PathBuf)-> Result<(), String> {
-    let directories = vec!["documents", "deliverables", "works"];
-
-    for dir in &directories {
-        let dir_path = project_directory_path.join(dir);
-        fs::create_dir_all(&dir_path).map_err(|e| format!("Failed to create directory '{}': {}", dir_path.display(), e))?;
+// project用のディレクトリ作成。テンプレートのフォルダとファイルを作る
+pub fn create_project_directories(project_directory_path:PathBuf, template:&FolderTemplate, project:&Project)-> Result<(), AppError> {
+    fs::create_dir_all(&project_directory_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", project_directory_path.display(), e)))?;
+    for dir in template.directories.iter() {
+        let dir_path = project_directory_path.join(render_template_relative_path(dir, project)?);
+        fs::create_dir_all(&dir_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", dir_path.display(), e)))?;
+    }
+    for file in template.files.iter() {
+        let file_path = project_directory_path.join(render_template_relative_path(&file.path, project)?);
+        if let Some(parent_path) = file_path.parent() {
+            fs::create_dir_all(parent_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", parent_path.display(), e)))?;
+        }
+        fs::write(&file_path, render_template_text(&file.contents, project))
+            .map_err(|e| AppError::io(tr!("file.write_failed", file_path.display(), e)))?;
     }
 
     Ok(())
 }
 
+// プレースホルダーを置き換えた後のパスも、プロジェクトのフォルダの中を指しているか確かめる
+fn render_template_relative_path(path:&str, project:&Project) -> Result<String, AppError> {
+    let rendered = render_template_path(path, project);
+    if !is_relative_path(&rendered) {
+        return Err(AppError::validation(tr!("folder_template.invalid_path", rendered)));
+    }
+    Ok(rendered)
+}
+
 
 
+/// プロジェクト管理フォルダの直下にあるフォルダの名前。ゴミ箱などの隠しフォルダは含めない
+pub fn list_project_folder_names() -> Result<Vec<String>, AppError> {
+    let manage_path = get_project_manage_path();
+    let read_error = |e: std::io::Error| AppError::io(tr!("file.read_directory_failed", manage_path.display(), e));
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&manage_path).map_err(read_error)? {
+        let entry = entry.map_err(read_error)?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with('.') && entry.path().is_dir() {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
 // project用のディレクトリ名を変更
 pub fn rename_project_directory(
     old_project_directory_path: PathBuf,
     new_project_suffix: &str,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     // 古いディレクトリの親パスを取得
     if let Some(parent_path) = old_project_directory_path.parent() {
         // 新しいプロジェクトディレクトリのパスを作成
@@ -41,36 +71,276 @@ pub fn rename_project_directory(
         
         // ディレクトリ名の変更（移動）
         fs::rename(&old_project_directory_path, &new_project_directory_path)
-            .map_err(|e| format!("Failed to rename directory from '{}' to '{}': {}", old_project_directory_path.display(), new_project_directory_path.display(), e))?;
+            .map_err(|e| AppError::io(tr!("file.rename_directory_failed", old_project_directory_path.display(), new_project_directory_path.display(), e)))?;
     } else {
-        return Err("Failed to get parent directory of the old project directory".to_string());
+        return Err(AppError::io(tr!("file.project_parent_missing")));
     }
 
     Ok(())
 }
 
+// ゴミ箱に入れたプロジェクトのフォルダを置く場所
+pub fn get_trash_path() -> PathBuf {
+    get_project_manage_path().join(".trash")
+}
+
+/// ゴミ箱の中のプロジェクトフォルダのパス。
+/// 同じsuffixのプロジェクトが何度も削除されても衝突しないように、idを前につける。
+pub fn get_trash_directory_path(project:&Project) -> PathBuf{
+    get_trash_path().join(format!("{}_{}", project.id, get_folder_path_suffix(project)))
+}
+
+/// プロジェクトのフォルダが現在置かれている場所。ゴミ箱に入っていればゴミ箱の中を返す。
+pub fn get_current_project_directory_path(project:&Project) -> PathBuf{
+    match project.deleted_at {
+        Some(_) => get_trash_directory_path(project),
+        None => get_project_directory_path(&get_folder_path_suffix(project)),
+    }
+}
+
+/// ゴミ箱にもアーカイブにも入っていないプロジェクトのフォルダが、アプリの外で移動・削除されてなくなっているか
+pub fn is_project_folder_missing(project:&Project) -> bool {
+    project.deleted_at.is_none() && project.archived_at.is_none() && !get_current_project_directory_path(project).exists()
+}
+
+/// プロジェクトのdeliverablesフォルダにファイルを書き出し、そのパスを返す
+pub fn write_deliverable_file(project:&Project, file_name:&str, contents:&str) -> Result<PathBuf, AppError> {
+    let deliverables_path = get_current_project_directory_path(project).join("deliverables");
+    fs::create_dir_all(&deliverables_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", deliverables_path.display(), e)))?;
+    let file_path = deliverables_path.join(file_name);
+    fs::write(&file_path, contents).map_err(|e| AppError::io(tr!("file.write_failed", file_path.display(), e)))?;
+    Ok(file_path)
+}
+
+// project用のディレクトリを移動
+pub fn move_project_directory(from:PathBuf, to:PathBuf) -> Result<(), AppError> {
+    if let Some(parent_path) = to.parent() {
+        fs::create_dir_all(parent_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", parent_path.display(), e)))?;
+    }
+    fs::rename(&from, &to)
+        .map_err(|e| AppError::io(tr!("file.move_directory_failed", from.display(), to.display(), e)))
+}
+
 pub fn get_project_directory_path(project_folder_path_suffix:&str) -> PathBuf{
     let base_path = get_project_manage_path(); // 適切なパス取得関数を使用してください
     base_path.join(project_folder_path_suffix)
 }
 
 
-// project用のディレクトリ削除
-pub fn delete_project_directories(project_directory_path:PathBuf) ->Result<(),String>{
+// project用のディレクトリ削除。テンプレートで作ったものだけを消し、空になったらプロジェクトのフォルダも消す
+pub fn delete_project_directories(project_directory_path:PathBuf, template:&FolderTemplate, project:&Project) ->Result<(),AppError>{
 
-    let directories = vec!["documents", "deliverables", "works"];
-    for dir in directories.iter() {
-        let dir_path = project_directory_path.join(dir);
-        fs::remove_dir_all(&dir_path)
-            .map_err(|e| format!("Failed to remove directory '{}': {}", dir_path.display(), e))?;
+    for entry in top_level_entries(template, project) {
+        let entry_path = project_directory_path.join(entry);
+        if entry_path.is_dir() {
+            fs::remove_dir_all(&entry_path)
+                .map_err(|e| AppError::io(tr!("file.remove_directory_failed", entry_path.display(), e)))?;
+        } else if entry_path.exists() {
+            fs::remove_file(&entry_path)
+                .map_err(|e| AppError::io(tr!("file.remove_file_failed", entry_path.display(), e)))?;
+        }
+    }
+    let is_empty = fs::read_dir(&project_directory_path)
+        .map_err(|e| AppError::io(tr!("file.read_directory_failed", project_directory_path.display(), e)))?
+        .next()
+        .is_none();
+    if is_empty {
+        fs::remove_dir(&project_directory_path)
+            .map_err(|e| AppError::io(tr!("file.remove_directory_failed", project_directory_path.display(), e)))?;
     }
     Ok(())
 }
 
+// project用のディレクトリを中身ごと削除
+pub fn remove_project_directory(project_directory_path:PathBuf) ->Result<(),AppError>{
+    fs::remove_dir_all(&project_directory_path)
+        .map_err(|e| AppError::io(tr!("file.remove_directory_failed", project_directory_path.display(), e)))
+}
+
 ///  プロジェクトにすでにsuffixが登録されて入れば、それを返し、登録されていなければidを返す
 pub fn get_folder_path_suffix(project:&Project)->String{
     match  project.folder_path_suffix{
         Some(ref suffix)   => return suffix.clone(),
         None => return project.id.clone()  
     };
-}
\ No newline at end of file
+}
+
+/// プロジェクトのフォルダからの相対パスを実際のパスにする。空文字はプロジェクトのフォルダ自身。
+/// ".."や絶対パス、フォルダの外を指すシンボリックリンクでプロジェクトのフォルダの外に出られないようにする。
+pub fn resolve_project_file_path(project_directory_path:&Path, relative_path:&str) -> Result<PathBuf, AppError> {
+    let mut path = project_directory_path.to_path_buf();
+    for part in relative_path.split(['/', '\\']).filter(|part| !part.is_empty()) {
+        if !is_single_name(part) {
+            return Err(AppError::validation(tr!("file.outside_project", relative_path)));
+        }
+        path.push(part);
+    }
+
+    // シンボリックリンクをたどった先もプロジェクトのフォルダの中か確かめる。まだないパスは存在する親で確かめる
+    let root = fs::canonicalize(project_directory_path)
+        .map_err(|e| AppError::io(tr!("file.read_directory_failed", project_directory_path.display(), e)))?;
+    let existing = path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(project_directory_path);
+    let canonical = fs::canonicalize(existing).map_err(|e| AppError::io(tr!("file.read_directory_failed", existing.display(), e)))?;
+    if !canonical.starts_with(&root) {
+        return Err(AppError::validation(tr!("file.outside_project", relative_path)));
+    }
+    Ok(path)
+}
+
+/// ファイルやフォルダの新しい名前を確かめる。区切り文字を含む名前は別のフォルダへの移動になるので受け付けない
+pub fn validate_file_name(name:&str) -> Result<(), AppError> {
+    if !is_single_name(name) || name.contains(['/', '\\']) {
+        return Err(AppError::validation(tr!("file.invalid_name", name)));
+    }
+    Ok(())
+}
+
+// "."や".."ではない、区切りのない名前か
+fn is_single_name(name:&str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+/// 同じ名前のファイルがあれば "名前 (2).拡張子" のように番号をつけたパスを返す
+pub fn unique_file_path(directory:&Path, file_name:&str) -> PathBuf {
+    let candidate = directory.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let file_name_path = Path::new(file_name);
+    let stem = file_name_path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = file_name_path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|number| directory.join(format!("{} ({}){}", stem, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(candidate)
+}
+
+/// ファイルをフォルダごとコピーする。フォルダの中のシンボリックリンクは、外のフォルダや自分自身を指していることがあるのでコピーしない
+pub fn copy_path(from:&Path, to:&Path) -> Result<(), AppError> {
+    let metadata = fs::symlink_metadata(from).map_err(|e| AppError::io(tr!("file.copy_failed", from.display(), to.display(), e)))?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    if metadata.is_dir() {
+        fs::create_dir_all(to).map_err(|e| AppError::io(tr!("file.create_directory_failed", to.display(), e)))?;
+        let entries = fs::read_dir(from).map_err(|e| AppError::io(tr!("file.read_directory_failed", from.display(), e)))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| AppError::io(tr!("file.read_directory_failed", from.display(), e)))?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| AppError::io(tr!("file.copy_failed", from.display(), to.display(), e)))?;
+    Ok(())
+}
+
+/// ファイルやフォルダの名前を変える、または移動する。移動先に同じ名前があれば上書きしない
+pub fn move_path(from:&Path, to:&Path) -> Result<(), AppError> {
+    if to.exists() {
+        return Err(AppError::validation(tr!("file.already_exists", to.display())));
+    }
+    fs::rename(from, to).map_err(|e| AppError::io(tr!("file.move_failed", from.display(), to.display(), e)))
+}
+
+/// ファイルまたはフォルダを中身ごと削除する
+pub fn remove_path(path:&Path) -> Result<(), AppError> {
+    // シンボリックリンクはリンク先ではなくリンク自体を削除する
+    let is_directory = fs::symlink_metadata(path).map(|metadata| metadata.is_dir()).unwrap_or(false);
+    if is_directory {
+        fs::remove_dir_all(path).map_err(|e| AppError::io(tr!("file.remove_directory_failed", path.display(), e)))
+    } else {
+        fs::remove_file(path).map_err(|e| AppError::io(tr!("file.remove_file_failed", path.display(), e)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_project_file_path_stays_in_project() {
+        let directory = tempfile::tempdir().unwrap();
+        let project_path = directory.path().join("案件");
+        fs::create_dir_all(project_path.join("資料")).unwrap();
+
+        assert_eq!(resolve_project_file_path(&project_path, "").unwrap(), project_path);
+        assert_eq!(resolve_project_file_path(&project_path, "資料/見積.txt").unwrap(), project_path.join("資料").join("見積.txt"));
+        assert_eq!(resolve_project_file_path(&project_path, "資料\\新しいフォルダ/メモ.txt").unwrap(), project_path.join("資料").join("新しいフォルダ").join("メモ.txt"));
+        // 先頭の区切り文字は無視するので、絶対パスもプロジェクトのフォルダの中になる
+        assert_eq!(resolve_project_file_path(&project_path, "/etc/passwd").unwrap(), project_path.join("etc").join("passwd"));
+        for escape in ["..", "../other", "資料/../../other", "./資料"] {
+            let result = resolve_project_file_path(&project_path, escape);
+            assert!(matches!(result, Err(AppError::Validation(_))), "{}", escape);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_project_file_path_rejects_links_to_outside() {
+        let directory = tempfile::tempdir().unwrap();
+        let project_path = directory.path().join("案件");
+        fs::create_dir_all(project_path.join("資料")).unwrap();
+        fs::create_dir(directory.path().join("外")).unwrap();
+        std::os::unix::fs::symlink(directory.path().join("外"), project_path.join("外へのリンク")).unwrap();
+        std::os::unix::fs::symlink(project_path.join("資料"), project_path.join("資料へのリンク")).unwrap();
+
+        for escape in ["外へのリンク", "外へのリンク/メモ.txt"] {
+            let result = resolve_project_file_path(&project_path, escape);
+            assert!(matches!(result, Err(AppError::Validation(_))), "{}", escape);
+        }
+        // 中を指すリンクはたどってよい
+        assert!(resolve_project_file_path(&project_path, "資料へのリンク/メモ.txt").is_ok());
+    }
+
+    #[test]
+    fn validate_file_name_rejects_separators_and_dots() {
+        assert!(validate_file_name("見積書 (2).pdf").is_ok());
+        for name in ["", ".", "..", "a/b", "a\\b"] {
+            assert!(validate_file_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn unique_file_path_numbers_duplicates() {
+        let directory = tempfile::tempdir().unwrap();
+        assert_eq!(unique_file_path(directory.path(), "見積書.pdf"), directory.path().join("見積書.pdf"));
+        fs::write(directory.path().join("見積書.pdf"), "").unwrap();
+        fs::write(directory.path().join("見積書 (2).pdf"), "").unwrap();
+        assert_eq!(unique_file_path(directory.path(), "見積書.pdf"), directory.path().join("見積書 (3).pdf"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_path_skips_links_inside_folders() {
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("元");
+        fs::create_dir_all(source.join("資料")).unwrap();
+        fs::write(source.join("資料").join("メモ.txt"), "メモ").unwrap();
+        // 自分自身を指すリンクがあってもコピーが終わる
+        std::os::unix::fs::symlink(&source, source.join("資料").join("ループ")).unwrap();
+
+        let target = directory.path().join("先");
+        copy_path(&source, &target).unwrap();
+
+        assert_eq!(fs::read_to_string(target.join("資料").join("メモ.txt")).unwrap(), "メモ");
+        assert!(fs::symlink_metadata(target.join("資料").join("ループ")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remove_path_removes_link_not_target() {
+        let directory = tempfile::tempdir().unwrap();
+        let target = directory.path().join("外");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("メモ.txt"), "").unwrap();
+        let link = directory.path().join("リンク");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        remove_path(&link).unwrap();
+
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(target.join("メモ.txt").exists());
+    }
+}
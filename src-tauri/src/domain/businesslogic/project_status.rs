@@ -0,0 +1,160 @@
+use chrono::{Local, NaiveDate};
+
+use crate::domain::error::AppError;
+use crate::domain::models::project::{Project, ProjectStatus};
+use crate::domain::models::project_status_history::ProjectStatusHistory;
+use crate::tr;
+
+/// ステータスの遷移表。fromから遷移できるステータスを返す
+pub fn allowed_transitions(from: &ProjectStatus) -> &'static [ProjectStatus] {
+    use ProjectStatus::*;
+    match from {
+        NotStarted => &[InProgress, OnHold, Cancelled],
+        InProgress => &[OnHold, Delivered, Completed, Cancelled],
+        OnHold => &[InProgress, Cancelled],
+        // 納品後の修正対応で作業中に戻すことがある
+        Delivered => &[InProgress, Completed],
+        Completed => &[InProgress, Invoiced],
+        Invoiced => &[],
+        Cancelled => &[NotStarted],
+    }
+}
+
+pub fn can_transition(from: &ProjectStatus, to: &ProjectStatus) -> bool {
+    from == to || allowed_transitions(from).contains(to)
+}
+
+/// 完了日を持つステータスか
+fn has_completion_date(status: &ProjectStatus) -> bool {
+    matches!(status, ProjectStatus::Completed | ProjectStatus::Invoiced)
+}
+
+/// ステータスに合わせて完了日を設定・クリアする。
+/// 完了済みで完了日がなければ今日の日付を入れ、完了前のステータスなら完了日を消す。
+pub fn normalize_completion_date(project: &mut Project) {
+    if !has_completion_date(&project.status) {
+        project.completion_date = None;
+    } else if project.completion_date.is_none() {
+        project.completion_date = Some(Local::now().date_naive());
+    }
+}
+
+/// 納期を過ぎても納品・完了していないか
+pub fn is_overdue(project: &Project, today: NaiveDate) -> bool {
+    let finished = matches!(
+        project.status,
+        ProjectStatus::Delivered | ProjectStatus::Completed | ProjectStatus::Invoiced | ProjectStatus::Cancelled
+    );
+    !finished && project.due_date < today
+}
+
+/// 新規作成したプロジェクトの最初の履歴
+pub fn initial_status_history(project: &Project) -> ProjectStatusHistory {
+    ProjectStatusHistory {
+        project_id: project.id.clone(),
+        from_status: None,
+        to_status: project.status.clone(),
+        changed_at: Local::now(),
+    }
+}
+
+/// 更新前後のプロジェクトを比べてステータスの遷移を検証し、完了日をステータスに合わせる。
+/// ステータスが変わった場合は、保存すべき履歴を返す。
+pub fn apply_status_change(old_project: &Project, updated_project: &mut Project) -> Result<Option<ProjectStatusHistory>, AppError> {
+    if !can_transition(&old_project.status, &updated_project.status) {
+        return Err(AppError::validation(tr!(
+            "project.invalid_status_change",
+            format!("{:?}", old_project.status),
+            format!("{:?}", updated_project.status)
+        )));
+    }
+    Ok(record_status_change(old_project, updated_project))
+}
+
+/// 遷移表を確かめずに、完了日をステータスに合わせて履歴を返す。
+/// 元に戻す・やり直しでは、遷移表にない向き(請求済みから完了など)にも戻すのでこちらを使う
+pub fn record_status_change(old_project: &Project, updated_project: &mut Project) -> Option<ProjectStatusHistory> {
+    normalize_completion_date(updated_project);
+
+    if old_project.status == updated_project.status {
+        return None;
+    }
+    Some(ProjectStatusHistory {
+        project_id: updated_project.id.clone(),
+        from_status: Some(old_project.status.clone()),
+        to_status: updated_project.status.clone(),
+        changed_at: Local::now(),
+    })
+}
+
+/// 取り込むファイルに書かれたステータスを読む。英語の名前(InProgress, in progressなど)と日本語の表示名を受け付ける
+pub fn parse_status_label(label: &str) -> Option<ProjectStatus> {
+    use ProjectStatus::*;
+    let normalized = label.trim().to_lowercase().replace([' ', '_', '-'], "");
+    let status = match normalized.as_str() {
+        "notstarted" | "未着手" => NotStarted,
+        "inprogress" | "進行中" | "作業中" => InProgress,
+        "onhold" | "待機中" | "保留" => OnHold,
+        "delivered" | "納品済み" | "納品済" => Delivered,
+        "completed" | "完了" => Completed,
+        "invoiced" | "請求済み" | "請求済" => Invoiced,
+        "cancelled" | "canceled" | "キャンセル" | "中止" => Cancelled,
+        _ => return None,
+    };
+    Some(status)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::{date, sample_client, sample_project};
+
+    #[test]
+    fn transitions_follow_the_table() {
+        use ProjectStatus::*;
+        assert!(can_transition(&NotStarted, &NotStarted));
+        assert!(can_transition(&NotStarted, &InProgress));
+        assert!(can_transition(&Delivered, &InProgress));
+        assert!(can_transition(&Cancelled, &NotStarted));
+        assert!(!can_transition(&NotStarted, &Completed));
+        assert!(!can_transition(&Invoiced, &Completed));
+        assert!(!can_transition(&OnHold, &Delivered));
+    }
+
+    #[test]
+    fn apply_status_change_returns_history_only_when_status_changes() {
+        let old_project = sample_project("案件A", sample_client("1", "取引先"));
+        let mut updated_project = old_project.clone();
+        assert!(apply_status_change(&old_project, &mut updated_project).unwrap().is_none());
+
+        updated_project.status = ProjectStatus::InProgress;
+        let history = apply_status_change(&old_project, &mut updated_project).unwrap().unwrap();
+        assert_eq!(history.from_status, Some(ProjectStatus::NotStarted));
+        assert_eq!(history.to_status, ProjectStatus::InProgress);
+
+        updated_project.status = ProjectStatus::Invoiced;
+        assert!(matches!(apply_status_change(&old_project, &mut updated_project), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn record_status_change_skips_the_table_and_fixes_completion_date() {
+        let mut old_project = sample_project("案件A", sample_client("1", "取引先"));
+        old_project.status = ProjectStatus::Invoiced;
+        old_project.completion_date = Some(date("2024-04-20"));
+        let mut updated_project = old_project.clone();
+        updated_project.status = ProjectStatus::InProgress;
+
+        let history = record_status_change(&old_project, &mut updated_project).unwrap();
+        assert_eq!(history.from_status, Some(ProjectStatus::Invoiced));
+        assert_eq!(updated_project.completion_date, None);
+    }
+
+    #[test]
+    fn parse_status_label_accepts_english_and_japanese() {
+        assert_eq!(parse_status_label(" in progress "), Some(ProjectStatus::InProgress));
+        assert_eq!(parse_status_label("請求済み"), Some(ProjectStatus::Invoiced));
+        assert_eq!(parse_status_label("canceled"), Some(ProjectStatus::Cancelled));
+        assert_eq!(parse_status_label("不明"), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+/// 索引に入れたファイル
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexedFile {
+    pub project_id: String,
+    pub relative_path: String, // プロジェクトフォルダからのパス。区切りは"/"
+    pub modified: i64,         // 更新日時(UNIX秒)。変わっていなければ読み直さない
+    pub size: u64,
+    pub text: String,
+}
+
+/// (プロジェクトID, プロジェクトフォルダからのパス)
+pub type FileKey = (String, String);
+/// (更新日時, サイズ)
+pub type FileState = (i64, u64);
+
+/// ファイルの中身の転置索引。
+/// 日本語は単語に区切れないので、2文字ずつ(bigram)を見出しにし、候補を絞ってから本文で確かめる。
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "StoredSearchIndex")]
+pub struct SearchIndex {
+    files: HashMap<u32, IndexedFile>,
+    postings: HashMap<String, BTreeSet<u32>>,
+    next_id: u32,
+    // ファイルからidを引く表。filesから作れるので保存しない
+    #[serde(skip_serializing)]
+    ids: HashMap<FileKey, u32>,
+}
+
+/// 保存した索引。読み込んだ後にidを引く表を作り直す
+#[derive(Deserialize)]
+struct StoredSearchIndex {
+    files: HashMap<u32, IndexedFile>,
+    postings: HashMap<String, BTreeSet<u32>>,
+    next_id: u32,
+}
+
+impl From<StoredSearchIndex> for SearchIndex {
+    fn from(stored: StoredSearchIndex) -> Self {
+        let ids = stored
+            .files
+            .iter()
+            .map(|(id, file)| ((file.project_id.clone(), file.relative_path.clone()), *id))
+            .collect();
+        SearchIndex { files: stored.files, postings: stored.postings, next_id: stored.next_id, ids }
+    }
+}
+
+/// 検索に一致した場所
+#[derive(Debug, Clone)]
+pub struct SearchMatch<'a> {
+    pub file: &'a IndexedFile,
+    pub snippet: String,
+    pub hit_count: usize, // 本文に語が出てくる回数の合計。多いものほど上に並べる
+}
+
+/// 一致した語の前後に表示する文字数
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+impl SearchIndex {
+    /// 索引に入っているファイルの更新日時とサイズ。変わったファイルだけ読み直すのに使う
+    pub fn file_states(&self) -> HashMap<FileKey, FileState> {
+        self.files
+            .values()
+            .map(|file| ((file.project_id.clone(), file.relative_path.clone()), (file.modified, file.size)))
+            .collect()
+    }
+
+    /// ファイルを追加する。同じファイルが既にあれば置き換える
+    pub fn upsert(&mut self, file: IndexedFile) {
+        self.remove(&file.project_id, &file.relative_path);
+        let id = self.next_id;
+        self.next_id += 1;
+        for token in tokenize(&file.text) {
+            self.postings.entry(token).or_default().insert(id);
+        }
+        self.ids.insert((file.project_id.clone(), file.relative_path.clone()), id);
+        self.files.insert(id, file);
+    }
+
+    pub fn remove(&mut self, project_id: &str, relative_path: &str) {
+        let Some(id) = self.ids.remove(&(project_id.to_string(), relative_path.to_string())) else {
+            return;
+        };
+        if let Some(file) = self.files.remove(&id) {
+            for token in tokenize(&file.text) {
+                if let Some(ids) = self.postings.get_mut(&token) {
+                    ids.remove(&id);
+                    if ids.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 空白区切りのすべての語を含むファイルを返す
+    pub fn search(&self, query: &str) -> Vec<SearchMatch<'_>> {
+        let terms = query.split_whitespace().map(str::to_lowercase).collect::<Vec<String>>();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        self.candidates(&terms)
+            .into_iter()
+            .filter_map(|id| self.files.get(&id))
+            .filter_map(|file| {
+                let text = file.text.to_lowercase();
+                if !terms.iter().all(|term| text.contains(term.as_str())) {
+                    return None;
+                }
+                let hit_count = terms.iter().map(|term| text.matches(term.as_str()).count()).sum();
+                Some(SearchMatch { file, snippet: make_snippet(&file.text, &terms[0]), hit_count })
+            })
+            .collect()
+    }
+
+    // 見出しで候補を絞る。1文字の語は見出しがないので全ファイルが候補
+    fn candidates(&self, terms: &[String]) -> Vec<u32> {
+        let mut candidates: Option<BTreeSet<u32>> = None;
+        for token in terms.iter().flat_map(|term| tokenize(term)) {
+            let ids = self.postings.get(&token).cloned().unwrap_or_default();
+            candidates = Some(match candidates {
+                Some(current) => current.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        match candidates {
+            Some(ids) => ids.into_iter().collect(),
+            None => self.files.keys().copied().collect(),
+        }
+    }
+}
+
+/// 小文字にした2文字ずつの見出し。空白を含むものは除く
+fn tokenize(text: &str) -> BTreeSet<String> {
+    let chars = text.to_lowercase().chars().collect::<Vec<char>>();
+    chars
+        .windows(2)
+        .filter(|pair| !pair[0].is_whitespace() && !pair[1].is_whitespace())
+        .map(|pair| pair.iter().collect())
+        .collect()
+}
+
+/// 最初に一致した場所の前後を切り出す
+fn make_snippet(text: &str, term: &str) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    let lower = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect::<Vec<char>>();
+    let term = term.chars().collect::<Vec<char>>();
+    let position = lower.windows(term.len()).position(|window| window == term.as_slice()).unwrap_or(0);
+    let start = position.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (position + term.len() + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let snippet = chars[start..end].iter().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" ");
+    format!("{}{}{}", if start > 0 { "…" } else { "" }, snippet, if end < chars.len() { "…" } else { "" })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(project_id: &str, relative_path: &str, text: &str) -> IndexedFile {
+        IndexedFile {
+            project_id: project_id.to_string(),
+            relative_path: relative_path.to_string(),
+            modified: 0,
+            size: text.len() as u64,
+            text: text.to_string(),
+        }
+    }
+
+    fn found_paths(index: &SearchIndex, query: &str) -> Vec<String> {
+        let mut paths = index.search(query).into_iter().map(|found| found.file.relative_path.clone()).collect::<Vec<String>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn tokenize_makes_lowercase_bigrams_without_whitespace() {
+        let tokens = tokenize("見積 AB");
+        assert_eq!(tokens, ["ab", "見積"].iter().map(|token| token.to_string()).collect::<BTreeSet<String>>());
+        assert!(tokenize("a").is_empty());
+    }
+
+    #[test]
+    fn search_requires_all_terms() {
+        let mut index = SearchIndex::default();
+        index.upsert(file("1", "見積書.txt", "山田商店様 見積書 ロゴ制作"));
+        index.upsert(file("1", "請求書.txt", "山田商店様 請求書"));
+        index.upsert(file("2", "memo.md", "Logo design memo"));
+
+        assert_eq!(found_paths(&index, "山田商店"), vec!["見積書.txt", "請求書.txt"]);
+        assert_eq!(found_paths(&index, "山田 ロゴ"), vec!["見積書.txt"]);
+        assert_eq!(found_paths(&index, "LOGO"), vec!["memo.md"]);
+        // 1文字の語は見出しがないので本文で確かめる
+        assert_eq!(found_paths(&index, "様"), vec!["見積書.txt", "請求書.txt"]);
+        assert!(found_paths(&index, "").is_empty());
+        // 見出しはすべて含むが、続けては出てこない
+        assert!(found_paths(&index, "商店様見").is_empty());
+    }
+
+    #[test]
+    fn upsert_replaces_and_remove_forgets_file() {
+        let mut index = SearchIndex::default();
+        index.upsert(file("1", "memo.txt", "古い内容"));
+        index.upsert(file("1", "memo.txt", "新しい内容"));
+        assert!(found_paths(&index, "古い").is_empty());
+        assert_eq!(found_paths(&index, "新しい"), vec!["memo.txt"]);
+        assert_eq!(index.file_states().len(), 1);
+
+        index.remove("1", "memo.txt");
+        assert!(found_paths(&index, "内容").is_empty());
+        assert!(index.file_states().is_empty());
+        assert!(index.postings.is_empty());
+    }
+
+    #[test]
+    fn saved_index_can_still_replace_files() {
+        let mut index = SearchIndex::default();
+        index.upsert(file("1", "memo.txt", "古い内容"));
+        let json = serde_json::to_string(&index).unwrap();
+        let mut loaded = serde_json::from_str::<SearchIndex>(&json).unwrap();
+
+        loaded.upsert(file("1", "memo.txt", "新しい内容"));
+        assert!(found_paths(&loaded, "古い").is_empty());
+        assert_eq!(loaded.file_states().len(), 1);
+    }
+
+    #[test]
+    fn search_counts_hits_and_makes_snippet() {
+        let mut index = SearchIndex::default();
+        index.upsert(file("1", "memo.txt", "ロゴの案 ロゴの色 ロゴの形"));
+        let found = index.search("ロゴ 色");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].hit_count, 4);
+        assert_eq!(found[0].snippet, "ロゴの案 ロゴの色 ロゴの形");
+    }
+
+    #[test]
+    fn snippet_is_cut_around_first_match() {
+        let text = format!("{}目印{}", "あ".repeat(100), "い".repeat(100));
+        let snippet = make_snippet(&text, "目印");
+        assert_eq!(snippet, format!("…{}目印{}…", "あ".repeat(SNIPPET_CONTEXT_CHARS), "い".repeat(SNIPPET_CONTEXT_CHARS)));
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::domain::error::{AppError, Entity};
+use crate::domain::models::task::Task;
+use crate::tr;
+
+/// 指定したタスクの子孫(サブタスク、その下のサブタスク...)のidを返す
+pub fn collect_descendant_ids(tasks: &[Task], task_id: &str) -> Vec<String> {
+    let mut descendants = Vec::new();
+    let mut parents = vec![task_id.to_string()];
+    while let Some(parent_id) = parents.pop() {
+        for task in tasks.iter().filter(|task| task.parent_id.as_deref() == Some(parent_id.as_str())) {
+            descendants.push(task.id.clone());
+            parents.push(task.id.clone());
+        }
+    }
+    descendants
+}
+
+/// 親タスクが同じプロジェクトにあり、自分自身や自分のサブタスクでないことを確認する
+pub fn validate_task_parent(tasks: &[Task], task: &Task) -> Result<(), AppError> {
+    let Some(parent_id) = task.parent_id.as_deref() else {
+        return Ok(());
+    };
+    let parent = tasks
+        .iter()
+        .find(|candidate| candidate.id == parent_id)
+        .ok_or_else(|| AppError::not_found(Entity::Task, parent_id))?;
+    if parent.project_id != task.project_id {
+        return Err(AppError::validation(tr!("task.parent_in_other_project")));
+    }
+    if parent_id == task.id || collect_descendant_ids(tasks, &task.id).iter().any(|id| id == parent_id) {
+        return Err(AppError::validation(tr!("task.parent_cycle")));
+    }
+    Ok(())
+}
+
+/// 完了したタスクの割合(0〜100)。タスクがなければNone
+pub fn completion_percentage(tasks: &[&Task]) -> Option<f64> {
+    if tasks.is_empty() {
+        return None;
+    }
+    let done = tasks.iter().filter(|task| task.done).count();
+    Some(done as f64 * 100.0 / tasks.len() as f64)
+}
+
+/// プロジェクトごとの完了したタスクの割合
+pub fn completion_percentages_by_project(tasks: &[Task]) -> HashMap<String, f64> {
+    let mut tasks_by_project: HashMap<String, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        tasks_by_project.entry(task.project_id.clone()).or_default().push(task);
+    }
+    tasks_by_project
+        .into_iter()
+        .filter_map(|(project_id, tasks)| completion_percentage(&tasks).map(|percentage| (project_id, percentage)))
+        .collect()
+}
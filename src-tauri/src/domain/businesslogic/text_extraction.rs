@@ -0,0 +1,81 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::panic;
+use std::path::Path;
+
+use crate::domain::error::AppError;
+
+/// これより大きいファイルは中身を読まない
+const MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+/// 1ファイルから索引に入れる文字数の上限
+pub const MAX_TEXT_CHARS: usize = 200_000;
+
+const PLAIN_TEXT_EXTENSIONS: [&str; 9] = ["txt", "md", "markdown", "csv", "tsv", "json", "log", "html", "htm"];
+
+/// ファイルからテキストを取り出す。対応していない形式はNone
+pub fn extract_text(path: &Path) -> Result<Option<String>, AppError> {
+    let extension = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extension.to_lowercase(),
+        None => return Ok(None),
+    };
+    if fs::metadata(path)?.len() > MAX_FILE_SIZE {
+        return Ok(None);
+    }
+
+    let text = match extension.as_str() {
+        extension if PLAIN_TEXT_EXTENSIONS.contains(&extension) => {
+            // Shift_JISなどのファイルも読めるところだけ読む
+            Some(String::from_utf8_lossy(&fs::read(path)?).into_owned())
+        }
+        "docx" => Some(extract_office_xml(path, |name| name == "word/document.xml")?),
+        "pptx" => Some(extract_office_xml(path, |name| name.starts_with("ppt/slides/slide") && name.ends_with(".xml"))?),
+        "xlsx" => Some(extract_office_xml(path, |name| name == "xl/sharedStrings.xml")?),
+        "odt" | "ods" | "odp" => Some(extract_office_xml(path, |name| name == "content.xml")?),
+        // PDFはテキストを持っていないもの(スキャン画像など)もあるので、読めなければ索引に入れない。
+        // 壊れたPDFではpdf_extractがpanicすることがあるので、その時も読めなかったものとして扱う
+        "pdf" => panic::catch_unwind(|| pdf_extract::extract_text(path)).ok().and_then(Result::ok),
+        _ => None,
+    };
+    Ok(text.map(|text| text.chars().take(MAX_TEXT_CHARS).collect()))
+}
+
+/// Officeファイル(zipの中のXML)から、条件に合うXMLの文字だけを取り出す
+fn extract_office_xml(path: &Path, is_target: impl Fn(&str) -> bool) -> Result<String, AppError> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(|e| AppError::parse(e.to_string()))?;
+    let mut text = String::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| AppError::parse(e.to_string()))?;
+        if !is_target(entry.name()) {
+            continue;
+        }
+        let mut xml = String::new();
+        entry.read_to_string(&mut xml)?;
+        text.push_str(&strip_xml_tags(&xml));
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// タグを取り除いて文字だけにする。段落などの区切りは空白にする
+fn strip_xml_tags(xml: &str) -> String {
+    let mut text = String::with_capacity(xml.len() / 2);
+    let mut in_tag = false;
+    for c in xml.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                if !text.ends_with(' ') {
+                    text.push(' ');
+                }
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use crate::domain::error::AppError;
+use crate::domain::models::time_entry::TimeEntry;
+use crate::tr;
+
+/// 計測中のタイマーを返す
+pub fn find_running_entry(entries: &[TimeEntry]) -> Option<&TimeEntry> {
+    entries.iter().find(|entry| entry.ended_at.is_none())
+}
+
+/// 終了時刻が開始時刻より前になっていないか、計測中のタイマーが2つにならないかを確認する
+pub fn validate_time_entry(entries: &[TimeEntry], entry: &TimeEntry) -> Result<(), AppError> {
+    if let Some(ended_at) = entry.ended_at {
+        if ended_at < entry.started_at {
+            return Err(AppError::validation(tr!("timer.ended_before_started")));
+        }
+        return Ok(());
+    }
+    match entries.iter().find(|other| other.ended_at.is_none() && other.id != entry.id) {
+        Some(running) => Err(AppError::validation(tr!("timer.already_running", running.project_id))),
+        None => Ok(()),
+    }
+}
+
+/// 記録の長さ(秒)。計測中のものは0として扱う
+pub fn duration_seconds(entry: &TimeEntry) -> i64 {
+    entry
+        .ended_at
+        .map(|ended_at| (ended_at - entry.started_at).num_seconds())
+        .unwrap_or(0)
+}
+
+/// プロジェクトごとの合計時間(秒)
+pub fn total_seconds_by_project(entries: &[TimeEntry]) -> HashMap<String, i64> {
+    let mut totals = HashMap::new();
+    for entry in entries {
+        *totals.entry(entry.project_id.clone()).or_insert(0) += duration_seconds(entry);
+    }
+    totals
+}
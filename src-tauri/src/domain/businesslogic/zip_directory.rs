@@ -0,0 +1,72 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::domain::error::AppError;
+use crate::tr;
+
+/// アプリが作るzipの圧縮方法
+pub fn zip_options() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// フォルダの中身をzipのprefixの下に入れる。skipがtrueを返したファイルやフォルダは入れない
+pub fn add_directory_to_zip<W: Write + Seek>(writer: &mut ZipWriter<W>, directory: &Path, prefix: &str, skip: impl Fn(&Path) -> bool) -> Result<(), AppError> {
+    let entries = WalkDir::new(directory).min_depth(1).into_iter().filter_entry(|entry| !skip(entry.path()));
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::io(tr!("file.read_directory_failed", directory.display(), e)))?;
+        let Ok(relative) = entry.path().strip_prefix(directory) else {
+            continue;
+        };
+        // zipの中の区切りはOSに関係なく'/'にする
+        let mut name = prefix.to_string();
+        for component in relative.components() {
+            name.push('/');
+            name.push_str(&component.as_os_str().to_string_lossy());
+        }
+        if entry.file_type().is_dir() {
+            writer.add_directory(name, zip_options())?;
+        } else if entry.file_type().is_file() {
+            writer.start_file(name, zip_options())?;
+            io::copy(&mut File::open(entry.path())?, writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// zipのprefixの下にあるものをdestinationに展開する。
+/// zipの中のパスがdestinationの外を指していれば展開しない
+pub fn extract_zip_directory<R: Read + Seek>(zip: &mut ZipArchive<R>, prefix: &str, destination: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(destination).map_err(|e| AppError::io(tr!("file.create_directory_failed", destination.display(), e)))?;
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        let Some(relative) = file.enclosed_name().and_then(|name| name.strip_prefix(prefix).ok()).map(Path::to_path_buf) else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        let path = destination.join(relative);
+        if file.is_dir() {
+            fs::create_dir_all(&path).map_err(|e| AppError::io(tr!("file.create_directory_failed", path.display(), e)))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| AppError::io(tr!("file.create_directory_failed", parent.display(), e)))?;
+        }
+        let mut output = File::create(&path).map_err(|e| AppError::io(tr!("file.write_failed", path.display(), e)))?;
+        io::copy(&mut file, &mut output).map_err(|e| AppError::io(tr!("file.write_failed", path.display(), e)))?;
+    }
+    Ok(())
+}
+
+/// zipの中のファイルを文字列で読み込む
+pub fn read_zip_text<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<String, AppError> {
+    let mut contents = String::new();
+    zip.by_name(name)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::tr;
+
+/// エラーの対象
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Project,
+    Client,
+    Task,
+    TimeEntry,
+    Invoice,
+    FolderTemplate,
+}
+
+impl Entity {
+    pub fn label(&self) -> String {
+        match self {
+            Entity::Project => tr!("entity.project"),
+            Entity::Client => tr!("entity.client"),
+            Entity::Task => tr!("entity.task"),
+            Entity::TimeEntry => tr!("entity.time_entry"),
+            Entity::Invoice => tr!("entity.invoice"),
+            Entity::FolderTemplate => tr!("entity.folder_template"),
+        }
+    }
+}
+
+/// アプリ全体で使うエラー。
+/// フロントエンドには { code, message, details } の形で渡し、codeで処理を分けられるようにする。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    NotFound { entity: Entity, id: String },
+    DuplicateFolder { folder: String },
+    Validation(String),
+    Io(String),
+    Parse(String),
+    Conflict { entity: Entity, id: String },
+    /// 書き込みに失敗し、取り消しも一部失敗した。errorは元の失敗、failuresは取り消せなかった処理
+    RollbackFailed { error: Box<AppError>, failures: Vec<String> },
+}
+
+impl AppError {
+    pub fn not_found(entity: Entity, id: impl Into<String>) -> Self {
+        AppError::NotFound { entity, id: id.into() }
+    }
+
+    pub fn duplicate_folder(folder: impl Into<String>) -> Self {
+        AppError::DuplicateFolder { folder: folder.into() }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation(message.into())
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        AppError::Io(message.into())
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        AppError::Parse(message.into())
+    }
+
+    pub fn conflict(entity: Entity, id: impl Into<String>) -> Self {
+        AppError::Conflict { entity, id: id.into() }
+    }
+
+    pub fn rollback_failed(error: AppError, failures: Vec<String>) -> Self {
+        AppError::RollbackFailed { error: Box::new(error), failures }
+    }
+
+    /// フロントエンドが処理を分けるためのコード
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound { .. } => "NOT_FOUND",
+            AppError::DuplicateFolder { .. } => "DUPLICATE_FOLDER",
+            AppError::Validation(_) => "VALIDATION",
+            AppError::Io(_) => "IO",
+            AppError::Parse(_) => "PARSE",
+            AppError::Conflict { .. } => "CONFLICT",
+            AppError::RollbackFailed { .. } => "ROLLBACK_FAILED",
+        }
+    }
+
+    /// メッセージを組み立て直すための値。フロントエンドで翻訳する時に使う
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::NotFound { entity, id } | AppError::Conflict { entity, id } => {
+                Some(serde_json::json!({ "entity": entity, "id": id }))
+            }
+            AppError::DuplicateFolder { folder } => Some(serde_json::json!({ "folder": folder })),
+            AppError::RollbackFailed { error, failures } => Some(serde_json::json!({
+                "error": { "code": error.code(), "message": error.to_string(), "details": error.details() },
+                "failures": failures,
+            })),
+            AppError::Validation(_) | AppError::Io(_) | AppError::Parse(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound { entity, id } => write!(f, "{}", tr!("error.not_found", entity.label(), id)),
+            AppError::DuplicateFolder { folder } => write!(f, "{}", tr!("error.duplicate_folder", folder)),
+            AppError::Validation(message) | AppError::Io(message) | AppError::Parse(message) => write!(f, "{}", message),
+            AppError::Conflict { entity, id } => write!(f, "{}", tr!("error.conflict", entity.label(), id)),
+            AppError::RollbackFailed { error, failures } => write!(f, "{}", tr!("error.rollback_failed", error, failures.join("\n"))),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<csv::Error> for AppError {
+    fn from(e: csv::Error) -> Self {
+        if e.is_io_error() {
+            AppError::Io(e.to_string())
+        } else {
+            AppError::Parse(e.to_string())
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            // 外部キー・一意制約の違反は入力の問題として扱う
+            rusqlite::Error::SqliteFailure(ref error, _) if error.code == rusqlite::ErrorCode::ConstraintViolation => {
+                AppError::Validation(e.to_string())
+            }
+            rusqlite::Error::FromSqlConversionFailure(_, _, ref cause) => match cause.downcast_ref::<AppError>() {
+                Some(app_error) => app_error.clone(),
+                None => AppError::Parse(e.to_string()),
+            },
+            rusqlite::Error::InvalidColumnType(..) => AppError::Parse(e.to_string()),
+            _ => AppError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<tempfile::PersistError> for AppError {
+    fn from(e: tempfile::PersistError) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(_) => AppError::Io(e.to_string()),
+            _ => AppError::Parse(e.to_string()),
+        }
+    }
+}
+
+impl From<calamine::Error> for AppError {
+    fn from(e: calamine::Error) -> Self {
+        match e {
+            calamine::Error::Io(e) => AppError::Io(e.to_string()),
+            other => AppError::Parse(other.to_string()),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fmt::Display, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+
+/// 画面とエラーメッセージの言語
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+/// 現在の言語。起動時とset_localeで設定する
+static LOCALE: RwLock<Locale> = RwLock::new(Locale::Ja);
+
+pub fn get_locale() -> Locale {
+    LOCALE.read().map(|locale| *locale).unwrap_or_default()
+}
+
+pub fn set_locale(locale: Locale) {
+    if let Ok(mut current) = LOCALE.write() {
+        *current = locale;
+    }
+}
+
+/// メッセージのカタログ。(キー, 日本語, 英語)
+/// {0}, {1}...は引数に置き換える。言語によって語順が変わるので番号で指定する
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("error.not_found", "{0}が見つかりません: {1}", "Could not find the {0}: {1}"),
+    ("error.duplicate_folder", "既に存在するプロジェクト名です。: {0}", "A project folder with this name already exists: {0}"),
+    ("error.rollback_failed", "{0}\n途中までの変更を元に戻せませんでした。データを確認してください:\n{1}", "{0}\nSome changes could not be undone. Please check your data:\n{1}"),
+    ("error.conflict", "この{0}(id={1})は他の画面で更新されています。再読み込みしてからもう一度保存してください。", "This {0} (id={1}) was updated elsewhere. Reload and save again."),
+
+    ("entity.project", "プロジェクト", "project"),
+    ("entity.client", "クライアント", "client"),
+    ("entity.task", "タスク", "task"),
+    ("entity.time_entry", "作業時間の記録", "time entry"),
+    ("entity.invoice", "請求書", "invoice"),
+    ("entity.folder_template", "フォルダテンプレート", "folder template"),
+
+    ("store.history_not_supported", "この保存先では変更を元に戻せません", "Changes to this data cannot be undone"),
+    ("store.restore_not_supported", "この保存先では削除を取り消せません", "Deletions in this data cannot be undone"),
+    ("store.locked", "他のウィンドウまたはアプリがデータを使用中です。しばらくしてからもう一度お試しください。", "The data is in use by another window or application. Please wait a moment and try again."),
+
+    ("settings.read_failed", "設定ファイルを読み込めません '{0}': {1}", "Cannot read the settings file '{0}': {1}"),
+    ("settings.directory_missing", "設定フォルダを取得できません", "Failed to get settings directory"),
+
+    ("data_directory.not_absolute", "データフォルダは絶対パスで指定してください: {0}", "Specify the data folder as an absolute path: {0}"),
+    ("data_directory.not_writable", "データフォルダに書き込めません '{0}': {1}", "Cannot write to the data folder '{0}': {1}"),
+    ("data_directory.destination_not_empty", "移動先に既にデータがあります: {0}", "The destination already contains data: {0}"),
+
+    ("migration.newer_schema", "データのバージョン({0})がアプリのバージョン({1})より新しいため開けません。アプリを更新してください。", "The data version ({0}) is newer than this app supports ({1}). Please update the app."),
+    ("migration.failed", "データの移行に失敗しました(v{0}: {1}): {2}\n移行前のファイルは {3} に保存されています。", "Failed to migrate the data (v{0}: {1}): {2}\nThe files before migration were saved to {3}."),
+    ("migration.read_version_failed", "スキーマのバージョンファイルを読み込めません '{0}': {1}", "Cannot read the schema version file '{0}': {1}"),
+    ("migration.invalid_folder_path", "フォルダのパスが不正です: {0}", "Invalid folder path: {0}"),
+    ("migration.add_deleted_at", "projects.csvにdeleted_atを追加", "Add deleted_at to projects.csv"),
+    ("migration.rename_folder_path", "projects.csvのfolder_pathをfolder_path_suffixに変更", "Rename folder_path to folder_path_suffix in projects.csv"),
+    ("migration.add_client_details", "clients.csvに連絡先と請求先の列を追加", "Add contact and billing columns to clients.csv"),
+    ("migration.add_revision", "projects.csvにrevisionを追加", "Add revision to projects.csv"),
+    ("migration.add_template_id", "projects.csvにtemplate_idを追加", "Add template_id to projects.csv"),
+    ("migration.add_archived_at", "projects.csvにarchived_atを追加", "Add archived_at to projects.csv"),
+    ("migration.normalize_dates", "projects.csvの日付をYYYY-MM-DD形式にそろえる", "Normalize dates in projects.csv to YYYY-MM-DD"),
+
+    ("csv.header_mismatch", "CSVファイルの列がアプリと一致しません '{0}'\n期待する列: {1}\nファイルの列: {2}", "The columns of the CSV file do not match the app '{0}'\nExpected: {1}\nFound: {2}"),
+
+    ("file.open_lock_failed", "ロックファイルを開けません '{0}': {1}", "Failed to open lock file '{0}': {1}"),
+    ("file.create_directory_failed", "フォルダを作成できません '{0}': {1}", "Failed to create directory '{0}': {1}"),
+    ("file.copy_failed", "ファイルをコピーできません '{0}' -> '{1}': {2}", "Failed to copy file from '{0}' to '{1}': {2}"),
+    ("file.parent_directory_missing", "親フォルダを取得できません '{0}'", "Failed to get parent directory of '{0}'"),
+    ("file.rename_directory_failed", "フォルダの名前を変更できません '{0}' -> '{1}': {2}", "Failed to rename directory from '{0}' to '{1}': {2}"),
+    ("file.project_parent_missing", "プロジェクトフォルダの親フォルダを取得できません", "Failed to get parent directory of the old project directory"),
+    ("file.write_failed", "ファイルに書き込めません '{0}': {1}", "Failed to write file '{0}': {1}"),
+    ("file.move_directory_failed", "フォルダを移動できません '{0}' -> '{1}': {2}", "Failed to move directory from '{0}' to '{1}': {2}"),
+    ("file.remove_directory_failed", "フォルダを削除できません '{0}': {1}", "Failed to remove directory '{0}': {1}"),
+    ("file.remove_file_failed", "ファイルを削除できません '{0}': {1}", "Failed to remove file '{0}': {1}"),
+    ("file.move_failed", "移動できません '{0}' -> '{1}': {2}", "Failed to move '{0}' to '{1}': {2}"),
+    ("file.outside_project", "プロジェクトのフォルダの外は指定できません: {0}", "The path must be inside the project folder: {0}"),
+    ("file.invalid_name", "ファイル名が不正です: {0}", "Invalid file name: {0}"),
+    ("file.already_exists", "同じ名前のファイルまたはフォルダが既にあります: {0}", "A file or folder with the same name already exists: {0}"),
+    ("file.not_found", "ファイルまたはフォルダが見つかりません: {0}", "Could not find the file or folder: {0}"),
+    ("file.not_directory", "フォルダではありません: {0}", "Not a folder: {0}"),
+    ("file.copy_into_itself", "フォルダをその中にコピーすることはできません: {0}", "A folder cannot be copied into itself: {0}"),
+    ("file.project_root", "プロジェクトのフォルダ自体は変更できません", "The project folder itself cannot be changed"),
+    ("file.read_directory_failed", "フォルダを読み込めません '{0}': {1}", "Failed to read directory '{0}': {1}"),
+    ("folder_watch.start_failed", "フォルダを監視できません: {0}", "Failed to start watching folders: {0}"),
+    ("folder_watch.watch_failed", "フォルダを監視できません '{0}': {1}", "Failed to watch folder '{0}': {1}"),
+    ("folder_watch.event_failed", "フォルダの変更を受け取れませんでした: {0}", "Failed to receive a folder change: {0}"),
+
+    ("date.invalid_date", "{0}の日付の形式が不正です: '{1}'", "Invalid date format for {0}: '{1}'"),
+    ("date.invalid_datetime", "{0}の日時の形式が不正です: '{1}'", "Invalid date-time format for {0}: '{1}'"),
+
+    ("project.invalid_id", "不正なプロジェクトIDです: {0}", "Invalid project ID: {0}"),
+    ("project.invalid_for_csv", "不正なプロジェクトをcsvに渡そうとしています", "Tried to write an invalid project to the CSV file"),
+    ("project.in_trash", "ゴミ箱に入っているプロジェクトです: {0}", "The project is in the trash: {0}"),
+    ("project.already_in_trash", "プロジェクトは既にゴミ箱に入っています: {0}", "The project is already in the trash: {0}"),
+    ("project.not_in_trash", "プロジェクトはゴミ箱に入っていません: {0}", "The project is not in the trash: {0}"),
+    ("project.invalid_status_change", "ステータスを{0}から{1}に変更することはできません", "The status cannot be changed from {0} to {1}"),
+    ("project.due_before_order", "納期({0})が受注日({1})より前になっています", "The due date ({0}) is before the order date ({1})"),
+    ("project.completed_before_order", "完了日({0})が受注日({1})より前になっています", "The completion date ({0}) is before the order date ({1})"),
+
+    ("client.invalid_id", "不正なクライアントIDです: {0}", "Invalid client ID: {0}"),
+    ("client.invalid_id_in_file", "クライアントIDが不正です: {0} ({1})", "Invalid client ID: {0} ({1})"),
+    ("client.in_use", "プロジェクトで使用中のクライアントは削除できません: {0}", "A client used by projects cannot be deleted: {0}"),
+
+    ("task.move_to_other_project", "タスクを別のプロジェクトに移動することはできません", "A task cannot be moved to another project"),
+    ("task.parent_in_other_project", "親タスクは同じプロジェクトのタスクを指定してください", "The parent task must belong to the same project"),
+    ("task.reorder_mismatch", "並べ替えるタスクには、同じ親を持つタスクをすべて1回ずつ指定してください", "Specify every task under the same parent exactly once to reorder"),
+    ("task.parent_cycle", "タスクを自分自身やサブタスクの下に移動することはできません", "A task cannot be moved under itself or one of its subtasks"),
+
+    ("timer.not_running", "計測中のタイマーがありません", "No timer is running"),
+    ("timer.project_in_trash", "ゴミ箱のプロジェクトには時間を記録できません: {0}", "Time cannot be recorded for a project in the trash: {0}"),
+    ("timer.project_archived", "アーカイブ中のプロジェクトには時間を記録できません: {0}", "Time cannot be recorded for an archived project: {0}"),
+    ("timer.ended_before_started", "終了時刻が開始時刻より前になっています", "The end time is before the start time"),
+    ("timer.already_running", "既に計測中のタイマーがあります (project id={0})", "A timer is already running (project id={0})"),
+
+    ("invoice.project_not_completed", "完了したプロジェクトのみ請求書を作成できます: {0}", "Invoices can only be created for completed projects: {0}"),
+    ("invoice.timer_running", "計測中のタイマーを止めてから請求書を作成してください", "Stop the running timer before creating an invoice"),
+    ("invoice.invalid_tax_rate", "税率が不正です。0から1の間で、0.01%単位にしてください: {0}", "Invalid tax rate. Use a value from 0 to 1 in steps of 0.01%: {0}"),
+    ("invoice.no_line_items", "請求書に明細がありません", "The invoice has no line items"),
+    ("invoice.negative_line_item", "明細の数量と単価は0以上にしてください", "Line item quantities and unit prices must be 0 or more"),
+    ("invoice.invalid_quantity", "明細の数量は小数第2位までにしてください: {0}", "Line item quantities can have at most two decimal places: {0}"),
+    ("invoice.duplicate_number", "請求書番号が既に使われています: {0}", "The invoice number is already in use: {0}"),
+    ("invoice.due_before_issue", "支払期限が発行日より前になっています", "The payment due date is before the issue date"),
+    ("invoice.hours_line_item", "作業時間", "Working hours"),
+
+    ("archive.project_not_finished", "完了・請求済み・キャンセルのプロジェクトのみアーカイブできます: {0}", "Only completed, invoiced or cancelled projects can be archived: {0}"),
+    ("archive.already_archived", "プロジェクトは既にアーカイブされています: {0}", "The project is already archived: {0}"),
+    ("archive.not_archived", "プロジェクトはアーカイブされていません: {0}", "The project is not archived: {0}"),
+    ("archive.file_missing", "アーカイブのファイルが見つかりません: {0}", "Could not find the archive file: {0}"),
+    ("archive.project_mismatch", "アーカイブのファイルがこのプロジェクトのものではありません: {0}", "The archive file does not belong to this project: {0}"),
+    ("archive.folder_rename_archived", "アーカイブ中のプロジェクトはフォルダ名を変更できません。展開してから変更してください: {0}", "The folder of an archived project cannot be renamed. Unarchive it first: {0}"),
+    ("archive.timer_running", "計測中のタイマーを止めてからアーカイブしてください", "Stop the running timer before archiving"),
+
+    ("backup.not_found", "バックアップが見つかりません: {0}", "Could not find the backup: {0}"),
+    ("backup.invalid", "バックアップにデータのファイルがありません: {0}", "The backup does not contain any data files: {0}"),
+    ("backup.invalid_file", "バックアップのファイルが壊れています '{0}': {1}", "A file in the backup is corrupted '{0}': {1}"),
+    ("backup.invalid_settings", "バックアップの間隔と残す数は1以上にしてください", "The backup interval and the number of backups to keep must be 1 or more"),
+    ("backup.restoring", "バックアップを復元しています", "Restoring a backup"),
+    ("backup.exit_failed", "終了時のバックアップを作成できませんでした。もう一度閉じると、バックアップを作らずに終了します: {0}", "Failed to create the backup on exit. Close the window again to quit without a backup: {0}"),
+
+    ("import.unsupported_file", "読み込めないファイルの種類です。csv・xlsx・xls・odsのファイルを指定してください: {0}", "Unsupported file type. Choose a csv, xlsx, xls or ods file: {0}"),
+    ("import.read_failed", "ファイルを読み込めません '{0}': {1}", "Could not read the file '{0}': {1}"),
+    ("import.sheet_not_found", "シートが見つかりません: {0}", "Could not find the sheet: {0}"),
+    ("import.empty_file", "ファイルに見出しの行がありません: {0}", "The file has no header row: {0}"),
+    ("import.column_required", "{0}の列を指定してください", "Choose the column for {0}"),
+    ("import.column_not_found", "ファイルに列がありません: {0}", "The file has no column named: {0}"),
+    ("import.required_value", "{0}が空です", "{0} is empty"),
+    ("import.invalid_status", "ステータスが不正です: {0}", "Invalid status: {0}"),
+    ("import.folder_in_use", "フォルダ名は既に使われています: {0}", "The folder name is already in use: {0}"),
+    ("import.has_errors", "{0}行にエラーがあるため読み込みません。プレビューでエラーを確認してください", "Nothing was imported because {0} rows have errors. Check the errors in the preview"),
+    ("import.no_rows", "読み込む行がありません", "There are no rows to import"),
+
+    ("folder_template.default_name", "標準", "Standard"),
+    ("folder_template.empty_name", "テンプレート名を入力してください", "Enter a template name"),
+    ("folder_template.invalid_path", "テンプレートのパスが不正です。プロジェクトのフォルダからの相対パスで指定してください: {0}", "Invalid template path. Specify a path relative to the project folder: {0}"),
+    ("folder_template.duplicate_path", "テンプレートに同じパスが複数あります: {0}", "The template contains the same path more than once: {0}"),
+    ("folder_template.builtin", "標準のテンプレートは変更・削除できません", "The built-in template cannot be changed or deleted"),
+    ("folder_template.in_use", "プロジェクトで使用中のテンプレートは削除できません: {0}", "A template used by projects cannot be deleted: {0}"),
+    ("folder_template.in_use_edit", "プロジェクトで使用中のテンプレートのフォルダとファイルは変更できません: {0}", "The folders and files of a template used by projects cannot be changed: {0}"),
+
+    ("step.add", "追加", "Add"),
+    ("step.write_clients", "クライアントの書き込み", "Write the clients"),
+    ("step.write_contacts", "担当者の書き込み", "Write the contacts"),
+    ("step.rename_project_folder", "プロジェクトフォルダの名前の変更", "Rename the project folder"),
+    ("step.update_project", "プロジェクトの更新", "Update the project"),
+    ("step.create_project_folder", "プロジェクトフォルダの作成", "Create the project folder"),
+    ("step.add_project", "プロジェクトの追加", "Add the project"),
+    ("step.delete_project", "プロジェクトの削除", "Delete the project"),
+    ("step.delete_status_history", "ステータス履歴の削除", "Delete the status history"),
+    ("step.add_status_history", "ステータス履歴の追加", "Add the status history"),
+    ("step.append_audit_log", "監査ログの追記", "Append to the audit log"),
+    ("step.move_current_data", "今のデータを退避", "Move the current data aside"),
+    ("step.keep_migration_backup", "移行前のファイルのバックアップを引き継ぐ", "Keep the backup of the files from before the migration"),
+    ("step.place_backup_data", "バックアップのデータを配置", "Put the backup data in place"),
+    ("step.save_invoice", "請求書の保存", "Save the invoice"),
+    ("step.create_invoice_file", "請求書ファイルの作成", "Create the invoice file"),
+    ("step.delete_task", "タスクの削除", "Delete a task"),
+    ("step.revert_project", "プロジェクトを書き戻す", "Write back the project"),
+    ("step.delete_time_entry", "作業時間の記録の削除", "Delete a time entry"),
+    ("step.move_folder_to_trash", "フォルダをゴミ箱へ移動", "Move the folder to the trash"),
+    ("step.restore_folder_from_trash", "フォルダをゴミ箱から戻す", "Move the folder back from the trash"),
+    ("step.create_archive", "アーカイブの作成", "Create the archive"),
+    ("step.restore_folder_from_archive", "アーカイブからフォルダを戻す", "Move the folder back from the archive"),
+];
+
+/// キーのメッセージを現在の言語で返す。カタログにないキーはそのまま返す
+pub fn translate(key: &str, args: &[&dyn Display]) -> String {
+    let locale = get_locale();
+    let template = CATALOG
+        .iter()
+        .find(|(catalog_key, _, _)| *catalog_key == key)
+        .map(|(_, ja, en)| match locale {
+            Locale::Ja => *ja,
+            Locale::En => *en,
+        })
+        .unwrap_or(key);
+    args.iter()
+        .enumerate()
+        .fold(template.to_string(), |text, (index, arg)| text.replace(&format!("{{{}}}", index), &arg.to_string()))
+}
+
+/// tr!("key", arg0, arg1) でカタログのメッセージを作る
+#[macro_export]
+macro_rules! tr {
+    ($key:expr $(, $arg:expr)* $(,)?) => {
+        $crate::domain::i18n::translate($key, &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
@@ -1,2 +1,4 @@
 pub mod models;
-pub mod businesslogic;
\ No newline at end of file
+pub mod businesslogic;
+pub mod error;
+pub mod i18n;
\ No newline at end of file
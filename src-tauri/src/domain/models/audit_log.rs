@@ -0,0 +1,37 @@
+// models/audit_log.rs
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// データの変更の記録。追記するだけで、書き換えたり消したりしない
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditLogEntry {
+    pub timestamp: DateTime<Local>,
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub changes: Vec<FieldChange>,
+    pub user: String, // 変更したOSのユーザー
+}
+
+/// 変更したデータの種類
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AuditEntity {
+    Project,
+    Client,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    Add,
+    Update,
+    Delete,
+    RenameFolder, // プロジェクトのフォルダの名前の変更
+}
+
+/// 項目ごとの変更前と変更後の値。追加の時はbeforeが、削除の時はafterがNone
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String, // client.nameのように、入れ子の項目は.でつなぐ
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
@@ -0,0 +1,23 @@
+// models/backup.rs
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// データフォルダのバックアップ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub id: String, // バックアップのファイル名から拡張子を除いたもの
+    pub created_at: DateTime<Local>,
+    pub reason: BackupReason,
+    pub schema_version: u32, // 作成した時のcsvのスキーマのバージョン
+    pub includes_project_folders: bool,
+    pub size: u64, // バイト
+}
+
+/// バックアップを作ったきっかけ
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BackupReason {
+    Scheduled,
+    Exit,
+    Manual,
+    BeforeRestore, // 復元で上書きする前のデータ
+}
@@ -5,5 +5,36 @@ use serde::{Deserialize, Serialize};
 pub struct Client {
     pub id:String,
     pub name: String,
-    pub contact_person: String,
+    pub contact_person: String, // 主担当者
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub phone: String,
+    #[serde(default)]
+    pub postal_code: String,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub billing: BillingDetails,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub contacts: Vec<ClientContact>, // 主担当者以外の担当者
+}
+
+/// 請求書の宛先。空欄の項目はクライアントの情報を使う。
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BillingDetails {
+    pub billing_name: String,
+    pub billing_address: String,
+    pub billing_email: String,
+}
+
+/// クライアントの担当者
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ClientContact {
+    pub name: String,
+    pub role: String,
+    pub email: String,
+    pub phone: String,
 }
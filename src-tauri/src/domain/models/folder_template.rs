@@ -0,0 +1,19 @@
+// models/folder_template.rs
+use serde::{Deserialize, Serialize};
+
+/// プロジェクトを追加する時に作るフォルダの構成
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FolderTemplate {
+    pub id: String,
+    pub name: String,
+    pub directories: Vec<String>, // プロジェクトのフォルダからの相対パス。"documents/contracts"のように入れ子にできる
+    #[serde(default)]
+    pub files: Vec<TemplateFile>,
+}
+
+/// テンプレートから最初に作るファイル
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateFile {
+    pub path: String, // プロジェクトのフォルダからの相対パス
+    pub contents: String, // {{project_title}}などのプレースホルダーはプロジェクトの値に置き換える
+}
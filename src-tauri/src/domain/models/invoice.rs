@@ -0,0 +1,32 @@
+// models/invoice.rs
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// 請求書
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Invoice {
+    pub id: String,
+    pub invoice_number: String, // INV-2024-0001 のような連番
+    pub project_id: String,
+    pub client_id: String,
+    pub issue_date: NaiveDate,
+    pub payment_due_date: NaiveDate,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub tax_rate: f64, // 10%なら0.1
+    pub file_path: Option<String>, // 出力した請求書ファイルのパス
+}
+
+/// 請求書の明細
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InvoiceLineItem {
+    pub description: String,
+    pub kind: LineItemKind,
+    pub quantity: f64, // 時間単価なら時間数、固定額なら1
+    pub unit_price: i64, // 円
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LineItemKind {
+    Hourly,
+    Fixed,
+}
@@ -1,2 +1,11 @@
 pub mod project;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod project_status_history;
+pub mod task;
+pub mod time_entry;
+pub mod invoice;
+pub mod settings;
+pub mod folder_template;
+pub mod project_archive;
+pub mod backup;
+pub mod audit_log;
\ No newline at end of file
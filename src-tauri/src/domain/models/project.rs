@@ -1,5 +1,6 @@
 // models/order.rs
 use crate::domain::models::client::Client;
+use chrono::{DateTime, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -8,17 +9,25 @@ pub struct Project {
     pub title: String,
     pub description: String,
     // pub category: String, // ウェブデザイン、イラストなど
-    pub order_date: String, // 実際にはより適切な日付型を使用する
-    pub due_date: String,
-    pub completion_date: Option<String>,
+    pub order_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub completion_date: Option<NaiveDate>,
     pub client: Client,
     pub status: ProjectStatus,
     pub folder_path_suffix:Option<String>, 
+    pub deleted_at: Option<DateTime<Local>>, // ゴミ箱に入れた日時。Noneなら通常のプロジェクト
+    pub archived_at: Option<DateTime<Local>>, // アーカイブした日時。アーカイブのファイルはarchiveフォルダにある
+    pub revision: u64, // 保存するたびに1増える。読み込んだ時と違えば他で更新されている
+    pub template_id: Option<String>, // フォルダを作った時のテンプレート。Noneなら標準のテンプレート
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ProjectStatus {
+    NotStarted,
     InProgress,
-    Completed,
     OnHold,
+    Delivered,
+    Completed,
+    Invoiced,
+    Cancelled,
 }
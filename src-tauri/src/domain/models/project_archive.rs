@@ -0,0 +1,18 @@
+// models/project_archive.rs
+use crate::domain::models::project::Project;
+use crate::domain::models::project_status_history::ProjectStatusHistory;
+use crate::domain::models::task::Task;
+use crate::domain::models::time_entry::TimeEntry;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// アーカイブのファイルに入れるプロジェクトの情報。アプリのデータがなくても中身が分かるように、関連するデータをまとめて持つ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectArchive {
+    pub archived_at: DateTime<Local>,
+    pub project: Project, // クライアントも含む
+    pub tasks: Vec<Task>,
+    pub time_entries: Vec<TimeEntry>,
+    pub status_history: Vec<ProjectStatusHistory>,
+    pub includes_folder: bool, // プロジェクトのフォルダを含んでいるか
+}
@@ -0,0 +1,13 @@
+// models/project_status_history.rs
+use crate::domain::models::project::ProjectStatus;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// プロジェクトのステータス変更の履歴
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectStatusHistory {
+    pub project_id: String,
+    pub from_status: Option<ProjectStatus>, // 新規作成時はNone
+    pub to_status: ProjectStatus,
+    pub changed_at: DateTime<Local>,
+}
@@ -0,0 +1,38 @@
+// models/settings.rs
+use serde::{Deserialize, Serialize};
+
+use crate::domain::i18n::Locale;
+
+/// アプリの設定。データフォルダとは別の場所(OSの設定フォルダ)に保存する
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub data_directory: Option<String>, // Noneの時はOSのアプリデータフォルダを使う
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub backup: BackupSettings,
+}
+
+/// 自動バックアップの設定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_hours: u32, // 前回のバックアップからこの時間が経ったら作る
+    pub on_exit: bool, // ウィンドウを閉じる時にも作る
+    pub keep_count: usize, // 残す自動バックアップの数。古いものから消す。手動と復元前のバックアップは数えない
+    pub include_project_folders: bool, // プロジェクトのフォルダも含めるか。大きくなるので初期値はfalse
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            on_exit: true,
+            keep_count: 10,
+            include_project_folders: false,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// models/task.rs
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// プロジェクトの中のタスク。parent_idがあればサブタスク
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Task {
+    pub id: String,
+    pub project_id: String,
+    pub parent_id: Option<String>,
+    pub title: String,
+    pub description: String,
+    pub due_date: Option<NaiveDate>,
+    pub done: bool,
+    pub sort_order: i32, // 同じ親を持つタスクの中での並び順
+}
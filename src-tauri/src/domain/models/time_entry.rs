@@ -0,0 +1,13 @@
+// models/time_entry.rs
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// プロジェクトの作業時間の記録。ended_atがNoneならタイマーで計測中
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeEntry {
+    pub id: String,
+    pub project_id: String,
+    pub started_at: DateTime<Local>,
+    pub ended_at: Option<DateTime<Local>>,
+    pub note: String,
+}
@@ -5,83 +5,767 @@ mod domain;
 mod repositories;
 mod application;
 
+use std::collections::HashMap;
 use std::env;
+use tauri::Manager;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard};
+use std::thread;
+use std::time::Duration;
 
-use application::{interface::ProjectFrontEnd, usecase::convert_project_to_frontend};
+use notify::Event;
+
+use application::{interface::{ClientDetail, ClientTimeTotal, CreateInvoiceRequest, DeleteProjectOption, ProjectFrontEnd, ProjectSearchQuery, ProjectSearchResult, ProjectTimeTotal, FileSearchHit, ProjectFileEntry, ProjectFolderReport, FolderReconcileAction, ArchiveProjectOption, AuditLogQuery, UndoRedoState, ImportFileInfo, ImportPreview, ImportRequest, ImportSummary}, usecase::{convert_ifrontend_to_project, convert_projects_to_frontend, fetch_active_projects}};
+use domain::businesslogic::edit_history::EditHistory;
+use domain::businesslogic::search_index::SearchIndex;
+use domain::error::AppError;
+use domain::i18n::Locale;
+use domain::businesslogic::data_directory::{get_data_root, resolve_data_root, set_data_root, validate_data_directory};
+use domain::models::audit_log::AuditLogEntry;
+use domain::models::backup::{Backup, BackupReason};
 use domain::models::client::Client;
+use domain::models::folder_template::FolderTemplate;
+use domain::models::invoice::Invoice;
 use domain::models::project::Project;
-use repositories::{file_repository::{ClientFileRepository, ProjectFileRepository}, repository_trait::Repository};
+use domain::models::project_status_history::ProjectStatusHistory;
+use domain::models::settings::BackupSettings;
+use domain::models::task::Task;
+use domain::models::time_entry::TimeEntry;
+use repositories::{file_repository::{AuditLogFileRepository, ClientFileRepository, FolderTemplateFileRepository, InvoiceFileRepository, ProjectFileRepository, StatusHistoryFileRepository, TaskFileRepository, TimeEntryFileRepository}, repository_trait::{AuditLogRepository, Repository, StatusHistoryRepository}};
+use repositories::backup_repository::BackupFileRepository;
+use repositories::search_index_repository::SearchIndexFileRepository;
+use repositories::settings_repository::SettingsFileRepository;
+use repositories::unit_of_work::UnitOfWork;
+use repositories::sqlite_repository::{open_database, SqliteAuditLogRepository, SqliteClientRepository, SqliteFolderTemplateRepository, SqliteInvoiceRepository, SqliteProjectRepository, SqliteStatusHistoryRepository, SqliteTaskRepository, SqliteTimeEntryRepository};
+
 
+/// ファイル検索の索引を更新する間隔
+const SEARCH_INDEX_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// search_filesでlimitを省略した時の件数
+const SEARCH_FILES_DEFAULT_LIMIT: usize = 100;
+/// プロジェクト管理フォルダの中でフォルダが作成・削除・名前変更された時にフロントエンドへ送るイベント。中身はProjectFolderReport
+const PROJECT_FOLDERS_CHANGED_EVENT: &str = "project-folders-changed";
+/// バックグラウンドの処理が失敗した時にフロントエンドへ送るイベント。中身はAppError
+const BACKGROUND_ERROR_EVENT: &str = "background-error";
+/// 自動バックアップが必要か確かめる間隔。バックアップの間隔そのものは設定のinterval_hours
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 保存先ごとに作り直すrepository
+struct Repositories{
+    project_repo: Box<dyn Repository<Project> + Send + Sync>,
+    client_repo: Box<dyn Repository<Client> + Send + Sync>,
+    status_history_repo: Box<dyn StatusHistoryRepository + Send + Sync>,
+    task_repo: Box<dyn Repository<Task> + Send + Sync>,
+    time_entry_repo: Box<dyn Repository<TimeEntry> + Send + Sync>,
+    invoice_repo: Box<dyn Repository<Invoice> + Send + Sync>,
+    folder_template_repo: Box<dyn Repository<FolderTemplate> + Send + Sync>,
+    audit_log_repo: Box<dyn AuditLogRepository + Send + Sync>,
+    // ファイル検索の索引。バックグラウンドのスレッドが更新する
+    search_index: RwLock<SearchIndex>,
+    search_index_repo: SearchIndexFileRepository,
+    // 監視で見つけたプロジェクトのフォルダの名前の変更(元の名前 -> 新しい名前)
+    folder_renames: Mutex<HashMap<String, String>>,
+    backup_repo: BackupFileRepository,
+    // 元に戻す・やり直しの履歴。データフォルダを変えたら消える
+    edit_history: Mutex<EditHistory>,
+}
 
 struct AppState{
-    project_repo: ProjectFileRepository,
-    client_repo: ClientFileRepository
+    // データフォルダを変更した時に差し替えるので、RwLockで持つ。
+    // 起動時にデータを開けなかった時はエラーを持ち、別のデータフォルダを選び直せるようにする。
+    repos: RwLock<Result<Repositories, AppError>>,
+    settings_repo: SettingsFileRepository,
+    // 閉じる時のバックアップを一度作ろうとしたか。失敗した後にもう一度閉じた時は作らない
+    exit_backup_attempted: AtomicBool,
+}
+
+/// 開けているrepositoryへの読み取りロック
+struct ReposGuard<'a>(RwLockReadGuard<'a, Result<Repositories, AppError>>);
+
+impl Deref for ReposGuard<'_>{
+    type Target = Repositories;
+
+    fn deref(&self) -> &Repositories {
+        // read_reposでOkの時だけ作るので、ここではErrにならない
+        self.0.as_ref().expect("repositories are not available")
+    }
+}
+
+impl AppState{
+    fn read_repos(&self) -> Result<ReposGuard<'_>, AppError>{
+        let repos = self.repos.read()?;
+        if let Err(e) = repos.as_ref() {
+            return Err(e.clone());
+        }
+        Ok(ReposGuard(repos))
+    }
 }
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 
 #[tauri::command]
-fn fetch_projects(state: tauri::State<'_, AppState>) ->Result<Vec<ProjectFrontEnd>, String>{
-    let projects = state.project_repo.fetch().map_err(|e| e.to_string())?;
-    let project_frontends = projects.into_iter().map(convert_project_to_frontend).collect::<Vec<ProjectFrontEnd>>();
+fn fetch_projects(state: tauri::State<'_, AppState>) ->Result<Vec<ProjectFrontEnd>, AppError>{
+    let repos = state.read_repos()?;
+    let projects = fetch_active_projects(repos.project_repo.as_ref())?;
+    convert_projects_to_frontend(projects, repos.task_repo.as_ref())
+}
 
-    Ok(project_frontends)
+/// 条件に合うプロジェクトを並べ替えてページごとに返す
+#[tauri::command]
+fn search_projects(query:ProjectSearchQuery, state: tauri::State<'_, AppState>) ->Result<ProjectSearchResult, AppError>{
+    let repos = state.read_repos()?;
+    application::project_search_usecase::search_projects(repos.project_repo.as_ref(), repos.task_repo.as_ref(), &query)
 }
 
 #[tauri::command]
-fn fetch_clients(state: tauri::State<'_, AppState>) ->Result<Vec<Client>, String>{
-    state.client_repo.fetch().map_err(|e| e.to_string())
+fn fetch_clients(state: tauri::State<'_, AppState>) ->Result<Vec<Client>, AppError>{
+    let repos = state.read_repos()?;
+    repos.client_repo.fetch()
 }
 
 
 #[tauri::command]
-fn add_project(new_project:Project , state: tauri::State<'_, AppState>) ->Result<(),String>{
+fn add_project(new_project:ProjectFrontEnd , state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    let new_project = convert_ifrontend_to_project(new_project)?;
 
-    if let Err(e) = state.project_repo.add(new_project) {
-        return Err(e.to_string());
-    }
+    repos.project_repo.add(new_project)?;
   
     Ok(())
 }
 
 #[tauri::command]
-fn update_project(mut project:Project, state: tauri::State<'_, AppState>) ->Result<(),String>{
-     // プロジェクトに付随するClientが新しければ、新規作成
-     if let Ok(None) =state.client_repo.get(&project.client.id)  {
-        let new_client_id= state.client_repo.add(project.client.clone()).map_err(|e| e.to_string())?;
-        project.client.id=new_client_id;
-    }
-    if let Err(e) = state.project_repo.update(project){
-        println!("{:?}", e.to_string());
-        return  Err(e.to_string());
+fn update_project(project:ProjectFrontEnd, state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    let project = convert_ifrontend_to_project(project)?;
+    // プロジェクトに付随するClientが新しければ、repositoryがプロジェクトの更新と一緒に追加する
+    let updated = application::usecase::update_project(repos.project_repo.as_ref(), project)?;
+    // 元に戻せるように、更新する前と後のプロジェクトを記録する
+    application::edit_history_usecase::record_project_edit(&repos.edit_history, updated)?;
+    Ok(())
+}
+
+#[tauri::command]
+fn update_client(client:Client , state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    let updated = repos.client_repo.update_with_history(client)?;
+    application::edit_history_usecase::record_client_edit(&repos.edit_history, updated)?;
+    Ok(())
+}
+
+/// プロジェクトとクライアントの最後の変更を取り消す
+#[tauri::command]
+fn undo(state: tauri::State<'_, AppState>) ->Result<UndoRedoState, AppError>{
+    let repos = state.read_repos()?;
+    application::edit_history_usecase::undo(&repos.edit_history, repos.project_repo.as_ref(), repos.client_repo.as_ref())
+}
+
+/// 取り消した変更をもう一度行う
+#[tauri::command]
+fn redo(state: tauri::State<'_, AppState>) ->Result<UndoRedoState, AppError>{
+    let repos = state.read_repos()?;
+    application::edit_history_usecase::redo(&repos.edit_history, repos.project_repo.as_ref(), repos.client_repo.as_ref())
+}
+
+#[tauri::command]
+fn fetch_undo_redo_state(state: tauri::State<'_, AppState>) ->Result<UndoRedoState, AppError>{
+    let repos = state.read_repos()?;
+    application::edit_history_usecase::fetch_undo_redo_state(&repos.edit_history)
+}
+
+#[tauri::command]
+fn fetch_client_detail(id:String, state: tauri::State<'_, AppState>) ->Result<ClientDetail, AppError>{
+    let repos = state.read_repos()?;
+    application::usecase::fetch_client_detail(repos.project_repo.as_ref(), repos.client_repo.as_ref(), repos.task_repo.as_ref(), &id)
+}
+
+#[tauri::command]
+fn fetch_project_history(project_id:String, state: tauri::State<'_, AppState>) ->Result<Vec<ProjectStatusHistory>, AppError>{
+    let repos = state.read_repos()?;
+    repos.status_history_repo.fetch_by_project(&project_id)
+}
+
+/// プロジェクトとクライアントの変更の記録を新しい順に返す
+#[tauri::command]
+fn fetch_audit_log(query:AuditLogQuery, state: tauri::State<'_, AppState>) ->Result<Vec<AuditLogEntry>, AppError>{
+    let repos = state.read_repos()?;
+    application::audit_log_usecase::fetch_audit_log(repos.audit_log_repo.as_ref(), &query)
+}
+
+/// 取り込むファイルのシート名と列の見出しを返す
+#[tauri::command]
+fn read_import_file(file_path:String, sheet_name:Option<String>) ->Result<ImportFileInfo, AppError>{
+    application::import_usecase::read_import_file(&file_path, sheet_name.as_deref())
+}
+
+/// ファイルから取り込むプロジェクトと、行ごとのエラーを返す。何も保存しない
+#[tauri::command]
+fn preview_import(request:ImportRequest, state: tauri::State<'_, AppState>) ->Result<ImportPreview, AppError>{
+    let repos = state.read_repos()?;
+    application::import_usecase::preview_import(repos.project_repo.as_ref(), repos.client_repo.as_ref(), repos.folder_template_repo.as_ref(), &request)
+}
+
+/// ファイルのプロジェクトとクライアントをまとめて取り込む
+#[tauri::command]
+fn import_projects(request:ImportRequest, state: tauri::State<'_, AppState>) ->Result<ImportSummary, AppError>{
+    let repos = state.read_repos()?;
+    application::import_usecase::import_projects(repos.project_repo.as_ref(), repos.client_repo.as_ref(), repos.folder_template_repo.as_ref(), &request)
+}
+
+#[tauri::command]
+fn delete_project(id:String, option:DeleteProjectOption, state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    application::usecase::delete_project(repos.project_repo.as_ref(), repos.task_repo.as_ref(), repos.time_entry_repo.as_ref(), &id, option)?;
+    // ゴミ箱への移動と削除は元に戻す履歴に記録しないので、それより前の変更も元に戻せないようにする
+    application::edit_history_usecase::forget_project_edits(&repos.edit_history, &id)
+}
+
+#[tauri::command]
+fn list_trash(state: tauri::State<'_, AppState>) ->Result<Vec<ProjectFrontEnd>, AppError>{
+    let repos = state.read_repos()?;
+    let projects = application::usecase::list_trash(repos.project_repo.as_ref())?;
+    convert_projects_to_frontend(projects, repos.task_repo.as_ref())
+}
+
+#[tauri::command]
+fn restore_project(id:String, state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    application::usecase::restore_project(repos.project_repo.as_ref(), &id)?;
+    application::edit_history_usecase::forget_project_edits(&repos.edit_history, &id)
+}
+
+#[tauri::command]
+fn purge_trash(state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    let trashed = application::usecase::list_trash(repos.project_repo.as_ref())?;
+    application::usecase::purge_trash(repos.project_repo.as_ref(), repos.task_repo.as_ref(), repos.time_entry_repo.as_ref())?;
+    for project in trashed {
+        application::edit_history_usecase::forget_project_edits(&repos.edit_history, &project.id)?;
     }
     Ok(())
 }
 
 #[tauri::command]
-fn update_client(client:Client , state: tauri::State<'_, AppState>) ->Result<(),String>{
-    if let Err(e) = state.client_repo.update(client){
-        return  Err(e.to_string());
+fn fetch_tasks(project_id:String, state: tauri::State<'_, AppState>) ->Result<Vec<Task>, AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::fetch_tasks(repos.task_repo.as_ref(), &project_id)
+}
+
+#[tauri::command]
+fn add_task(new_task:Task, state: tauri::State<'_, AppState>) ->Result<String, AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::add_task(repos.project_repo.as_ref(), repos.task_repo.as_ref(), new_task)
+}
+
+#[tauri::command]
+fn update_task(task:Task, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::update_task(repos.task_repo.as_ref(), task)
+}
+
+#[tauri::command]
+fn reorder_tasks(project_id:String, parent_id:Option<String>, task_ids:Vec<String>, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::reorder_tasks(repos.task_repo.as_ref(), &project_id, parent_id.as_deref(), task_ids)
+}
+
+#[tauri::command]
+fn complete_task(id:String, done:bool, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::complete_task(repos.task_repo.as_ref(), &id, done)
+}
+
+#[tauri::command]
+fn delete_task(id:String, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::task_usecase::delete_task(repos.task_repo.as_ref(), &id)
+}
+
+#[tauri::command]
+fn start_timer(project_id:String, note:Option<String>, state: tauri::State<'_, AppState>) ->Result<TimeEntry, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::start_timer(repos.project_repo.as_ref(), repos.time_entry_repo.as_ref(), &project_id, note.unwrap_or_default())
+}
+
+#[tauri::command]
+fn stop_timer(state: tauri::State<'_, AppState>) ->Result<TimeEntry, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::stop_timer(repos.time_entry_repo.as_ref())
+}
+
+#[tauri::command]
+fn fetch_running_timer(state: tauri::State<'_, AppState>) ->Result<Option<TimeEntry>, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::fetch_running_timer(repos.time_entry_repo.as_ref())
+}
+
+#[tauri::command]
+fn fetch_time_entries(project_id:String, state: tauri::State<'_, AppState>) ->Result<Vec<TimeEntry>, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::fetch_time_entries(repos.time_entry_repo.as_ref(), &project_id)
+}
+
+#[tauri::command]
+fn add_time_entry(new_entry:TimeEntry, state: tauri::State<'_, AppState>) ->Result<String, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::add_time_entry(repos.project_repo.as_ref(), repos.time_entry_repo.as_ref(), new_entry)
+}
+
+#[tauri::command]
+fn update_time_entry(entry:TimeEntry, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::update_time_entry(repos.project_repo.as_ref(), repos.time_entry_repo.as_ref(), entry)
+}
+
+#[tauri::command]
+fn delete_time_entry(id:String, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::delete_time_entry(repos.time_entry_repo.as_ref(), &id)
+}
+
+#[tauri::command]
+fn fetch_project_time_totals(state: tauri::State<'_, AppState>) ->Result<Vec<ProjectTimeTotal>, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::fetch_project_time_totals(repos.project_repo.as_ref(), repos.time_entry_repo.as_ref())
+}
+
+#[tauri::command]
+fn fetch_client_time_totals(state: tauri::State<'_, AppState>) ->Result<Vec<ClientTimeTotal>, AppError>{
+    let repos = state.read_repos()?;
+    application::time_entry_usecase::fetch_client_time_totals(repos.project_repo.as_ref(), repos.client_repo.as_ref(), repos.time_entry_repo.as_ref())
+}
+
+#[tauri::command]
+fn create_invoice(request:CreateInvoiceRequest, state: tauri::State<'_, AppState>) ->Result<Invoice, AppError>{
+    let repos = state.read_repos()?;
+    application::invoice_usecase::create_invoice(repos.project_repo.as_ref(), repos.client_repo.as_ref(), repos.time_entry_repo.as_ref(), repos.invoice_repo.as_ref(), request)
+}
+
+#[tauri::command]
+fn fetch_invoices(project_id:String, state: tauri::State<'_, AppState>) ->Result<Vec<Invoice>, AppError>{
+    let repos = state.read_repos()?;
+    application::invoice_usecase::fetch_invoices(repos.invoice_repo.as_ref(), &project_id)
+}
+
+#[tauri::command]
+fn delete_client(id:String, state: tauri::State<'_, AppState>) ->Result<(),AppError>{
+    let repos = state.read_repos()?;
+    application::usecase::delete_client(repos.project_repo.as_ref(), repos.client_repo.as_ref(), &id)?;
+    application::edit_history_usecase::forget_client_edits(&repos.edit_history, &id)
+}
+
+#[tauri::command]
+fn fetch_folder_templates(state: tauri::State<'_, AppState>) ->Result<Vec<FolderTemplate>, AppError>{
+    let repos = state.read_repos()?;
+    application::folder_template_usecase::fetch_folder_templates(repos.folder_template_repo.as_ref())
+}
+
+/// フォルダテンプレートを保存する。idが空なら新しく追加する
+#[tauri::command]
+fn save_folder_template(template:FolderTemplate, state: tauri::State<'_, AppState>) ->Result<FolderTemplate, AppError>{
+    let repos = state.read_repos()?;
+    application::folder_template_usecase::save_folder_template(repos.project_repo.as_ref(), repos.folder_template_repo.as_ref(), template)
+}
+
+#[tauri::command]
+fn delete_folder_template(id:String, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::folder_template_usecase::delete_folder_template(repos.project_repo.as_ref(), repos.folder_template_repo.as_ref(), &id)
+}
+
+/// プロジェクトのフォルダの中のファイルの一覧。relative_pathを省略するとプロジェクトのフォルダ全体を返す
+#[tauri::command]
+fn list_project_files(project_id:String, relative_path:Option<String>, state: tauri::State<'_, AppState>) ->Result<Vec<ProjectFileEntry>, AppError>{
+    let repos = state.read_repos()?;
+    application::project_file_usecase::list_project_files(repos.project_repo.as_ref(), &project_id, relative_path.as_deref().unwrap_or(""))
+}
+
+/// 選択したファイルをプロジェクトのフォルダ(documentsやdeliverablesなど)にコピーする
+#[tauri::command]
+fn import_project_files(project_id:String, sources:Vec<String>, destination:String, state: tauri::State<'_, AppState>) ->Result<Vec<String>, AppError>{
+    let repos = state.read_repos()?;
+    application::project_file_usecase::import_project_files(repos.project_repo.as_ref(), &project_id, &sources, &destination)
+}
+
+#[tauri::command]
+fn rename_project_file(project_id:String, relative_path:String, new_name:String, state: tauri::State<'_, AppState>) ->Result<String, AppError>{
+    let repos = state.read_repos()?;
+    application::project_file_usecase::rename_project_file(repos.project_repo.as_ref(), &project_id, &relative_path, &new_name)
+}
+
+#[tauri::command]
+fn move_project_file(project_id:String, relative_path:String, destination:String, state: tauri::State<'_, AppState>) ->Result<String, AppError>{
+    let repos = state.read_repos()?;
+    application::project_file_usecase::move_project_file(repos.project_repo.as_ref(), &project_id, &relative_path, &destination)
+}
+
+#[tauri::command]
+fn delete_project_file(project_id:String, relative_path:String, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::project_file_usecase::delete_project_file(repos.project_repo.as_ref(), &project_id, &relative_path)
+}
+
+/// 終わったプロジェクトをフォルダごとzipにまとめてアーカイブする
+#[tauri::command]
+fn archive_project(id:String, option:ArchiveProjectOption, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::archive_usecase::archive_project(repos.project_repo.as_ref(), repos.task_repo.as_ref(), repos.time_entry_repo.as_ref(), repos.status_history_repo.as_ref(), &id, option)?;
+    application::edit_history_usecase::forget_project_edits(&repos.edit_history, &id)
+}
+
+#[tauri::command]
+fn unarchive_project(id:String, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    let repos = state.read_repos()?;
+    application::archive_usecase::unarchive_project(repos.project_repo.as_ref(), &id)?;
+    application::edit_history_usecase::forget_project_edits(&repos.edit_history, &id)
+}
+
+/// フォルダが見つからないプロジェクトと、どのプロジェクトのものでもないフォルダを返す
+#[tauri::command]
+fn check_project_folders(state: tauri::State<'_, AppState>) ->Result<ProjectFolderReport, AppError>{
+    let repos = state.read_repos()?;
+    let renames = repos.folder_renames.lock()?;
+    application::project_folder_sync_usecase::check_project_folders(repos.project_repo.as_ref(), &renames)
+}
+
+/// フォルダが見つからないプロジェクトを、既にあるフォルダにつなぎ直すか、フォルダを作り直す
+#[tauri::command]
+fn reconcile_project_folder(project_id:String, action:FolderReconcileAction, state: tauri::State<'_, AppState>) ->Result<ProjectFolderReport, AppError>{
+    let repos = state.read_repos()?;
+    let renames = repos.folder_renames.lock()?;
+    let report = application::project_folder_sync_usecase::reconcile_project_folder(repos.project_repo.as_ref(), repos.folder_template_repo.as_ref(), &project_id, action, &renames)?;
+    application::edit_history_usecase::forget_project_edits(&repos.edit_history, &project_id)?;
+    Ok(report)
+}
+
+/// バックアップを新しい順に返す
+#[tauri::command]
+fn list_backups(state: tauri::State<'_, AppState>) ->Result<Vec<Backup>, AppError>{
+    let repos = state.read_repos()?;
+    repos.backup_repo.list()
+}
+
+/// 今すぐバックアップを作る
+#[tauri::command]
+fn create_backup(state: tauri::State<'_, AppState>) ->Result<Backup, AppError>{
+    let repos = state.read_repos()?;
+    let settings = state.settings_repo.load()?;
+    application::backup_usecase::create_backup(&repos.backup_repo, &settings.backup, BackupReason::Manual)
+}
+
+/// バックアップからデータを元に戻す。
+/// 戻す前に今のデータもバックアップし、バックアップの中身を確かめてから入れ替える
+#[tauri::command]
+fn restore_backup(id:String, app: tauri::AppHandle, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    // 入れ替えが終わるまで他のコマンドがデータに触らないように、書き込みロックを取る
+    let mut repos = state.repos.write()?;
+    // データを開けなかった時にも復元できるように、repositoryとは別に作る
+    let data_root = get_data_root();
+    let backup_repo = BackupFileRepository::new(&data_root);
+    let settings = state.settings_repo.load()?;
+    backup_repo.create(BackupReason::BeforeRestore, settings.backup.include_project_folders)?;
+    let staged = backup_repo.stage_restore(&id)?;
+
+    // sqliteのファイルを入れ替えられるように、今のrepositoryを閉じる
+    *repos = Err(AppError::io(tr!("backup.restoring")));
+    let mut unit_of_work = UnitOfWork::new();
+    let result = backup_repo
+        .swap_in(&staged, &mut unit_of_work)
+        .and_then(|_| create_repositories(&data_root));
+    let result = match result {
+        Ok(new_repos) => {
+            unit_of_work.commit();
+            *repos = Ok(new_repos);
+            Ok(())
+        }
+        Err(e) => {
+            // 入れ替えたフォルダを元に戻してから開き直す
+            let e = unit_of_work.rollback(e);
+            *repos = create_repositories(&data_root);
+            Err(e)
+        }
+    };
+    // 展開に使ったフォルダを消せなくても復元の結果は変わらないので、別に知らせる。残ったフォルダは次の復元の前に消す
+    if let Err(e) = staged.cleanup() {
+        let _ = app.emit_all(BACKGROUND_ERROR_EVENT, e);
+    }
+    result
+}
+
+#[tauri::command]
+fn get_backup_settings(state: tauri::State<'_, AppState>) ->Result<BackupSettings, AppError>{
+    Ok(state.settings_repo.load()?.backup)
+}
+
+#[tauri::command]
+fn set_backup_settings(settings:BackupSettings, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    application::backup_usecase::save_backup_settings(&state.settings_repo, settings)
+}
+
+/// プロジェクトのフォルダのファイルを中身で検索する
+#[tauri::command]
+fn search_files(query:String, limit:Option<usize>, state: tauri::State<'_, AppState>) ->Result<Vec<FileSearchHit>, AppError>{
+    let repos = state.read_repos()?;
+    let index = repos.search_index.read()?;
+    Ok(application::file_search_usecase::search_files(&index, &query, limit.unwrap_or(SEARCH_FILES_DEFAULT_LIMIT)))
+}
+
+/// 起動時にデータを開けなかった時のエラー
+#[tauri::command]
+fn fetch_startup_error(state: tauri::State<'_, AppState>) ->Result<Option<AppError>, AppError>{
+    let repos = state.repos.read()?;
+    Ok(repos.as_ref().err().cloned())
+}
+
+#[tauri::command]
+fn get_data_directory() ->Result<String, AppError>{
+    Ok(get_data_root().to_string_lossy().into())
+}
+
+#[tauri::command]
+fn get_locale() ->Result<Locale, AppError>{
+    Ok(domain::i18n::get_locale())
+}
+
+/// 言語を変更する。以降のエラーメッセージはこの言語で返す
+#[tauri::command]
+fn set_locale(locale:Locale, state: tauri::State<'_, AppState>) ->Result<(), AppError>{
+    application::settings_usecase::save_locale(&state.settings_repo, locale)
+}
+
+/// データフォルダを変更する。migrateがtrueなら今のデータを新しいフォルダにコピーしてから切り替える。
+#[tauri::command]
+fn set_data_directory(path:String, migrate:bool, state: tauri::State<'_, AppState>) ->Result<String, AppError>{
+    // 切り替えが終わるまで他のコマンドがデータに触らないように、書き込みロックを取る
+    let mut repos = state.repos.write()?;
+    let current_root = get_data_root();
+    if Path::new(path.trim()) == current_root {
+        return Ok(current_root.to_string_lossy().into());
+    }
+    let new_root = application::settings_usecase::prepare_data_directory(&current_root, &path, migrate)?;
+
+    set_data_root(new_root.clone());
+    let result = create_repositories(&new_root)
+        .and_then(|new_repos| application::settings_usecase::save_data_directory(&state.settings_repo, &new_root).map(|_| new_repos));
+    match result {
+        Ok(new_repos) => {
+            *repos = Ok(new_repos);
+            Ok(new_root.to_string_lossy().into())
+        }
+        Err(e) => {
+            set_data_root(current_root);
+            Err(e)
+        }
+    }
+}
+
+/// 保存先のrepositoryを作成する。
+/// 環境変数 TASK_MANAGER_STORAGE=sqlite の時はsqlite、それ以外はcsvファイルを使う。
+fn create_repositories(data_root: &Path) -> Result<Repositories, AppError> {
+    let search_index_repo = SearchIndexFileRepository::new(data_root);
+    // 索引は作り直せるので、読めなければ空から作る
+    let search_index = RwLock::new(search_index_repo.load().unwrap_or_default());
+    match env::var("TASK_MANAGER_STORAGE").as_deref() {
+        Ok("sqlite") => {
+            let connection = open_database(data_root)?;
+            Ok(Repositories {
+                project_repo: Box::new(SqliteProjectRepository::new(connection.clone())),
+                client_repo: Box::new(SqliteClientRepository::new(connection.clone())),
+                status_history_repo: Box::new(SqliteStatusHistoryRepository::new(connection.clone())),
+                task_repo: Box::new(SqliteTaskRepository::new(connection.clone())),
+                time_entry_repo: Box::new(SqliteTimeEntryRepository::new(connection.clone())),
+                invoice_repo: Box::new(SqliteInvoiceRepository::new(connection.clone())),
+                folder_template_repo: Box::new(SqliteFolderTemplateRepository::new(connection.clone())),
+                audit_log_repo: Box::new(SqliteAuditLogRepository::new(connection)),
+                search_index,
+                search_index_repo,
+                folder_renames: Mutex::new(HashMap::new()),
+                backup_repo: BackupFileRepository::new(data_root),
+                edit_history: Mutex::new(EditHistory::default()),
+            })
+        }
+        _ => {
+            let project_repository = ProjectFileRepository::new(data_root)?;
+            let client_repository = ClientFileRepository::new(&project_repository.get_self_client_file_path());
+            let status_history_repository = StatusHistoryFileRepository::new(&project_repository.get_self_status_history_file_path());
+            let audit_log_repository = AuditLogFileRepository::new(&project_repository.get_self_audit_log_file_path());
+            Ok(Repositories {
+                project_repo: Box::new(project_repository),
+                client_repo: Box::new(client_repository),
+                status_history_repo: Box::new(status_history_repository),
+                task_repo: Box::new(TaskFileRepository::new(data_root)?),
+                time_entry_repo: Box::new(TimeEntryFileRepository::new(data_root)?),
+                invoice_repo: Box::new(InvoiceFileRepository::new(data_root)?),
+                folder_template_repo: Box::new(FolderTemplateFileRepository::new(data_root)?),
+                audit_log_repo: Box::new(audit_log_repository),
+                search_index,
+                search_index_repo,
+                folder_renames: Mutex::new(HashMap::new()),
+                backup_repo: BackupFileRepository::new(data_root),
+                edit_history: Mutex::new(EditHistory::default()),
+            })
+        }
+    }
+}
+
+/// 検索の索引を更新する。
+/// ファイルの読み込みに時間がかかるので、その間はrepositoryのロックを持たない。
+fn update_search_index(state: &AppState) -> Result<(), AppError> {
+    let data_root = get_data_root();
+    let (targets, known) = {
+        let repos = state.read_repos()?;
+        let targets = application::file_search_usecase::collect_index_targets(repos.project_repo.as_ref())?;
+        let known = repos.search_index.read()?.file_states();
+        (targets, known)
+    };
+    let changes = application::file_search_usecase::scan_index_changes(&targets, &known);
+    if changes.is_empty() {
+        return Ok(());
     }
+
+    let repos = state.read_repos()?;
+    // 読み込んでいる間にデータフォルダが変わっていたら、古いフォルダの結果は捨てる
+    if get_data_root() != data_root {
+        return Ok(());
+    }
+    let mut index = repos.search_index.write()?;
+    changes.apply(&mut index);
+    repos.search_index_repo.save(&index)
+}
+
+/// 起動時と一定間隔で、検索の索引をバックグラウンドで更新する
+fn spawn_search_indexer(app: tauri::AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if let Err(e) = update_search_index(state.inner()) {
+            // フロントエンドに送れなければ、次の更新で同じエラーになった時にまた送る
+            let _ = app.emit_all(BACKGROUND_ERROR_EVENT, e);
+        }
+        thread::sleep(SEARCH_INDEX_INTERVAL);
+    });
+}
+
+/// プロジェクト管理フォルダを監視し、プロジェクトのフォルダが作成・削除・名前変更されたらフロントエンドに知らせる
+fn spawn_folder_watcher(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        // フロントエンドに送れなかったイベントは、次にフォルダが変わった時にまとめて確かめ直す
+        let on_error = |e: AppError| {
+            let _ = app.emit_all(BACKGROUND_ERROR_EVENT, e);
+        };
+        let on_events = |manage_path: &Path, events: &[Event]| {
+            let state = app.state::<AppState>();
+            match handle_folder_events(state.inner(), manage_path, events) {
+                Ok(Some(report)) => {
+                    let _ = app.emit_all(PROJECT_FOLDERS_CHANGED_EVENT, report);
+                }
+                Ok(None) => {}
+                Err(e) => on_error(e),
+            }
+        };
+        if let Err(e) = application::folder_watch_usecase::watch_project_folders(on_events, on_error) {
+            on_error(e);
+        }
+    });
+}
+
+/// 設定の間隔で、データのバックアップをバックグラウンドで作る
+fn spawn_backup_scheduler(app: tauri::AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if let Err(e) = run_scheduled_backup(state.inner()) {
+            let _ = app.emit_all(BACKGROUND_ERROR_EVENT, e);
+        }
+        thread::sleep(BACKUP_CHECK_INTERVAL);
+    });
+}
+
+fn run_scheduled_backup(state: &AppState) -> Result<(), AppError> {
+    let settings = state.settings_repo.load()?;
+    // バックアップを作っている間にデータフォルダが入れ替わらないように、読み取りロックを持ったまま作る
+    let repos = state.read_repos()?;
+    application::backup_usecase::run_scheduled_backup(&repos.backup_repo, &settings.backup)?;
     Ok(())
 }
 
+/// ウィンドウを閉じる時にバックアップを作る。
+/// 失敗した時はウィンドウを閉じずにエラーを表示し、もう一度閉じた時はバックアップを作らずに閉じる
+fn handle_close_requested(window: &tauri::Window, api: &tauri::CloseRequestApi) {
+    let state = window.state::<AppState>();
+    if state.exit_backup_attempted.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Err(e) = run_exit_backup(state.inner()) {
+        api.prevent_close();
+        let _ = window.emit(BACKGROUND_ERROR_EVENT, AppError::io(tr!("backup.exit_failed", e)));
+    }
+}
+
+fn run_exit_backup(state: &AppState) -> Result<(), AppError> {
+    let settings = state.settings_repo.load()?;
+    let repos = state.read_repos()?;
+    application::backup_usecase::run_exit_backup(&repos.backup_repo, &settings.backup)?;
+    Ok(())
+}
+
+/// 監視のイベントからプロジェクトのフォルダの変更を拾う。プロジェクトのフォルダに関係なければNoneを返す
+fn handle_folder_events(state: &AppState, manage_path: &Path, events: &[Event]) -> Result<Option<ProjectFolderReport>, AppError> {
+    let repos = state.read_repos()?;
+    let mut renames = repos.folder_renames.lock()?;
+    application::folder_watch_usecase::handle_folder_events(repos.project_repo.as_ref(), &mut renames, manage_path, events)
+}
+
+/// 設定からデータフォルダを決めて、repositoryを作成する。
+/// 失敗してもアプリは起動し、エラーはfetch_startup_errorで画面に表示する。
+fn create_app_state() -> AppState {
+    let settings_repo = SettingsFileRepository::new();
+    // 開けなかった時のエラーは、画面からfetch_startup_errorで取得して表示する
+    let repos = open_data_directory(&settings_repo);
+    AppState {
+        repos: RwLock::new(repos),
+        settings_repo,
+        exit_backup_attempted: AtomicBool::new(false),
+    }
+}
+
+fn open_data_directory(settings_repo: &SettingsFileRepository) -> Result<Repositories, AppError> {
+    let settings = settings_repo.load()?;
+    domain::i18n::set_locale(settings.locale);
+    let data_root = resolve_data_root(&settings);
+    set_data_root(data_root.clone());
+    validate_data_directory(&data_root)?;
+    create_repositories(&data_root)
+}
+
 fn main() {
-   
-   let project_repository = ProjectFileRepository::new();
-   let client_repository = ClientFileRepository::new(&project_repository.get_self_client_file_path());
 
-    
     // repositoryの初期化
-    let app_state = AppState {
-        project_repo: project_repository,
-        client_repo: client_repository,
-        };
+    let app_state = create_app_state();
 
     // test_reading_csv();
     tauri::Builder::default()
         .manage(app_state)  // AppStateをTauriアプリケーションに登録
-        .invoke_handler(tauri::generate_handler![fetch_projects, fetch_clients, add_project,update_project, update_client])
+        .setup(|app| {
+            spawn_search_indexer(app.handle());
+            spawn_folder_watcher(app.handle());
+            spawn_backup_scheduler(app.handle());
+            Ok(())
+        })
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event.event() {
+                handle_close_requested(event.window(), api);
+            }
+        })
+        .invoke_handler(tauri::generate_handler![fetch_projects, search_projects, fetch_clients, add_project,update_project, update_client, fetch_client_detail, delete_project, delete_client, list_trash, restore_project, purge_trash, fetch_project_history, fetch_audit_log, undo, redo, fetch_undo_redo_state, read_import_file, preview_import, import_projects,
+            fetch_tasks, add_task, update_task, reorder_tasks, complete_task, delete_task,
+            start_timer, stop_timer, fetch_running_timer, fetch_time_entries, add_time_entry, update_time_entry, delete_time_entry,
+            fetch_project_time_totals, fetch_client_time_totals, create_invoice, fetch_invoices,
+            fetch_folder_templates, save_folder_template, delete_folder_template,
+            list_project_files, import_project_files, rename_project_file, move_project_file, delete_project_file,
+            check_project_folders, reconcile_project_folder, archive_project, unarchive_project,
+            list_backups, create_backup, restore_backup, get_backup_settings, set_backup_settings,
+            search_files, fetch_startup_error, get_data_directory, set_data_directory, get_locale, set_locale])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 
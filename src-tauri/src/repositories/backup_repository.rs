@@ -0,0 +1,451 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::domain::businesslogic::zip_directory::{add_directory_to_zip, extract_zip_directory, read_zip_text, zip_options};
+use crate::domain::error::AppError;
+use crate::domain::models::backup::{Backup, BackupReason};
+use crate::tr;
+
+use super::csv_migration::{BACKUP_DIRECTORY_NAME as MIGRATION_BACKUP_DIRECTORY_NAME, CURRENT_SCHEMA_VERSION};
+use super::file_lock::{lock_directory, StoreLock};
+use super::sqlite_repository::{copy_database, get_database_file_path};
+use super::unit_of_work::UnitOfWork;
+
+const BACKUP_DIRECTORY_NAME: &str = "backups";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// 復元するデータを展開する場所。データフォルダの中に置いて、入れ替えをフォルダの名前の変更だけで済ませる
+const RESTORE_DIRECTORY_NAME: &str = ".restore";
+const CSV_DIRECTORY_NAME: &str = "files";
+const PROJECT_DIRECTORY_NAME: &str = "project";
+const DATABASE_FILE_NAME: &str = "task_manager.db";
+/// どちらかがなければデータのバックアップとして扱わない
+const REQUIRED_DATA_FILES: [&str; 2] = ["projects.csv", DATABASE_FILE_NAME];
+/// 自動で作り、keep_countを超えたら古いものから消すバックアップ。手動と復元前のバックアップは消さない
+const ROTATED_REASONS: [BackupReason; 2] = [BackupReason::Scheduled, BackupReason::Exit];
+
+/// バックアップのzipに入れる、バックアップの情報
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BackupManifest {
+    created_at: DateTime<Local>,
+    reason: BackupReason,
+    schema_version: u32,
+    includes_project_folders: bool,
+}
+
+/// 復元の準備ができたバックアップ。swap_inでデータフォルダと入れ替える。
+/// cleanupまで、今のcsvのフォルダと展開したcsvのフォルダのロックを持つ。
+/// 入れ替えるとロックファイルもフォルダと一緒に移動するので、どちらがfilesになっても他のウィンドウは書き込めない
+pub struct StagedRestore {
+    staging_directory: PathBuf,
+    directories: Vec<&'static str>,
+    locks: Vec<StoreLock>,
+}
+
+/// データフォルダのbackupsフォルダに、files(csvとsqlite)とプロジェクトのフォルダをzipにまとめて保存する
+pub struct BackupFileRepository {
+    data_root: PathBuf,
+    backup_directory: PathBuf,
+}
+
+impl BackupFileRepository {
+    pub fn new(data_root: &Path) -> Self {
+        Self {
+            data_root: data_root.to_path_buf(),
+            backup_directory: data_root.join(BACKUP_DIRECTORY_NAME),
+        }
+    }
+
+    /// 今のデータのバックアップを作る。書き込み途中のデータを含めないように、csvのフォルダのロックを取ってから読む。
+    /// sqliteのデータベースはロックの外でも書き込まれるので、ファイルをそのまま入れずにVACUUM INTOでコピーしたものを入れる
+    pub fn create(&self, reason: BackupReason, include_project_folders: bool) -> Result<Backup, AppError> {
+        fs::create_dir_all(&self.backup_directory)
+            .map_err(|e| AppError::io(tr!("file.create_directory_failed", self.backup_directory.display(), e)))?;
+        let csv_directory = self.data_root.join(CSV_DIRECTORY_NAME);
+        let _lock = lock_directory(&csv_directory)?;
+
+        let manifest = BackupManifest {
+            created_at: Local::now(),
+            reason,
+            schema_version: CURRENT_SCHEMA_VERSION,
+            includes_project_folders: include_project_folders,
+        };
+        let temp_file = NamedTempFile::new_in(&self.backup_directory)?;
+        let mut writer = ZipWriter::new(temp_file.reopen()?);
+        writer.start_file(MANIFEST_FILE_NAME, zip_options())?;
+        writer.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        // ロックファイルや書き込み途中の一時ファイル、移行前のファイルのバックアップは入れない
+        let migration_backup_directory = csv_directory.join(MIGRATION_BACKUP_DIRECTORY_NAME);
+        add_directory_to_zip(&mut writer, &csv_directory, CSV_DIRECTORY_NAME, |path| {
+            is_hidden(path) || path == migration_backup_directory || is_database_file(path)
+        })?;
+        let database_file_path = get_database_file_path(&self.data_root);
+        if database_file_path.exists() {
+            let database_copy = tempfile::tempdir_in(&self.backup_directory)?;
+            let database_copy_path = database_copy.path().join(DATABASE_FILE_NAME);
+            copy_database(&database_file_path, &database_copy_path)?;
+            writer.start_file(format!("{}/{}", CSV_DIRECTORY_NAME, DATABASE_FILE_NAME), zip_options())?;
+            io::copy(&mut File::open(&database_copy_path)?, &mut writer)?;
+        }
+        let project_directory = self.data_root.join(PROJECT_DIRECTORY_NAME);
+        if include_project_folders && project_directory.exists() {
+            add_directory_to_zip(&mut writer, &project_directory, PROJECT_DIRECTORY_NAME, |_| false)?;
+        }
+        writer.finish()?;
+
+        let id = format!("backup_{}", manifest.created_at.format("%Y%m%d_%H%M%S_%3f"));
+        let path = self.backup_file_path(&id);
+        temp_file.persist(&path)?;
+        Ok(to_backup(id, manifest, fs::metadata(&path)?.len()))
+    }
+
+    /// バックアップを新しい順に返す。
+    /// 壊れたzipが1つあるだけで他のバックアップから復元できなくならないように、読めないファイルは一覧に出さない
+    pub fn list(&self) -> Result<Vec<Backup>, AppError> {
+        if !self.backup_directory.exists() {
+            return Ok(Vec::new());
+        }
+        let read_error = |e: io::Error| AppError::io(tr!("file.read_directory_failed", self.backup_directory.display(), e));
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.backup_directory).map_err(read_error)? {
+            let path = entry.map_err(read_error)?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("zip") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+                continue;
+            };
+            if let Ok(manifest) = read_manifest(&path) {
+                backups.push(to_backup(id, manifest, fs::metadata(&path)?.len()));
+            }
+        }
+        backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+        Ok(backups)
+    }
+
+    /// 自動で作ったバックアップを、新しいものからkeep_count個を残して消す
+    pub fn rotate(&self, keep_count: usize) -> Result<(), AppError> {
+        let rotated = self.list()?.into_iter().filter(|backup| ROTATED_REASONS.contains(&backup.reason));
+        for backup in rotated.skip(keep_count) {
+            let path = self.backup_file_path(&backup.id);
+            fs::remove_file(&path).map_err(|e| AppError::io(tr!("file.remove_file_failed", path.display(), e)))?;
+        }
+        Ok(())
+    }
+
+    /// バックアップを一時フォルダに展開し、復元できるデータか確かめる
+    pub fn stage_restore(&self, id: &str) -> Result<StagedRestore, AppError> {
+        let backup = self
+            .list()?
+            .into_iter()
+            .find(|backup| backup.id == id)
+            .ok_or_else(|| AppError::validation(tr!("backup.not_found", id)))?;
+        if backup.schema_version > CURRENT_SCHEMA_VERSION {
+            return Err(AppError::validation(tr!("migration.newer_schema", backup.schema_version, CURRENT_SCHEMA_VERSION)));
+        }
+
+        // 他のウィンドウがcsvを書き込んでいる途中でフォルダを入れ替えないように、復元が終わるまでロックを持つ
+        let csv_directory = self.data_root.join(CSV_DIRECTORY_NAME);
+        let mut locks = Vec::new();
+        if csv_directory.exists() {
+            locks.push(lock_directory(&csv_directory)?);
+        }
+
+        let staging_directory = self.data_root.join(RESTORE_DIRECTORY_NAME);
+        if staging_directory.exists() {
+            fs::remove_dir_all(&staging_directory)
+                .map_err(|e| AppError::io(tr!("file.remove_directory_failed", staging_directory.display(), e)))?;
+        }
+        let mut directories = vec![CSV_DIRECTORY_NAME];
+        if backup.includes_project_folders {
+            directories.push(PROJECT_DIRECTORY_NAME);
+        }
+        let mut staged = StagedRestore { staging_directory, directories, locks };
+        if let Err(e) = self.extract_backup(id, &staged) {
+            let _ = fs::remove_dir_all(&staged.staging_directory);
+            return Err(e);
+        }
+        staged.locks.push(lock_directory(&staged.staging_directory.join(CSV_DIRECTORY_NAME))?);
+        Ok(staged)
+    }
+
+    fn extract_backup(&self, id: &str, staged: &StagedRestore) -> Result<(), AppError> {
+        let mut zip = ZipArchive::new(File::open(self.backup_file_path(id))?)?;
+        for directory in staged.directories.iter() {
+            extract_zip_directory(&mut zip, directory, &staged.staging_directory.join(directory))?;
+        }
+        validate_csv_directory(&staged.staging_directory.join(CSV_DIRECTORY_NAME))
+    }
+
+    /// 展開したバックアップをデータフォルダと入れ替える。
+    /// 移行前のファイルのバックアップはバックアップに入っていないので、今のデータから引き継ぐ。
+    /// unit_of_workがcommitされずにdropされたら、元のデータに戻す
+    pub fn swap_in(&self, staged: &StagedRestore, unit_of_work: &mut UnitOfWork) -> Result<(), AppError> {
+        let previous_directory = staged.staging_directory.join("previous");
+        fs::create_dir_all(&previous_directory)
+            .map_err(|e| AppError::io(tr!("file.create_directory_failed", previous_directory.display(), e)))?;
+        for directory in staged.directories.iter() {
+            let current = self.data_root.join(directory);
+            let previous = previous_directory.join(directory);
+            let restored = staged.staging_directory.join(directory);
+            if current.exists() {
+                let (from, to) = (current.clone(), previous.clone());
+                unit_of_work.run(
+                    &tr!("step.move_current_data"),
+                    || rename(&current, &previous),
+                    move |_| rename(&to, &from),
+                )?;
+                let kept = previous.join(MIGRATION_BACKUP_DIRECTORY_NAME);
+                if *directory == CSV_DIRECTORY_NAME && kept.exists() {
+                    let restored_kept = restored.join(MIGRATION_BACKUP_DIRECTORY_NAME);
+                    let (from, to) = (kept.clone(), restored_kept.clone());
+                    unit_of_work.run(
+                        &tr!("step.keep_migration_backup"),
+                        || rename(&kept, &restored_kept),
+                        move |_| rename(&to, &from),
+                    )?;
+                }
+            }
+            let (from, to) = (restored.clone(), current.clone());
+            unit_of_work.run(
+                &tr!("step.place_backup_data"),
+                || rename(&restored, &current),
+                move |_| rename(&to, &from),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn backup_file_path(&self, id: &str) -> PathBuf {
+        self.backup_directory.join(format!("{}.zip", id))
+    }
+}
+
+impl StagedRestore {
+    /// 入れ替えが終わった後にロックを解除し、展開に使ったフォルダと入れ替える前のデータを消す
+    pub fn cleanup(mut self) -> Result<(), AppError> {
+        self.locks.clear();
+        fs::remove_dir_all(&self.staging_directory)
+            .map_err(|e| AppError::io(tr!("file.remove_directory_failed", self.staging_directory.display(), e)))
+    }
+}
+
+fn to_backup(id: String, manifest: BackupManifest, size: u64) -> Backup {
+    Backup {
+        id,
+        created_at: manifest.created_at,
+        reason: manifest.reason,
+        schema_version: manifest.schema_version,
+        includes_project_folders: manifest.includes_project_folders,
+        size,
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<BackupManifest, AppError> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    Ok(serde_json::from_str(&read_zip_text(&mut zip, MANIFEST_FILE_NAME)?)?)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+// データベースと、書き込み中に一緒に作られるファイル(-wal, -shm, -journal)
+fn is_database_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(DATABASE_FILE_NAME))
+}
+
+fn rename(from: &Path, to: &Path) -> Result<(), AppError> {
+    fs::rename(from, to).map_err(|e| AppError::io(tr!("file.move_directory_failed", from.display(), to.display(), e)))
+}
+
+/// 展開したデータが読めるか確かめる。csvはすべての行を、jsonは全体を読んでみる
+fn validate_csv_directory(csv_directory: &Path) -> Result<(), AppError> {
+    if !REQUIRED_DATA_FILES.iter().any(|name| csv_directory.join(name).exists()) {
+        return Err(AppError::validation(tr!("backup.invalid", csv_directory.display())));
+    }
+    let read_error = |e: io::Error| AppError::io(tr!("file.read_directory_failed", csv_directory.display(), e));
+    for entry in fs::read_dir(csv_directory).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        let invalid = |e: String| AppError::validation(tr!("backup.invalid_file", path.display(), e));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => {
+                let mut reader = csv::Reader::from_path(&path).map_err(|e| invalid(e.to_string()))?;
+                for record in reader.records() {
+                    record.map_err(|e| invalid(e.to_string()))?;
+                }
+            }
+            Some("json") => {
+                serde_json::from_reader::<_, serde_json::Value>(io::BufReader::new(File::open(&path)?)).map_err(|e| invalid(e.to_string()))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::repositories::file_repository::{AuditLogFileRepository, ProjectFileRepository};
+    use crate::repositories::repository_trait::{AuditLogRepository, Repository};
+    use crate::repositories::sqlite_repository::{open_database, SqliteAuditLogRepository, SqliteClientRepository};
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn zip_entry_names(backup_repo: &BackupFileRepository, id: &str) -> Vec<String> {
+        let zip = ZipArchive::new(File::open(backup_repo.backup_file_path(id)).unwrap()).unwrap();
+        zip.file_names().map(str::to_string).collect()
+    }
+
+    fn restore(backup_repo: &BackupFileRepository, id: &str) {
+        let staged = backup_repo.stage_restore(id).unwrap();
+        let mut unit_of_work = UnitOfWork::new();
+        backup_repo.swap_in(&staged, &mut unit_of_work).unwrap();
+        unit_of_work.commit();
+        staged.cleanup().unwrap();
+    }
+
+    // バックアップのidはミリ秒までの作成日時なので、続けて作る時は少し待つ
+    fn create(backup_repo: &BackupFileRepository, reason: BackupReason) -> Backup {
+        thread::sleep(Duration::from_millis(5));
+        backup_repo.create(reason, false).unwrap()
+    }
+
+    #[test]
+    fn restore_round_trips_csv_and_project_folders() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+        let backup = backup_repo.create(BackupReason::Manual, true).unwrap();
+        assert!(backup.includes_project_folders);
+        assert_eq!(backup.schema_version, CURRENT_SCHEMA_VERSION);
+
+        project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap();
+        fs::remove_dir_all(root.path().join(PROJECT_DIRECTORY_NAME).join(&id)).unwrap();
+        restore(&backup_repo, &backup.id);
+
+        let projects = project_repo.fetch().unwrap();
+        assert_eq!(projects.iter().map(|project| project.title.as_str()).collect::<Vec<&str>>(), vec!["案件A"]);
+        assert!(root.path().join(PROJECT_DIRECTORY_NAME).join(&id).join("documents").is_dir());
+        assert!(!root.path().join(RESTORE_DIRECTORY_NAME).exists());
+    }
+
+    #[test]
+    fn restore_keeps_audit_log() {
+        let root = TestDataRoot::new();
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let audit_log_repo = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        let backup_repo = BackupFileRepository::new(root.path());
+        let backup = backup_repo.create(BackupReason::Manual, false).unwrap();
+        project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let entries = audit_log_repo.fetch().unwrap().len();
+
+        restore(&backup_repo, &backup.id);
+
+        // 復元で消えたプロジェクトの追加の記録も残る
+        assert!(project_repo.fetch().unwrap().is_empty());
+        assert_eq!(audit_log_repo.fetch().unwrap().len(), entries);
+        assert!(zip_entry_names(&backup_repo, &backup.id).iter().all(|name| !name.contains("audit_log")));
+    }
+
+    #[test]
+    fn migration_backups_are_not_zipped_but_kept_on_restore() {
+        let root = TestDataRoot::new();
+        ProjectFileRepository::new(root.path()).unwrap();
+        let migration_backup = root.csv_directory().join(MIGRATION_BACKUP_DIRECTORY_NAME).join("schema_v1");
+        fs::create_dir_all(&migration_backup).unwrap();
+        fs::write(migration_backup.join("projects.csv"), "id\n").unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+
+        let backup = backup_repo.create(BackupReason::Manual, false).unwrap();
+
+        let names = zip_entry_names(&backup_repo, &backup.id);
+        assert!(names.contains(&"files/projects.csv".to_string()));
+        assert!(names.iter().all(|name| !name.starts_with("files/backup")), "{:?}", names);
+        restore(&backup_repo, &backup.id);
+        assert!(migration_backup.join("projects.csv").exists());
+    }
+
+    #[test]
+    fn rotate_removes_only_automatic_backups() {
+        let root = TestDataRoot::new();
+        ProjectFileRepository::new(root.path()).unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+        let manual = create(&backup_repo, BackupReason::Manual);
+        let before_restore = create(&backup_repo, BackupReason::BeforeRestore);
+        create(&backup_repo, BackupReason::Scheduled);
+        create(&backup_repo, BackupReason::Exit);
+        let latest = create(&backup_repo, BackupReason::Scheduled);
+
+        backup_repo.rotate(1).unwrap();
+
+        let ids = backup_repo.list().unwrap().into_iter().map(|backup| backup.id).collect::<Vec<String>>();
+        assert_eq!(ids, vec![latest.id, before_restore.id, manual.id]);
+    }
+
+    #[test]
+    fn database_is_copied_into_backup() {
+        let root = TestDataRoot::new();
+        let client_repo = SqliteClientRepository::new(open_database(root.path()).unwrap());
+        client_repo.add(sample_client("", "取引先")).unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+
+        let backup = backup_repo.create(BackupReason::Manual, false).unwrap();
+
+        let names = zip_entry_names(&backup_repo, &backup.id);
+        assert_eq!(names.iter().filter(|name| name.starts_with("files/task_manager.db")).collect::<Vec<&String>>(), vec!["files/task_manager.db"]);
+        let extracted = tempfile::tempdir().unwrap();
+        let mut zip = ZipArchive::new(File::open(backup_repo.backup_file_path(&backup.id)).unwrap()).unwrap();
+        extract_zip_directory(&mut zip, CSV_DIRECTORY_NAME, &extracted.path().join(CSV_DIRECTORY_NAME)).unwrap();
+        let connection = open_database(extracted.path()).unwrap();
+        assert_eq!(SqliteClientRepository::new(connection.clone()).fetch().unwrap().len(), 1);
+        // 監査ログは別のデータベースにあるので、バックアップには入らない
+        assert!(SqliteAuditLogRepository::new(connection).fetch().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unreadable_zip_is_not_listed() {
+        let root = TestDataRoot::new();
+        ProjectFileRepository::new(root.path()).unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+        let backup = backup_repo.create(BackupReason::Manual, false).unwrap();
+        fs::write(root.path().join(BACKUP_DIRECTORY_NAME).join("broken.zip"), "not a zip").unwrap();
+
+        let ids = backup_repo.list().unwrap().into_iter().map(|backup| backup.id).collect::<Vec<String>>();
+        assert_eq!(ids, vec![backup.id]);
+    }
+
+    #[test]
+    fn restore_holds_csv_lock_until_cleanup() {
+        let root = TestDataRoot::new();
+        ProjectFileRepository::new(root.path()).unwrap();
+        let backup_repo = BackupFileRepository::new(root.path());
+        let backup = create(&backup_repo, BackupReason::Manual);
+        let staged = backup_repo.stage_restore(&backup.id).unwrap();
+        let mut unit_of_work = UnitOfWork::new();
+        backup_repo.swap_in(&staged, &mut unit_of_work).unwrap();
+        unit_of_work.commit();
+
+        // 他のウィンドウの書き込みは、入れ替えたフォルダでも復元が終わるまで待つ
+        let csv_directory = root.csv_directory();
+        let writer = thread::spawn(move || {
+            let _lock = lock_directory(&csv_directory).unwrap();
+            Instant::now()
+        });
+        thread::sleep(Duration::from_millis(300));
+        let cleaned_at = Instant::now();
+        staged.cleanup().unwrap();
+
+        assert!(writer.join().unwrap() >= cleaned_at);
+    }
+}
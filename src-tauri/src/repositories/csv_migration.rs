@@ -0,0 +1,394 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::domain::businesslogic::project_date::{format_date, parse_date};
+use crate::domain::error::AppError;
+use crate::tr;
+
+/// csvファイルの現在のスキーマのバージョン。列を変更したらMIGRATIONSに手順を追加して上げる
+pub const CURRENT_SCHEMA_VERSION: u32 = 8;
+
+const SCHEMA_VERSION_FILE_NAME: &str = "schema_version.json";
+/// 移行前のファイルを残すフォルダ。データのバックアップには入れない
+pub const BACKUP_DIRECTORY_NAME: &str = "backup";
+
+/// バージョンを上げる手順。applyはcsvファイルのフォルダを受け取る
+struct Migration {
+    version: u32,
+    description: &'static str, // メッセージカタログのキー
+    apply: fn(&Path) -> Result<(), AppError>,
+}
+
+/// 古いバージョンから順番に適用する。
+/// バージョンファイルがない時代のデータは途中まで列が追加されていることがあるので、各手順は何度実行しても同じ結果になるように書く。
+const MIGRATIONS: [Migration; 7] = [
+    Migration {
+        version: 2,
+        description: "migration.add_deleted_at",
+        apply: add_project_deleted_at,
+    },
+    Migration {
+        version: 3,
+        description: "migration.rename_folder_path",
+        apply: rename_project_folder_path,
+    },
+    Migration {
+        version: 4,
+        description: "migration.add_client_details",
+        apply: add_client_details,
+    },
+    Migration {
+        version: 5,
+        description: "migration.add_revision",
+        apply: add_project_revision,
+    },
+    Migration {
+        version: 6,
+        description: "migration.add_template_id",
+        apply: add_project_template_id,
+    },
+    Migration {
+        version: 7,
+        description: "migration.add_archived_at",
+        apply: add_project_archived_at,
+    },
+    Migration {
+        version: 8,
+        description: "migration.normalize_dates",
+        apply: normalize_project_dates,
+    },
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SchemaVersion {
+    version: u32,
+}
+
+/// csvファイルのスキーマを最新にする。移行の前に元のファイルをbackupフォルダにコピーする。
+pub fn migrate_csv_schema(csv_directory: &Path) -> Result<(), AppError> {
+    let version = match read_schema_version(csv_directory)? {
+        Some(version) => version,
+        // バージョンファイルがなく、projects.csvもなければ新規インストール
+        None if !csv_directory.join("projects.csv").exists() => return write_schema_version(csv_directory, CURRENT_SCHEMA_VERSION),
+        // バージョンファイルを作る前のデータ
+        None => 1,
+    };
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(AppError::validation(tr!(
+            "migration.newer_schema",
+            version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+    if version == CURRENT_SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let backup_path = backup_csv_files(csv_directory, version)?;
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > version) {
+        (migration.apply)(csv_directory).map_err(|e| {
+            AppError::io(tr!(
+                "migration.failed",
+                migration.version,
+                tr!(migration.description),
+                e,
+                backup_path.display()
+            ))
+        })?;
+        // 途中で失敗しても、次回は失敗した手順から再開する
+        write_schema_version(csv_directory, migration.version)?;
+    }
+    Ok(())
+}
+
+fn read_schema_version(csv_directory: &Path) -> Result<Option<u32>, AppError> {
+    let path = csv_directory.join(SCHEMA_VERSION_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)?;
+    let schema_version: SchemaVersion = serde_json::from_str(&contents)
+        .map_err(|e| AppError::parse(tr!("migration.read_version_failed", path.display(), e)))?;
+    Ok(Some(schema_version.version))
+}
+
+/// 書き込み途中で落ちてもバージョンファイルが壊れないように、同じフォルダの一時ファイルから入れ替える
+fn write_schema_version(csv_directory: &Path, version: u32) -> Result<(), AppError> {
+    fs::create_dir_all(csv_directory)?;
+    let contents = serde_json::to_string_pretty(&SchemaVersion { version })?;
+    let mut temp_file = NamedTempFile::new_in(csv_directory)?;
+    temp_file.write_all(contents.as_bytes())?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(csv_directory.join(SCHEMA_VERSION_FILE_NAME))?;
+    Ok(())
+}
+
+/// 移行前のファイルを files/backup/schema_v{バージョン}_{日時}/ にコピーし、そのパスを返す
+fn backup_csv_files(csv_directory: &Path, version: u32) -> Result<PathBuf, AppError> {
+    let backup_path = csv_directory
+        .join(BACKUP_DIRECTORY_NAME)
+        .join(format!("schema_v{}_{}", version, Local::now().format("%Y%m%d%H%M%S")));
+    fs::create_dir_all(&backup_path).map_err(|e| AppError::io(tr!("file.create_directory_failed", backup_path.display(), e)))?;
+    let entries = fs::read_dir(csv_directory)?;
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            let destination = backup_path.join(entry.file_name());
+            fs::copy(entry.path(), &destination)
+                .map_err(|e| AppError::io(tr!("file.copy_failed", entry.path().display(), destination.display(), e)))?;
+        }
+    }
+    Ok(backup_path)
+}
+
+/// csvファイルをヘッダーと行に分けて読み込み、変更してから書き戻す
+fn rewrite_csv(path: &Path, change: impl FnOnce(&mut Vec<String>, &mut Vec<Vec<String>>) -> Result<(), AppError>) -> Result<(), AppError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_path(path)?;
+    let mut headers = rdr.headers()?.iter().map(String::from).collect::<Vec<String>>();
+    let mut rows = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        rows.push(record.iter().map(String::from).collect::<Vec<String>>());
+    }
+    drop(rdr);
+
+    change(&mut headers, &mut rows)?;
+
+    let directory = path.parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", path.display())))?;
+    let mut temp_file = NamedTempFile::new_in(directory)?;
+    {
+        let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(&mut temp_file);
+        wtr.write_record(&headers)?;
+        for row in rows.iter() {
+            wtr.write_record(row)?;
+        }
+        wtr.flush()?;
+    }
+    temp_file.persist(path)?;
+    Ok(())
+}
+
+/// 列がなければ末尾に追加し、既存の行は初期値で埋める
+fn add_columns(path: &Path, columns: &[(&str, &str)]) -> Result<(), AppError> {
+    rewrite_csv(path, |headers, rows| {
+        for (column, default) in columns.iter() {
+            if headers.iter().any(|header| header == column) {
+                continue;
+            }
+            headers.push(column.to_string());
+            for row in rows.iter_mut() {
+                // 列が足りない行があっても、追加する列の位置に揃える
+                row.resize(headers.len() - 1, String::new());
+                row.push(default.to_string());
+            }
+        }
+        Ok(())
+    })
+}
+
+fn add_project_deleted_at(csv_directory: &Path) -> Result<(), AppError> {
+    add_columns(&csv_directory.join("projects.csv"), &[("deleted_at", "")])
+}
+
+/// folder_pathには実行ファイルのフォルダからの絶対パスが入っていたので、最後のフォルダ名だけを残す
+fn rename_project_folder_path(csv_directory: &Path) -> Result<(), AppError> {
+    rewrite_csv(&csv_directory.join("projects.csv"), |headers, rows| {
+        let Some(index) = headers.iter().position(|header| header == "folder_path") else {
+            return Ok(());
+        };
+        headers[index] = "folder_path_suffix".to_string();
+        for row in rows.iter_mut() {
+            let Some(folder_path) = row.get_mut(index) else {
+                continue;
+            };
+            let suffix = Path::new(folder_path.as_str())
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| AppError::validation(tr!("migration.invalid_folder_path", folder_path)))?;
+            *folder_path = suffix;
+        }
+        Ok(())
+    })
+}
+
+fn add_client_details(csv_directory: &Path) -> Result<(), AppError> {
+    add_columns(
+        &csv_directory.join("clients.csv"),
+        &[
+            ("email", ""),
+            ("phone", ""),
+            ("postal_code", ""),
+            ("address", ""),
+            ("billing_name", ""),
+            ("billing_address", ""),
+            ("billing_email", ""),
+            ("notes", ""),
+        ],
+    )
+}
+
+fn add_project_revision(csv_directory: &Path) -> Result<(), AppError> {
+    add_columns(&csv_directory.join("projects.csv"), &[("revision", "0")])
+}
+
+fn add_project_template_id(csv_directory: &Path) -> Result<(), AppError> {
+    add_columns(&csv_directory.join("projects.csv"), &[("template_id", "")])
+}
+
+fn add_project_archived_at(csv_directory: &Path) -> Result<(), AppError> {
+    add_columns(&csv_directory.join("projects.csv"), &[("archived_at", "")])
+}
+
+
+/// 古いフロントエンドがロケールの書式で保存した日付をYYYY-MM-DD形式にそろえる
+fn normalize_project_dates(csv_directory: &Path) -> Result<(), AppError> {
+    rewrite_csv(&csv_directory.join("projects.csv"), |headers, rows| {
+        let indexes = ["order_date", "due_date", "completion_date"]
+            .iter()
+            .filter_map(|column| headers.iter().position(|header| header == column).map(|index| (*column, index)))
+            .collect::<Vec<(&str, usize)>>();
+        for row in rows.iter_mut() {
+            for (column, index) in indexes.iter() {
+                let Some(value) = row.get_mut(*index) else {
+                    continue;
+                };
+                if value.trim().is_empty() {
+                    continue;
+                }
+                *value = format_date(&parse_date(column, value)?);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// バージョンファイルがない時代のprojects.csvとclients.csv
+    fn write_legacy_files(directory: &Path) {
+        fs::write(
+            directory.join("projects.csv"),
+            "id,title,description,order_date,due_date,completion_date,client_id,status,folder_path\n1,案件A,,2024-04-01,2024-04-30,,1,NotStarted,/old/project/案件A\n",
+        )
+        .unwrap();
+        fs::write(directory.join("clients.csv"), "id,name,contact_person\n1,取引先,担当者\n").unwrap();
+    }
+
+    #[test]
+    fn new_install_starts_at_current_version() {
+        let directory = tempfile::tempdir().unwrap();
+        let csv_directory = directory.path().join("files");
+
+        migrate_csv_schema(&csv_directory).unwrap();
+
+        assert_eq!(read_schema_version(&csv_directory).unwrap(), Some(CURRENT_SCHEMA_VERSION));
+        assert!(!csv_directory.join(BACKUP_DIRECTORY_NAME).exists());
+    }
+
+    #[test]
+    fn legacy_files_are_migrated_to_current_version() {
+        let directory = tempfile::tempdir().unwrap();
+        write_legacy_files(directory.path());
+
+        migrate_csv_schema(directory.path()).unwrap();
+
+        let projects = fs::read_to_string(directory.path().join("projects.csv")).unwrap();
+        assert_eq!(
+            projects,
+            "id,title,description,order_date,due_date,completion_date,client_id,status,folder_path_suffix,deleted_at,revision,template_id,archived_at\n\
+             1,案件A,,2024-04-01,2024-04-30,,1,NotStarted,案件A,,0,,\n"
+        );
+        let clients = fs::read_to_string(directory.path().join("clients.csv")).unwrap();
+        assert!(clients.starts_with("id,name,contact_person,email,phone,postal_code,address,billing_name,billing_address,billing_email,notes\n"));
+        assert_eq!(read_schema_version(directory.path()).unwrap(), Some(CURRENT_SCHEMA_VERSION));
+
+        // 移行前のファイルはbackupに残る
+        let backups = fs::read_dir(directory.path().join(BACKUP_DIRECTORY_NAME)).unwrap().collect::<Vec<_>>();
+        assert_eq!(backups.len(), 1);
+        let backup_path = backups[0].as_ref().unwrap().path();
+        assert!(fs::read_to_string(backup_path.join("projects.csv")).unwrap().contains("/old/project/案件A"));
+
+        // 最新になっていれば何もしない
+        migrate_csv_schema(directory.path()).unwrap();
+        assert_eq!(fs::read_to_string(directory.path().join("projects.csv")).unwrap(), projects);
+    }
+
+    #[test]
+    fn migrations_can_run_twice() {
+        let directory = tempfile::tempdir().unwrap();
+        write_legacy_files(directory.path());
+        for migration in MIGRATIONS.iter() {
+            (migration.apply)(directory.path()).unwrap();
+        }
+        let projects = fs::read_to_string(directory.path().join("projects.csv")).unwrap();
+
+        for migration in MIGRATIONS.iter() {
+            (migration.apply)(directory.path()).unwrap();
+        }
+        assert_eq!(fs::read_to_string(directory.path().join("projects.csv")).unwrap(), projects);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        write_legacy_files(directory.path());
+        write_schema_version(directory.path(), CURRENT_SCHEMA_VERSION + 1).unwrap();
+
+        assert!(matches!(migrate_csv_schema(directory.path()), Err(AppError::Validation(_))));
+        assert_eq!(read_schema_version(directory.path()).unwrap(), Some(CURRENT_SCHEMA_VERSION + 1));
+    }
+
+    #[test]
+    fn write_schema_version_leaves_no_temp_files() {
+        let directory = tempfile::tempdir().unwrap();
+        write_schema_version(directory.path(), 3).unwrap();
+        write_schema_version(directory.path(), 4).unwrap();
+
+        let names = fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec![SCHEMA_VERSION_FILE_NAME.to_string()]);
+        assert_eq!(read_schema_version(directory.path()).unwrap(), Some(4));
+    }
+
+    #[test]
+    fn normalize_dates_rewrites_legacy_formats() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(
+            directory.path().join("projects.csv"),
+            "id,title,order_date,due_date,completion_date\n1,案件A,2024/1/5,1/31/2024,\n2,案件B,2024-02-01,2024-02-29,29.2.2024\n",
+        )
+        .unwrap();
+        write_schema_version(directory.path(), 7).unwrap();
+
+        migrate_csv_schema(directory.path()).unwrap();
+
+        let contents = fs::read_to_string(directory.path().join("projects.csv")).unwrap();
+        assert_eq!(
+            contents,
+            "id,title,order_date,due_date,completion_date\n1,案件A,2024-01-05,2024-01-31,\n2,案件B,2024-02-01,2024-02-29,2024-02-29\n"
+        );
+        assert_eq!(read_schema_version(directory.path()).unwrap(), Some(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn normalize_dates_keeps_version_when_a_date_is_invalid() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join("projects.csv"), "id,title,order_date,due_date,completion_date\n1,案件A,来週,2024-01-31,\n").unwrap();
+        write_schema_version(directory.path(), 7).unwrap();
+
+        assert!(migrate_csv_schema(directory.path()).is_err());
+        // 失敗した手順は次回もう一度実行する
+        assert_eq!(read_schema_version(directory.path()).unwrap(), Some(7));
+    }
+}
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+
+use crate::domain::error::AppError;
+use crate::tr;
+
+const LOCK_FILE_NAME: &str = ".lock";
+/// 他のプロセスがロックを持っている時に待つ時間
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    // このスレッドが既に持っているロックと、その入れ子の深さ。
+    // ProjectFileRepositoryの中からClientFileRepositoryを呼ぶ時などに、自分自身のロックを待たないようにする。
+    static HELD_LOCKS: RefCell<HashMap<PathBuf, usize>> = RefCell::new(HashMap::new());
+}
+
+/// csvファイルのフォルダ単位の排他ロック。dropで解除する。
+/// データファイルは一時ファイルとの入れ替えで別のファイルになるので、ロックは専用の.lockファイルに取る。
+pub struct StoreLock {
+    lock_path: PathBuf,
+    file: Option<File>, // 一番外側のロックだけがファイルを持つ
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(depth) = held.get_mut(&self.lock_path) {
+                *depth -= 1;
+                if *depth == 0 {
+                    held.remove(&self.lock_path);
+                }
+            }
+        });
+        if let Some(file) = self.file.take() {
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// フォルダのロックを取る。他のプロセスやスレッドが持っていれば、LOCK_TIMEOUTまで待つ。
+pub fn lock_directory(directory: &Path) -> Result<StoreLock, AppError> {
+    let lock_path = directory.join(LOCK_FILE_NAME);
+    let already_held = HELD_LOCKS.with(|held| {
+        let mut held = held.borrow_mut();
+        match held.get_mut(&lock_path) {
+            Some(depth) => {
+                *depth += 1;
+                true
+            }
+            None => false,
+        }
+    });
+    if already_held {
+        return Ok(StoreLock { lock_path, file: None });
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| AppError::io(tr!("file.open_lock_failed", lock_path.display(), e)))?;
+    let started_at = Instant::now();
+    while file.try_lock_exclusive().is_err() {
+        if started_at.elapsed() >= LOCK_TIMEOUT {
+            return Err(AppError::io(tr!("store.locked")));
+        }
+        thread::sleep(LOCK_RETRY_INTERVAL);
+    }
+    HELD_LOCKS.with(|held| held.borrow_mut().insert(lock_path.clone(), 1));
+    Ok(StoreLock { lock_path, file: Some(file) })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    // 別のスレッドから、ロックファイルのロックをすぐに取れるか
+    fn can_lock_from_other_thread(directory: &Path) -> bool {
+        let lock_path = directory.join(LOCK_FILE_NAME);
+        thread::spawn(move || {
+            let file = OpenOptions::new().create(true).truncate(false).write(true).open(lock_path).unwrap();
+            let locked = file.try_lock_exclusive().is_ok();
+            if locked {
+                file.unlock().unwrap();
+            }
+            locked
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[test]
+    fn nested_lock_in_same_thread_is_reentrant() {
+        let directory = tempfile::tempdir().unwrap();
+        let outer = lock_directory(directory.path()).unwrap();
+        let inner = lock_directory(directory.path()).unwrap();
+        assert!(inner.file.is_none());
+
+        // 内側を外してもロックは残る
+        drop(inner);
+        assert!(!can_lock_from_other_thread(directory.path()));
+
+        drop(outer);
+        assert!(can_lock_from_other_thread(directory.path()));
+        assert!(HELD_LOCKS.with(|held| held.borrow().is_empty()));
+    }
+
+    #[test]
+    fn other_thread_waits_until_lock_is_released() {
+        let directory = tempfile::tempdir().unwrap();
+        let lock = lock_directory(directory.path()).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let path = directory.path().to_path_buf();
+        let waiter = thread::spawn(move || {
+            sender.send(()).unwrap();
+            let started_at = Instant::now();
+            let _lock = lock_directory(&path).unwrap();
+            started_at.elapsed()
+        });
+
+        receiver.recv().unwrap();
+        thread::sleep(Duration::from_millis(300));
+        drop(lock);
+
+        assert!(waiter.join().unwrap() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn different_directories_do_not_share_locks() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let _first_lock = lock_directory(first.path()).unwrap();
+        let second_lock = lock_directory(second.path()).unwrap();
+        assert!(second_lock.file.is_some());
+    }
+}
@@ -1,11 +1,26 @@
-use crate::domain::models::client::Client;
+use crate::domain::error::{AppError, Entity};
+use crate::domain::models::client::{BillingDetails, Client, ClientContact};
 use crate::domain::models::project::{Project, ProjectStatus};
-
-use crate::domain::businesslogic::project_file::{create_project_directories, delete_project_directories, get_folder_path_suffix, get_project_directory_path, get_project_manage_path, rename_project_directory};
+use crate::domain::models::project_status_history::ProjectStatusHistory;
+use crate::domain::models::task::Task;
+use crate::domain::models::time_entry::TimeEntry;
+use crate::domain::models::invoice::Invoice;
+use crate::domain::models::folder_template::FolderTemplate;
+use crate::domain::models::audit_log::{AuditAction, AuditEntity, AuditLogEntry, FieldChange};
+
+use crate::domain::businesslogic::project_date::{format_date, format_datetime, parse_date, parse_datetime, parse_optional_date, parse_optional_datetime, validate_project_dates};
+use crate::domain::businesslogic::project_status::{apply_status_change, initial_status_history, normalize_completion_date, record_status_change};
+use crate::domain::businesslogic::project_file::{create_project_directories, delete_project_directories, get_folder_path_suffix, get_project_directory_path, get_project_manage_path, is_project_folder_missing, rename_project_directory};
+use crate::domain::businesslogic::folder_template::resolve_folder_template;
+use crate::domain::businesslogic::invoice::assign_invoice_number;
+use crate::domain::businesslogic::time_tracking::validate_time_entry;
+use crate::domain::businesslogic::audit_log::{folder_rename_audit_log_entry, new_audit_log_entry, project_audit_log_entry};
+use crate::tr;
 
 use csv;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::fs::{File};
 
@@ -15,74 +30,90 @@ use std::path::{Path, PathBuf};
 
 use tempfile::NamedTempFile;
 
-use super::repository_trait::Repository;
+use super::csv_migration::migrate_csv_schema;
+use super::file_lock::{lock_directory, StoreLock};
+use super::repository_trait::{AuditLogRepository, Repository, StatusHistoryRepository, UpdatedItem};
+use super::unit_of_work::UnitOfWork;
+
+const PROJECT_CSV_HEADERS: [&str; 13] = ["id","title","description","order_date","due_date","completion_date","client_id","status","folder_path_suffix","deleted_at","revision","template_id","archived_at"];
+const CLIENT_CSV_HEADERS: [&str; 11] = ["id","name","contact_person","email","phone","postal_code","address","billing_name","billing_address","billing_email","notes"];
+const CLIENT_CONTACT_CSV_HEADERS: [&str; 5] = ["client_id","name","role","email","phone"];
+const CLIENT_CONTACT_FILE_NAME: &str = "client_contacts.csv";
+const STATUS_HISTORY_CSV_HEADERS: [&str; 4] = ["project_id","from_status","to_status","changed_at"];
+const TASK_CSV_HEADERS: [&str; 8] = ["id","project_id","parent_id","title","description","due_date","done","sort_order"];
+const TIME_ENTRY_CSV_HEADERS: [&str; 5] = ["id","project_id","started_at","ended_at","note"];
+const AUDIT_LOG_CSV_HEADERS: [&str; 6] = ["timestamp","entity","entity_id","action","changes","user"];
+const AUDIT_LOG_FILE_NAME: &str = "audit_log.csv";
+/// ファイルごとに、これまでに振った最大のidを残すファイル
+const ID_SEQUENCE_FILE_NAME: &str = "id_sequences.json";
+/// 監査ログのフォルダ。バックアップから復元した時に巻き戻らないように、filesフォルダの外に置く
+pub const AUDIT_DIRECTORY_NAME: &str = "audit";
 
 trait FileRepository {
     fn get_file_path(&self) -> &Path;
     /// 初期化処理
-    fn initialize_file_repository() -> std::io::Result<(String,String)> {
+    fn initialize_file_repository(data_root: &Path) -> Result<(String,String,String), AppError> {
         // プロジェクト管理フォルダの作成
         let project_manage_path = get_project_manage_path();
-        let csv_file_directory_path = Self::get_csv_file_directory_path();
+        let csv_file_directory_path = Self::get_csv_file_directory_path(data_root);
         Self::ensure_directory_exists(&project_manage_path)?;
         Self::ensure_directory_exists(&csv_file_directory_path)?;
 
+        // 古いバージョンのcsvファイルを最新の列にする。他のウィンドウと同時に移行しないようにロックを取る
+        let _lock = lock_directory(&csv_file_directory_path)?;
+        migrate_csv_schema(&csv_file_directory_path)?;
+
         // プロジェクトのファイルフォルダ作成
 
         // プロジェクトCSVファイルの作成
-        let project_file_path_buf = Self::get_project_file_path();
-        Self::ensure_csv_file_exists(&project_file_path_buf, &["id","title","description","order_date","due_date","completion_date","client_id","status","folder_path"])?;
+        let project_file_path_buf = Self::get_project_file_path(data_root);
+        Self::ensure_csv_file_exists(&project_file_path_buf, &PROJECT_CSV_HEADERS)?;
 
         // クライアントCSVファイルの作成
-        let client_file_path_buf = Self::get_client_file_path();
-        Self::ensure_csv_file_exists(&client_file_path_buf, &["id","name","contact_person"])?;
+        let client_file_path_buf = Self::get_client_file_path(data_root);
+        Self::ensure_csv_file_exists(&client_file_path_buf, &CLIENT_CSV_HEADERS)?;
+        Self::ensure_csv_file_exists(&client_file_path_buf.with_file_name(CLIENT_CONTACT_FILE_NAME), &CLIENT_CONTACT_CSV_HEADERS)?;
+
+        // ステータス履歴CSVファイルの作成
+        let status_history_file_path_buf = Self::get_status_history_file_path(data_root);
+        Self::ensure_csv_file_exists(&status_history_file_path_buf, &STATUS_HISTORY_CSV_HEADERS)?;
+
+        // 監査ログCSVファイルの作成。filesフォルダに置いていた頃のファイルは監査ログのフォルダへ移す
+        let audit_log_file_path_buf = get_audit_log_file_path(&project_file_path_buf);
+        if let Some(audit_directory_path) = audit_log_file_path_buf.parent() {
+            Self::ensure_directory_exists(audit_directory_path)?;
+        }
+        let legacy_audit_log_file_path_buf = project_file_path_buf.with_file_name(AUDIT_LOG_FILE_NAME);
+        if legacy_audit_log_file_path_buf.exists() && !audit_log_file_path_buf.exists() {
+            std::fs::rename(&legacy_audit_log_file_path_buf, &audit_log_file_path_buf)?;
+        }
+        Self::ensure_csv_file_exists(&audit_log_file_path_buf, &AUDIT_LOG_CSV_HEADERS)?;
 
-        let project_file_path = project_file_path_buf.to_str().expect("can not get project file path").to_string();
-        let client_file_path = client_file_path_buf.to_str().expect("can not get client file path").to_string();
+        let project_file_path = project_file_path_buf.to_string_lossy().to_string();
+        let client_file_path = client_file_path_buf.to_string_lossy().to_string();
+        let status_history_file_path = status_history_file_path_buf.to_string_lossy().to_string();
 
-        Ok((project_file_path,client_file_path) )
+        Ok((project_file_path,client_file_path,status_history_file_path) )
     }
 
     // projectの情報を格納するファイルのパス
-    fn get_project_file_path() -> PathBuf {
-        // 現在の実行ファイルのパスを取得
-        let mut exe_path = std::env::current_exe().expect("Failed to get current exe path");
-        
-        // 実行ファイルがあるディレクトリに移動
-        exe_path.pop();
-        
-        // "files/projects.csv" へのパスを追加
-        exe_path.push("files/projects.csv");
-        
-        exe_path
+    fn get_project_file_path(data_root: &Path) -> PathBuf {
+        Self::get_csv_file_directory_path(data_root).join("projects.csv")
     }
 
     // clientの情報を格納するファイルのパス
-    fn get_client_file_path() -> PathBuf {
-        // 現在の実行ファイルのパスを取得
-        let mut exe_path = std::env::current_exe().expect("Failed to get current exe path");
-        
-        // 実行ファイルがあるディレクトリに移動
-        exe_path.pop();
-        
-        // "files/clients.csv" へのパスを追加
-        exe_path.push("files/clients.csv");
-        
-        exe_path
+    fn get_client_file_path(data_root: &Path) -> PathBuf {
+        Self::get_csv_file_directory_path(data_root).join("clients.csv")
     }
 
+    // projectのステータス変更履歴を格納するファイルのパス
+    fn get_status_history_file_path(data_root: &Path) -> PathBuf {
+        Self::get_csv_file_directory_path(data_root).join("project_status_history.csv")
+    }
 
     // アプリが作成するファイルのディレクトリのパス
-    fn get_csv_file_directory_path() -> PathBuf {
-        // 現在の実行ファイルのパスを取得
-        let mut exe_path = std::env::current_exe().expect("Failed to get current exe path");
-        
-        // 実行ファイルがあるディレクトリに移動
-        exe_path.pop();
-        
-        exe_path.push("files/");
-        
-        exe_path
+    fn get_csv_file_directory_path(data_root: &Path) -> PathBuf {
+        data_root.join("files/")
     }
 
    
@@ -96,15 +127,20 @@ trait FileRepository {
     }
 
     // csvファイルが作成するかチェック。なければ作成。
-    fn ensure_csv_file_exists(path: &Path, headers: &[&str]) -> io::Result<()> {
+    // 列の変更はcsv_migrationで行うので、ここではヘッダーが一致するかだけを確認する。
+    fn ensure_csv_file_exists(path: &Path, headers: &[&str]) -> Result<(), AppError> {
         let file_exists = path.exists();
         
         if file_exists {
-            // ファイルが存在する場合、ヘッダーの確認（オプション）
             let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_path(path)?;
             let rdr_headers = rdr.headers()?;
             if rdr_headers != headers {
-                return Err(io::Error::new(io::ErrorKind::Other, "CSV header mismatch"));
+                return Err(AppError::parse(tr!(
+                    "csv.header_mismatch",
+                    path.display(),
+                    headers.join(","),
+                    rdr_headers.iter().collect::<Vec<&str>>().join(",")
+                )));
             }
         } else {
             // ファイルが存在しない場合、新規作成
@@ -115,12 +151,84 @@ trait FileRepository {
 
         Ok(())
     }
+
+    // csvファイルのフォルダのロックを取る。
+    // 読み込んでから書き戻すまでの間に、他のウィンドウや同期ツールが書き込まないようにする。
+    fn lock_store(&self) -> Result<StoreLock, AppError> {
+        let directory = self.get_file_path().parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", self.get_file_path().display())))?;
+        lock_directory(directory)
+    }
+
+    // 書き込み用の一時ファイル。renameで入れ替えられるように、元のファイルと同じフォルダに作る
+    fn new_temp_file(&self) -> Result<NamedTempFile, AppError> {
+        let directory = self.get_file_path().parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", self.get_file_path().display())))?;
+        NamedTempFile::new_in(directory).map_err(AppError::from)
+    }
+
+    // csvファイルの全行を読み込む
+    fn read_csv_rows<T: DeserializeOwned>(&self) -> Result<Vec<T>, AppError> {
+        let file = File::open(self.get_file_path())?;
+        let mut rdr = csv::Reader::from_reader(file);
+        let mut rows = Vec::new();
+        for result in rdr.deserialize() {
+            let row: T = result?;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    // csvファイルの末尾に1行追記する
+    fn append_csv_row<T: Serialize>(&self, row: &T) -> Result<(), AppError> {
+        let file = OpenOptions::new().append(true).open(self.get_file_path())?;
+        let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+        wtr.serialize(row)?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    // 一時ファイルに全行を書き込んでから、元のファイルと入れ替える
+    fn write_csv_rows<T: Serialize>(&self, headers: &[&str], rows: &[T]) -> Result<(), AppError> {
+        let mut temp_file = self.new_temp_file()?;
+        {
+            // 0行の場合でもヘッダーが残るように、ヘッダーは自分で書き込みます。
+            let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(&mut temp_file);
+            wtr.write_record(headers)?;
+            for row in rows {
+                wtr.serialize(row)?;
+            }
+            wtr.flush()?;
+        }
+        std::fs::rename(temp_file.path(), self.get_file_path())?;
+        Ok(())
+    }
+
+    // 新しいidを振る。ロックを取った中で呼ぶ。
+    // 最後の行を消しても同じidを使い回さないように、これまでに振った最大のidをファイルごとに残しておき、今の行の最大と大きい方の次にする
+    fn next_id(&self, ids: impl Iterator<Item = i32>) -> Result<i32, AppError> {
+        let directory = self.get_file_path().parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", self.get_file_path().display())))?;
+        let sequence_file_path = directory.join(ID_SEQUENCE_FILE_NAME);
+        let key = self.get_file_path().file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let mut sequences: BTreeMap<String, i32> = if sequence_file_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&sequence_file_path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        let new_id = ids.chain(sequences.get(&key).copied()).max().unwrap_or(0) + 1;
+        sequences.insert(key, new_id);
+
+        let mut temp_file = self.new_temp_file()?;
+        serde_json::to_writer_pretty(&mut temp_file, &sequences)?;
+        std::fs::rename(temp_file.path(), &sequence_file_path)?;
+        Ok(new_id)
+    }
     // 他の共通のメソッドもここに追加
 }
 
 
 pub struct ClientFileRepository {
     file_path: String,
+    contacts: ClientContactFile,
+    audit_log: AuditLogFileRepository,
 }
 
 impl FileRepository for ClientFileRepository{
@@ -131,48 +239,108 @@ impl FileRepository for ClientFileRepository{
 
 impl ClientFileRepository {
     pub fn new(file_path: &str) -> Self {
+        // 担当者のcsvはclients.csvと同じフォルダに置く
+        let contacts_file_path = Path::new(file_path).with_file_name(CLIENT_CONTACT_FILE_NAME);
+        let audit_log_file_path = get_audit_log_file_path(Path::new(file_path));
         Self {
             file_path: file_path.to_string(),
+            contacts: ClientContactFile {
+                file_path: contacts_file_path.to_string_lossy().to_string(),
+            },
+            audit_log: AuditLogFileRepository::new(&audit_log_file_path.to_string_lossy()),
         }
     }
 
-    pub fn new_client_id(&self) -> Result<i32, String> {
-        let file = File::open(&self.file_path).map_err(|e| e.to_string())?;
-        let mut rdr = csv::Reader::from_reader(file);
-        let mut max_id = 0;
-    
-        for result in rdr.deserialize() {
-            let csv: Result<Client, csv::Error> = result;
-            let csv = csv.map_err(|e| e.to_string())?;
-            let id = csv.id.parse::<i32>().map_err(|e| e.to_string())?;
-            if id > max_id {
-                max_id = id;
-            }
+    pub fn new_client_id(&self) -> Result<i32, AppError> {
+        let mut ids = Vec::new();
+        for csv in self.read_csv_rows::<ClientCSV>()? {
+            ids.push(csv.id.parse::<i32>().map_err(|e| AppError::parse(tr!("client.invalid_id_in_file", csv.id, e)))?);
         }
-    
-        Ok(max_id + 1)
+        self.next_id(ids.into_iter())
     }
 
-    pub fn fetch(&self) -> Result<Vec<Client>, Box<dyn Error>> {
-        let file = File::open(&self.file_path)?;
-        let mut rdr = csv::Reader::from_reader(file);
-        let mut clients = Vec::new();
+    // 担当者のcsvから、指定したクライアント以外の行を読み込む
+    fn other_clients_contacts(&self, client_id: &str) -> Result<Vec<ClientContactCSV>, AppError> {
+        Ok(self
+            .contacts
+            .read_csv_rows::<ClientContactCSV>()?
+            .into_iter()
+            .filter(|contact| contact.client_id != client_id)
+            .collect())
+    }
 
-        for result in rdr.deserialize() {
-            let client: Client = result?;
-            clients.push(client);
-        }
+    // 指定したクライアントの担当者を差し替えた、担当者のcsvの全行
+    fn contacts_with(&self, client: &Client) -> Result<Vec<ClientContactCSV>, AppError> {
+        let mut contacts = self.other_clients_contacts(&client.id)?;
+        contacts.extend(client.contacts.iter().map(|contact| convert_contact_to_csv(&client.id, contact)));
+        Ok(contacts)
+    }
 
-        Ok(clients)
+    // クライアントを追加する書き込みをunit_of_workに積み、idと監査ログの記録を返す。監査ログの追記は呼び出し側で行う
+    fn insert<'a>(&'a self, unit_of_work: &mut UnitOfWork<'a>, mut new_client: Client) -> Result<(String, AuditLogEntry), AppError> {
+        // 新しいクライアントIDの生成
+        let new_id = unit_of_work.check(self.new_client_id())?.to_string();
+        new_client.id = new_id.clone();
+
+        let mut rows = unit_of_work.check(self.read_csv_rows::<ClientCSV>())?;
+        rows.push(convert_client_to_csv(&new_client));
+        let contacts = unit_of_work.check(self.contacts_with(&new_client))?;
+        self.save_rows(unit_of_work, &rows, &contacts)?;
+        let entry = unit_of_work.check(new_audit_log_entry(AuditEntity::Client, &new_id, AuditAction::Add, None, Some(&new_client)))?;
+        Ok((new_id, entry))
     }
 
+    // クライアントのcsvと担当者のcsvを書き換える。後の書き込みが失敗したら、どちらも書き換える前の行に戻す
+    fn save_rows<'a>(&'a self, unit_of_work: &mut UnitOfWork<'a>, rows: &[ClientCSV], contacts: &[ClientContactCSV]) -> Result<(), AppError> {
+        let old_rows = unit_of_work.check(self.read_csv_rows::<ClientCSV>())?;
+        let old_contacts = unit_of_work.check(self.contacts.read_csv_rows::<ClientContactCSV>())?;
+        unit_of_work.run(
+            &tr!("step.write_clients"),
+            || self.write_csv_rows(&CLIENT_CSV_HEADERS, rows),
+            move |_| self.write_csv_rows(&CLIENT_CSV_HEADERS, &old_rows),
+        )?;
+        unit_of_work.run(
+            &tr!("step.write_contacts"),
+            || self.contacts.write_csv_rows(&CLIENT_CONTACT_CSV_HEADERS, contacts),
+            move |_| self.contacts.write_csv_rows(&CLIENT_CONTACT_CSV_HEADERS, &old_contacts),
+        )
+    }
+}
 
+/// クライアントの担当者を保存するcsvファイル
+struct ClientContactFile {
+    file_path: String,
+}
+
+impl FileRepository for ClientContactFile{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
 }
 
 
 impl Repository<Client> for ClientFileRepository {
-    fn get(&self, id: &str) -> Result<Option<Client>, String> {
-        let clients = self.fetch().map_err(|e| e.to_string())?;
+    fn fetch(&self) -> Result<Vec<Client>, AppError> {
+        let _lock = self.lock_store()?;
+        let contacts = self.contacts.read_csv_rows::<ClientContactCSV>()?;
+        let clients = self
+            .read_csv_rows::<ClientCSV>()?
+            .into_iter()
+            .map(|csv| {
+                let client_contacts = contacts
+                    .iter()
+                    .filter(|contact| contact.client_id == csv.id)
+                    .map(convert_csv_to_contact)
+                    .collect();
+                convert_csv_to_client(csv, client_contacts)
+            })
+            .collect();
+        Ok(clients)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Client>, AppError> {
+        let _lock = self.lock_store()?;
+        let clients = self.fetch()?;
         for client in clients {
             if client.id == id {
                 return Ok(Some(client));
@@ -180,65 +348,84 @@ impl Repository<Client> for ClientFileRepository {
         }
         Ok(None)
     }
-    fn add(&self, mut new_client: Client) -> Result<String, String>{
-        // 新しいクライアントIDの生成
-        let new_id = self.new_client_id()?;
-        new_client.id = new_id.to_string();
-
-        let file_path = Path::new(&self.file_path);
-        let file_exists = file_path.exists();
+    fn add(&self, new_client: Client) -> Result<String, AppError>{
+        let _lock = self.lock_store()?;
+        let mut unit_of_work = UnitOfWork::new();
+        let (new_id, entry) = self.insert(&mut unit_of_work, new_client)?;
+        append_audit_log(&mut unit_of_work, &self.audit_log, &[entry])?;
+        unit_of_work.commit();
+
+        Ok(new_id)
+    }
+    fn update(&self,  updated_client:Client) ->Result<(),AppError>{
+        self.update_with_history(updated_client)?;
+        Ok(())
+    }
 
-        let file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(file_path)
-            .map_err(|err| err.to_string())?;
+    fn update_with_history(&self, updated_client:Client) ->Result<UpdatedItem<Client>,AppError>{
+        let _lock = self.lock_store()?;
+        let old_client = self.get(&updated_client.id)?.ok_or_else(|| AppError::not_found(Entity::Client, &updated_client.id))?;
+        let mut rows = self.read_csv_rows::<ClientCSV>()?;
+        let row = rows
+            .iter_mut()
+            .find(|row| row.id == updated_client.id)
+            .ok_or_else(|| AppError::not_found(Entity::Client, &updated_client.id))?;
+        *row = convert_client_to_csv(&updated_client);
+        let contacts = self.contacts_with(&updated_client)?;
+        let mut unit_of_work = UnitOfWork::new();
+        self.save_rows(&mut unit_of_work, &rows, &contacts)?;
+        let entry = unit_of_work.check(new_audit_log_entry(AuditEntity::Client, &updated_client.id, AuditAction::Update, Some(&old_client), Some(&updated_client)))?;
+        if !entry.changes.is_empty() {
+            append_audit_log(&mut unit_of_work, &self.audit_log, &[entry])?;
+        }
+        let after = unit_of_work.check(self.get(&updated_client.id).and_then(|client| client.ok_or_else(|| AppError::not_found(Entity::Client, &updated_client.id))))?;
+        unit_of_work.commit();
+        Ok(UpdatedItem { before: old_client, after, created_client_id: None })
+    }
 
-        let mut wtr = if file_exists {
-            // ファイルが存在する場合、ヘッダーを書き込まずにWriterを生成
-            csv::WriterBuilder::new().has_headers(false).from_writer(file)
-        } else {
-            // ファイルが新規作成された場合、ヘッダーを書き込むようにWriterを生成
-            csv::WriterBuilder::new().has_headers(true).from_writer(file)
-        };
-        wtr.serialize(new_client).map_err(|err| err.to_string())?;
-        wtr.flush().map_err(|err| err.to_string())?;
+    fn revert(&self, client:Client) ->Result<UpdatedItem<Client>,AppError>{
+        self.update_with_history(client)
+    }
 
-        Ok(new_id.to_string())
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let old_client = self.get(id)?.ok_or_else(|| AppError::not_found(Entity::Client, id))?;
+        let mut rows = self.read_csv_rows::<ClientCSV>()?;
+        rows.retain(|row| row.id != id);
+        let contacts = self.other_clients_contacts(id)?;
+        let mut unit_of_work = UnitOfWork::new();
+        self.save_rows(&mut unit_of_work, &rows, &contacts)?;
+        let entry = unit_of_work.check(new_audit_log_entry(AuditEntity::Client, id, AuditAction::Delete, Some(&old_client), None))?;
+        append_audit_log(&mut unit_of_work, &self.audit_log, &[entry])?;
+        unit_of_work.commit();
+        Ok(())
     }
-    fn update(&self,  updated_client:Client) ->Result<(),String>{
-        // 一時ファイルを作成します。
-        let mut temp_file = NamedTempFile::new().expect("tempfiles作成失敗");
 
-        {
-            // 元のファイルを開きます。
-            let file = File::open(&self.file_path).map_err(|e| e.to_string())?;
-            let mut rdr = csv::Reader::from_reader(file);
-            let mut wtr = csv::Writer::from_writer(&mut temp_file);
-
-            // CSVリーダーを使用して、クライアントを1つずつ処理します。
-            for result in rdr.deserialize() {
-                let mut client: Client = result.map_err(|e| e.to_string())?;
-                
-                // 見つけたクライアントが更新すべきものであれば、更新します。
-                if client.id == updated_client.id {
-                    client = updated_client.clone();
-                }
+    }
 
-                // 一時ファイルに書き込みます。
-                wtr.serialize(&client).map_err(|e| e.to_string())?;
-            }
-            // 一時ファイルを元のファイル名にリネームする前に、元のファイルを閉じることを確認します。
-            drop(wtr);
-            drop(rdr);
-        }
-        // 元のファイルと一時ファイルを入れ替えます。
-        std::fs::rename(temp_file.path(), &self.file_path).map_err(|e| e.to_string())?;
-        
-        Ok(())
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientCSV {
+    id: String,
+    name: String,
+    contact_person: String,
+    email: String,
+    phone: String,
+    postal_code: String,
+    address: String,
+    billing_name: String,
+    billing_address: String,
+    billing_email: String,
+    notes: String,
 }
 
-    }
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientContactCSV {
+    client_id: String,
+    name: String,
+    role: String,
+    email: String,
+    phone: String,
+}
 
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -247,17 +434,26 @@ pub struct ProjectCSV {
     pub title: String,
     pub description: String,
     // pub category: String, // ウェブデザイン、イラストなど
-    pub order_date: String, // 実際にはより適切な日付型を使用する
+    pub order_date: String, // ISO-8601(YYYY-MM-DD)で保存する
     pub due_date: String,
     pub completion_date: Option<String>,
     pub client_id: String,
     pub status: ProjectStatus,
-    pub folder_path:String, 
+    pub folder_path_suffix:String, // プロジェクト管理フォルダからの相対パス
+    pub deleted_at: Option<String>,
+    pub revision: u64,
+    pub template_id: Option<String>,
+    pub archived_at: Option<String>,
 }
 
 pub struct ProjectFileRepository {
     project_file_path: String,
     client_file_path: String,
+    status_history_file_path: String,
+    folder_template_repository: FolderTemplateFileRepository,
+    audit_log: AuditLogFileRepository,
+    clients: ClientFileRepository,
+    status_histories: StatusHistoryFileRepository,
 }
 
 impl FileRepository for ProjectFileRepository{
@@ -267,13 +463,19 @@ impl FileRepository for ProjectFileRepository{
 }
 
 impl ProjectFileRepository {
-    pub fn new() -> Self {
-
-        let (project_file_path,client_file_path ) = Self::initialize_file_repository().expect("Failed to initialize file repository");        
-        Self {
+    pub fn new(data_root: &Path) -> Result<Self, AppError> {
+
+        let (project_file_path,client_file_path,status_history_file_path ) = Self::initialize_file_repository(data_root)?;
+        let audit_log_file_path = get_audit_log_file_path(Path::new(&project_file_path));
+        Ok(Self {
+            audit_log: AuditLogFileRepository::new(&audit_log_file_path.to_string_lossy()),
+            clients: ClientFileRepository::new(&client_file_path),
+            status_histories: StatusHistoryFileRepository::new(&status_history_file_path),
             project_file_path: project_file_path,
-            client_file_path:client_file_path
-        }
+            client_file_path:client_file_path,
+            status_history_file_path:status_history_file_path,
+            folder_template_repository: FolderTemplateFileRepository::new(data_root)?,
+        })
     }
 
     /// Returns the get self client file path of this [`ProjectFileRepository`].
@@ -281,24 +483,177 @@ impl ProjectFileRepository {
         self.client_file_path.to_string()
     }
 
-    pub fn new_project_id(&self) -> Result<i32, String> {
-        let file = File::open(self.get_file_path()).map_err(|e| e.to_string())?;
-        let mut rdr = csv::Reader::from_reader(file);
-        let mut max_id = 0;
-    
-        for result in rdr.deserialize() {
-            let project_csv: Result<ProjectCSV, csv::Error> = result;
-            let project_csv = project_csv.map_err(|e| e.to_string())?;
-            let id = project_csv.id.parse::<i32>().unwrap_or(0);
-            if id > max_id {
-                max_id = id;
-            }
+    /// Returns the get self status history file path of this [`ProjectFileRepository`].
+    pub fn get_self_status_history_file_path(&self)->String{
+        self.status_history_file_path.to_string()
+    }
+
+    /// Returns the get self audit log file path of this [`ProjectFileRepository`].
+    pub fn get_self_audit_log_file_path(&self)->String{
+        self.audit_log.file_path.to_string()
+    }
+
+    // プロジェクトの行を削除する。追加を取り消す時に使う
+    fn remove_project_row(&self, id: &str) -> Result<(), AppError> {
+        let mut rows = self.read_csv_rows::<ProjectCSV>()?;
+        rows.retain(|row| row.id != id);
+        self.write_csv_rows(&PROJECT_CSV_HEADERS, &rows)
+    }
+
+    /// プロジェクトを更新し、更新する前と後のプロジェクトを返す。check_status_transitionがfalseの時は、ステータスの遷移表を確かめない
+    fn save_update(&self, mut updated_project:Project, check_status_transition: bool) ->Result<UpdatedItem<Project>,AppError>{
+        let _lock = self.lock_store()?;
+        let new_suffix = get_folder_path_suffix(&updated_project);
+
+        let new_folder_path = get_project_directory_path(&new_suffix);
+        let old_project = self.get(&updated_project.id)?.ok_or_else(|| AppError::not_found(Entity::Project, &updated_project.id))?;
+        // 読み込んだ後に他の画面で更新されていたら保存しない
+        if old_project.revision != updated_project.revision {
+            return Err(AppError::conflict(Entity::Project, &updated_project.id));
         }
-    
-        Ok(max_id + 1)
+        updated_project.revision += 1;
+        // フォルダはもう作ってあるので、テンプレートは追加した時のものから変えない
+        updated_project.template_id = old_project.template_id.clone();
+        let old_suffix = get_folder_path_suffix(&old_project);
+        // 元のフォルダがアプリの外で移動・削除されている時は、フォルダを動かさずにsuffixだけを付け替える
+        let rename_folder = old_suffix != new_suffix && !is_project_folder_missing(&old_project);
+        if rename_folder &&  new_folder_path.exists() {
+            return Err(AppError::duplicate_folder(&new_suffix))
+        }
+
+        // ステータスの遷移を確認し、完了日をステータスに合わせる
+        let status_history = if check_status_transition {
+            apply_status_change(&old_project, &mut updated_project)?
+        } else {
+            record_status_change(&old_project, &mut updated_project)
+        };
+        validate_project_dates(&updated_project)?;
+
+        // 失敗した時に書き戻せるように、更新前の行を取っておく
+        let old_rows = self.read_csv_rows::<ProjectCSV>()?;
+
+        // ここから先の書き込みは、途中で失敗したらすべて取り消す。
+        // 監査ログは最後にまとめて追記するので、取り消したクライアントの追加は記録に残らない
+        let mut unit_of_work = UnitOfWork::new();
+        let mut audit_log_entries = Vec::new();
+
+        // 指定されたクライアントが新規の時はクライアントのcsvに追記する。
+        let mut created_client_id = None;
+        if unit_of_work.check(self.clients.get(&updated_project.client.id))?.is_none() {
+            let (client_id, entry) = self.clients.insert(&mut unit_of_work, updated_project.client.clone())?;
+            audit_log_entries.push(entry);
+            updated_project.client.id = client_id.clone();
+            created_client_id = Some(client_id);
+        }
+        let id = updated_project.id.clone();
+
+        // 変更した項目を監査ログに残す。フォルダの名前の変更は別に記録する
+        updated_project.folder_path_suffix = Some(new_suffix.clone());
+        let update_entry = unit_of_work.check(project_audit_log_entry(AuditAction::Update, Some(&old_project), Some(&updated_project)))?;
+        if !update_entry.changes.is_empty() {
+            audit_log_entries.push(update_entry);
+        }
+        if rename_folder {
+            audit_log_entries.push(folder_rename_audit_log_entry(&updated_project.id, &old_suffix, &new_suffix));
+        }
+
+        let updated_project_csv = unit_of_work.check(convert_project_to_csv(updated_project))?;
+        let new_rows = old_rows
+            .iter()
+            .map(|row| if row.id == updated_project_csv.id { updated_project_csv.clone() } else { row.clone() })
+            .collect::<Vec<ProjectCSV>>();
+
+        // もしもsuffixが更新されていたら、フォルダ構造を更新
+        if rename_folder {
+            unit_of_work.run(
+                &tr!("step.rename_project_folder"),
+                || rename_project_directory(get_project_directory_path(&old_suffix), &new_suffix),
+                |_| rename_project_directory(new_folder_path.clone(), &old_suffix),
+            )?;
+        }
+
+        unit_of_work.run(
+            &tr!("step.update_project"),
+            || self.write_csv_rows(&PROJECT_CSV_HEADERS, &new_rows),
+            |_| self.write_csv_rows(&PROJECT_CSV_HEADERS, &old_rows),
+        )?;
+
+        if let Some(status_history) = status_history {
+            add_status_history(&mut unit_of_work, &self.status_histories, status_history)?;
+        }
+        append_audit_log(&mut unit_of_work, &self.audit_log, &audit_log_entries)?;
+        // 更新した後のプロジェクトもロックを持ったまま読み込み、他の書き込みが混ざらないようにする
+        let updated_project = unit_of_work.check(self.get(&id).and_then(|project| project.ok_or_else(|| AppError::not_found(Entity::Project, &id))))?;
+        unit_of_work.commit();
+        Ok(UpdatedItem { before: old_project, after: updated_project, created_client_id })
+    }
+
+    /// プロジェクトを追加する書き込みをunit_of_workに積み、監査ログの記録をaudit_log_entriesに集める。
+    /// new_clientsは追加した新しいクライアントの名前とidで、同じ名前の新しいクライアントは1つだけ追加する
+    fn insert<'a>(&'a self, unit_of_work: &mut UnitOfWork<'a>, templates: &[FolderTemplate], mut new_project: Project, new_clients: &mut HashMap<String, String>, audit_log_entries: &mut Vec<AuditLogEntry>) -> Result<String, AppError> {
+        // 新しいプロジェクトIDの生成
+        let new_id = unit_of_work.check(self.new_project_id())?.to_string();
+        new_project.id = new_id.clone();
+        new_project.revision = 0;
+
+        // 完了日をステータスに合わせてから保存する。書き込みを始める前に日付を確認しておく
+        normalize_completion_date(&mut new_project);
+        unit_of_work.check(validate_project_dates(&new_project))?;
+        let status_history = initial_status_history(&new_project);
+
+        let project_folder_suffix = get_folder_path_suffix(&new_project);
+        new_project.folder_path_suffix = Some(project_folder_suffix.clone());
+        let project_path = get_project_directory_path(&project_folder_suffix);
+        if project_path.exists(){
+            return Err(unit_of_work.rollback(AppError::duplicate_folder(&project_folder_suffix)))
+        }
+        let template = unit_of_work.check(resolve_folder_template(templates.to_vec(), new_project.template_id.as_deref()))?;
+
+        // 指定されたクライアントが新規の時はクライアントのcsvに追記する。
+        if unit_of_work.check(self.clients.get(&new_project.client.id))?.is_none() {
+            new_project.client.id = match new_clients.get(&new_project.client.name) {
+                Some(client_id) => client_id.clone(),
+                None => {
+                    let (client_id, entry) = self.clients.insert(unit_of_work, new_project.client.clone())?;
+                    audit_log_entries.push(entry);
+                    new_clients.insert(new_project.client.name.clone(), client_id.clone());
+                    client_id
+                }
+            };
+        }
+
+        // プロジェクトディレクトリの作成
+        let (created_path, created_template, created_project) = (project_path.clone(), template.clone(), new_project.clone());
+        unit_of_work.run(
+            &tr!("step.create_project_folder"),
+            || create_project_directories(project_path.clone(), &template, &new_project),
+            move |_| delete_project_directories(created_path, &created_template, &created_project),
+        )?;
+
+        // csvファイルへ書き込み
+        let new_project_csv:ProjectCSV =unit_of_work.check(convert_project_to_csv(new_project.clone()))?;
+        let added_id = new_id.clone();
+        unit_of_work.run(
+            &tr!("step.add_project"),
+            || self.append_csv_row(&new_project_csv),
+            move |_| self.remove_project_row(&added_id),
+        )?;
+
+        add_status_history(unit_of_work, &self.status_histories, status_history)?;
+        audit_log_entries.push(unit_of_work.check(project_audit_log_entry(AuditAction::Add, None, Some(&new_project)))?);
+        Ok(new_id)
+    }
+
+    pub fn new_project_id(&self) -> Result<i32, AppError> {
+        let rows = self.read_csv_rows::<ProjectCSV>()?;
+        self.next_id(rows.iter().map(|row| row.id.parse::<i32>().unwrap_or(0)))
     }
 
-    pub fn fetch(&self) -> Result<Vec<Project>, Box<dyn Error>> {
+}
+
+impl Repository<Project> for ProjectFileRepository {
+    fn fetch(&self) -> Result<Vec<Project>, AppError> {
+        let _lock = self.lock_store()?;
         let file = File::open(self.get_file_path())?;
         let mut rdr = csv::Reader::from_reader(file);
         let mut projects = Vec::new();
@@ -308,175 +663,1215 @@ impl ProjectFileRepository {
 
         for result in rdr.deserialize() {
             let project_csv: ProjectCSV = result?;
-            let client = clients_repostiroy.get(&project_csv.client_id)?.expect(&format!("projectに紐づけられたClientがないよ, id={}", &project_csv.client_id));
-            let project = convert_csv_to_project(project_csv, client);
+            let client = clients_repostiroy.get(&project_csv.client_id)?.ok_or_else(|| AppError::not_found(Entity::Client, &project_csv.client_id))?;
+            let project = convert_csv_to_project(project_csv, client)?;
             projects.push(project);
         }
 
         Ok(projects)
     }
 
+    fn add(&self, new_project: Project) -> Result<String,AppError>{
+        let ids = self.add_all(vec![new_project])?;
+        Ok(ids.into_iter().next().unwrap_or_default())
+    }
+
+    /// まとめて追加する。ロックを持ったまま全部を書き込み、途中で失敗したらクライアント・フォルダも含めてすべて取り消す。
+    /// 監査ログは最後にまとめて追記するので、取り消した追加は記録に残らない
+    fn add_all(&self, new_projects: Vec<Project>) -> Result<Vec<String>, AppError> {
+        let _lock = self.lock_store()?;
+        let templates = self.folder_template_repository.fetch()?;
+        let mut new_clients = HashMap::new();
+        let mut audit_log_entries = Vec::new();
+        let mut unit_of_work = UnitOfWork::new();
+        let mut ids = Vec::new();
+        for new_project in new_projects {
+            ids.push(self.insert(&mut unit_of_work, &templates, new_project, &mut new_clients, &mut audit_log_entries)?);
+        }
+        append_audit_log(&mut unit_of_work, &self.audit_log, &audit_log_entries)?;
+        unit_of_work.commit();
+        Ok(ids)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Project>, AppError> {
+        let _lock = self.lock_store()?;
+        let projects = self.fetch()?;
+        for project in projects {
+            if project.id == id {
+                return Ok(Some(project));
+            }
+        }
+        Ok(None)
+    }
+
+    fn update(&self, updated_project:Project) ->Result<(),AppError>{
+        self.save_update(updated_project, true)?;
+        Ok(())
+    }
+
+    fn update_with_history(&self, updated_project:Project) ->Result<UpdatedItem<Project>,AppError>{
+        self.save_update(updated_project, true)
+    }
+
+    fn revert(&self, project:Project) ->Result<UpdatedItem<Project>,AppError>{
+        self.save_update(project, false)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let old_project = self.get(id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+        let old_rows = self.read_csv_rows::<ProjectCSV>()?;
+        let history_repository = StatusHistoryFileRepository::new(&self.status_history_file_path);
+        let old_histories = history_repository.fetch()?;
+        let mut unit_of_work = UnitOfWork::new();
+
+        // 一時ファイルに書いてから入れ替える。一時ファイルはcsvと同じフォルダに作るので、別のドライブでもrenameできる
+        unit_of_work.run(
+            &tr!("step.delete_project"),
+            || self.remove_project_row(id),
+            |_| self.write_csv_rows(&PROJECT_CSV_HEADERS, &old_rows),
+        )?;
+        unit_of_work.run(
+            &tr!("step.delete_status_history"),
+            || history_repository.delete_by_project(id),
+            |_| history_repository.restore(&old_histories),
+        )?;
+        let entry = unit_of_work.check(project_audit_log_entry(AuditAction::Delete, Some(&old_project), None))?;
+        append_audit_log(&mut unit_of_work, &self.audit_log, &[entry])?;
+        unit_of_work.commit();
+        Ok(())
+    }
 }
 
-impl Repository<Project> for ProjectFileRepository {
-    fn add(&self, mut new_project: Project) -> Result<String,String>{
 
-        // 新しいプロジェクトIDの生成
-        let new_id = self.new_project_id()?;
-        new_project.id = new_id.to_string();
+/// ステータス履歴を追記し、取り消す時は追記する前の行で書き戻す
+fn add_status_history<'a>(unit_of_work: &mut UnitOfWork<'a>, history_repository: &'a StatusHistoryFileRepository, history: ProjectStatusHistory) -> Result<(), AppError> {
+    let old_histories = unit_of_work.check(history_repository.fetch())?;
+    unit_of_work.run(
+        &tr!("step.add_status_history"),
+        || history_repository.add(history),
+        move |_| history_repository.restore(&old_histories),
+    )
+}
+
+/// 監査ログを追記し、取り消す時は追記する前の長さに戻す
+fn append_audit_log<'a>(unit_of_work: &mut UnitOfWork<'a>, audit_log: &'a AuditLogFileRepository, entries: &[AuditLogEntry]) -> Result<(), AppError> {
+    unit_of_work.run(
+        &tr!("step.append_audit_log"),
+        || audit_log.append(entries),
+        move |length| audit_log.truncate(*length),
+    )?;
+    Ok(())
+}
+
+/// データフォルダのcsvファイルのパスから、監査ログのファイルのパスを返す。
+/// csvファイルは<データフォルダ>/files/にあり、監査ログは<データフォルダ>/audit/に置く
+fn get_audit_log_file_path(csv_file_path: &Path) -> PathBuf {
+    let data_root = csv_file_path.parent().and_then(Path::parent).unwrap_or_else(|| Path::new(""));
+    data_root.join(AUDIT_DIRECTORY_NAME).join(AUDIT_LOG_FILE_NAME)
+}
 
-        let client_repository = ClientFileRepository::new(&self.client_file_path);
 
-        // 指定されたクライアントが新規の時はクライアントのcsvに追記する。
-        if let Ok(None) = client_repository.get(&new_project.client.id)  {
-            let new_client_id= client_repository.add(new_project.client.clone()).map_err(|e| e.to_string())?;
-            new_project.client.id=new_client_id;
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusHistoryCSV {
+    pub project_id: String,
+    pub from_status: Option<ProjectStatus>,
+    pub to_status: ProjectStatus,
+    pub changed_at: String,
+}
+
+pub struct StatusHistoryFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for StatusHistoryFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl StatusHistoryFileRepository {
+    pub fn new(file_path: &str) -> Self {
+        Self {
+            file_path: file_path.to_string(),
         }
+    }
 
-        // プロジェクトディレクトリの作成
-        let project_folder_suffix = get_folder_path_suffix(&new_project);
-        let project_path = get_project_directory_path(&project_folder_suffix);
-        
-        if project_path.exists(){
-            return Err("既に存在するプロジェクト名です。".to_string())
+    fn fetch(&self) -> Result<Vec<StatusHistoryCSV>, AppError> {
+        let file = File::open(&self.file_path)?;
+        let mut rdr = csv::Reader::from_reader(file);
+        let mut histories = Vec::new();
+
+        for result in rdr.deserialize() {
+            let history: StatusHistoryCSV = result?;
+            histories.push(history);
         }
-        
-        let _ = create_project_directories(project_path.clone())?;
 
+        Ok(histories)
+    }
 
-        // csvファイルへ書き込み
-        let file_path = Path::new(&self.project_file_path);
-        let file_exists = file_path.exists();
+    /// 読み込んでおいた全行で書き戻す。追加や削除を取り消す時に使う
+    pub fn restore(&self, histories: &[StatusHistoryCSV]) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        self.write_csv_rows(&STATUS_HISTORY_CSV_HEADERS, histories)
+    }
+}
 
-        let file = OpenOptions::new().write(true).append(true).open(file_path).map_err(|err| err.to_string())?;
+impl StatusHistoryRepository for StatusHistoryFileRepository {
+    fn add(&self, history: ProjectStatusHistory) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.file_path)?;
+        let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+        wtr.serialize(StatusHistoryCSV {
+            project_id: history.project_id,
+            from_status: history.from_status,
+            to_status: history.to_status,
+            changed_at: format_datetime(&history.changed_at),
+        })?;
+        wtr.flush()?;
+        Ok(())
+    }
 
-        let new_project_csv:ProjectCSV =convert_project_to_csv(new_project)?;
+    fn fetch_by_project(&self, project_id: &str) -> Result<Vec<ProjectStatusHistory>, AppError> {
+        let _lock = self.lock_store()?;
+        self.fetch()?
+            .into_iter()
+            .filter(|history| history.project_id == project_id)
+            .map(|history| {
+                Ok(ProjectStatusHistory {
+                    changed_at: parse_datetime("changed_at", &history.changed_at)?,
+                    project_id: history.project_id,
+                    from_status: history.from_status,
+                    to_status: history.to_status,
+                })
+            })
+            .collect()
+    }
 
-        let mut wtr = if file_exists {
-            // ファイルが存在する場合、ヘッダーを書き込まずにWriterを生成
-            csv::WriterBuilder::new().has_headers(false).from_writer(file)
-        } else {
-            // ファイルが新規作成された場合、ヘッダーを書き込むようにWriterを生成
-            csv::WriterBuilder::new().has_headers(true).from_writer(file)
-        };
-        if let Err(_) = wtr.serialize(new_project_csv){delete_project_directories(project_path.clone())?};
-        if let Err(_) = wtr.flush().map_err(|err| err.to_string()){delete_project_directories( project_path.clone())?};
-        Ok(new_id.to_string())
+    fn delete_by_project(&self, project_id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut histories = self.fetch()?;
+        histories.retain(|history| history.project_id != project_id);
+        self.write_csv_rows(&STATUS_HISTORY_CSV_HEADERS, &histories)
     }
+}
 
-    fn get(&self, id: &str) -> Result<Option<Project>, String> {
-        let projects = self.fetch().map_err(|e| e.to_string())?;
-        for project in projects {
-            if project.id == id {
-                return Ok(Some(project));
-            }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditLogCSV {
+    pub timestamp: String,
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub changes: String, // FieldChangeの配列をjsonで保存する
+    pub user: String,
+}
+
+/// 監査ログを保存するcsvファイル。行を追記するだけで書き換えない
+pub struct AuditLogFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for AuditLogFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl AuditLogFileRepository {
+    pub fn new(file_path: &str) -> Self {
+        Self {
+            file_path: file_path.to_string(),
         }
-        Ok(None)
     }
 
-    fn update(&self, mut updated_project:Project) ->Result<(),String>{
-        // 一時ファイルを作成します。
-        let mut temp_file = NamedTempFile::new().expect("tempfiles作成失敗");
-        let new_suffix = get_folder_path_suffix(&updated_project);
+    /// 監査ログを末尾に追記し、追記する前のファイルの長さを返す
+    pub fn append(&self, entries: &[AuditLogEntry]) -> Result<u64, AppError> {
+        let _lock = self.lock_store()?;
+        let length = std::fs::metadata(self.get_file_path())?.len();
+        for entry in entries {
+            self.append_csv_row(&AuditLogCSV {
+                timestamp: format_datetime(&entry.timestamp),
+                entity: entry.entity,
+                entity_id: entry.entity_id.clone(),
+                action: entry.action,
+                changes: serde_json::to_string(&entry.changes)?,
+                user: entry.user.clone(),
+            })?;
+        }
+        Ok(length)
+    }
 
-        let new_folder_path = get_project_directory_path(&new_suffix);
-        if let Ok(Some(old_project)) = self.get(&updated_project.id){
-            if get_folder_path_suffix(&old_project ) != new_suffix &&  new_folder_path.exists() {
-                return Err("既に存在するプロジェクト名です。".to_string())
-            }
+    /// appendが返した長さまでファイルを切り詰め、追記を取り消す
+    pub fn truncate(&self, length: u64) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let file = OpenOptions::new().write(true).open(self.get_file_path())?;
+        file.set_len(length)?;
+        Ok(())
+    }
+}
+
+impl AuditLogRepository for AuditLogFileRepository {
+    fn fetch(&self) -> Result<Vec<AuditLogEntry>, AppError> {
+        let _lock = self.lock_store()?;
+        self.read_csv_rows::<AuditLogCSV>()?
+            .into_iter()
+            .map(|row| {
+                Ok(AuditLogEntry {
+                    timestamp: parse_datetime("timestamp", &row.timestamp)?,
+                    changes: serde_json::from_str::<Vec<FieldChange>>(&row.changes)?,
+                    entity: row.entity,
+                    entity_id: row.entity_id,
+                    action: row.action,
+                    user: row.user,
+                })
+            })
+            .collect()
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskCSV {
+    pub id: String,
+    pub project_id: String,
+    pub parent_id: Option<String>,
+    pub title: String,
+    pub description: String,
+    pub due_date: Option<String>,
+    pub done: bool,
+    pub sort_order: i32,
+}
+
+pub struct TaskFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for TaskFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl TaskFileRepository {
+    pub fn new(data_root: &Path) -> Result<Self, AppError> {
+        let csv_file_directory_path = Self::get_csv_file_directory_path(data_root);
+        let task_file_path = csv_file_directory_path.join("tasks.csv");
+        Self::ensure_directory_exists(&csv_file_directory_path)?;
+        Self::ensure_csv_file_exists(&task_file_path, &TASK_CSV_HEADERS)?;
+        Ok(Self {
+            file_path: task_file_path.to_string_lossy().to_string(),
+        })
+    }
+
+    fn new_task_id(&self, rows: &[TaskCSV]) -> Result<i32, AppError> {
+        self.next_id(rows.iter().filter_map(|row| row.id.parse::<i32>().ok()))
+    }
+}
+
+impl Repository<Task> for TaskFileRepository {
+    fn add(&self, mut new_task: Task) -> Result<String, AppError> {
+        let _lock = self.lock_store()?;
+        let rows: Vec<TaskCSV> = self.read_csv_rows()?;
+        new_task.id = self.new_task_id(&rows)?.to_string();
+        self.append_csv_row(&convert_task_to_csv(&new_task))?;
+        Ok(new_task.id)
+    }
+
+    fn restore(&self, task: Task) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let rows: Vec<TaskCSV> = self.read_csv_rows()?;
+        if rows.iter().any(|row| row.id == task.id) {
+            return Err(AppError::conflict(Entity::Task, &task.id));
         }
-       
+        self.append_csv_row(&convert_task_to_csv(&task))
+    }
 
-        let client_repository = ClientFileRepository::new(&self.client_file_path);
-        // 指定されたクライアントが新規の時はクライアントのcsvに追記する。
-        if let Ok(None) = client_repository.get(&updated_project.client.id)  {
-            let new_client_id= client_repository.add(updated_project.client.clone()).map_err(|e| e.to_string())?;
-            updated_project.client.id=new_client_id;
+    fn get(&self, id: &str) -> Result<Option<Task>, AppError> {
+        let _lock = self.lock_store()?;
+        let tasks = self.fetch()?;
+        Ok(tasks.into_iter().find(|task| task.id == id))
+    }
+
+    fn update(&self, updated_task: Task) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut rows: Vec<TaskCSV> = self.read_csv_rows()?;
+        let row = rows
+            .iter_mut()
+            .find(|row| row.id == updated_task.id)
+            .ok_or_else(|| AppError::not_found(Entity::Task, &updated_task.id))?;
+        *row = convert_task_to_csv(&updated_task);
+        self.write_csv_rows(&TASK_CSV_HEADERS, &rows)
+    }
+
+    fn fetch(&self) -> Result<Vec<Task>, AppError> {
+        let _lock = self.lock_store()?;
+        let rows: Vec<TaskCSV> = self.read_csv_rows()?;
+        rows.into_iter().map(convert_csv_to_task).collect()
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut rows: Vec<TaskCSV> = self.read_csv_rows()?;
+        let count = rows.len();
+        rows.retain(|row| row.id != id);
+        if rows.len() == count {
+            return Err(AppError::not_found(Entity::Task, id));
         }
+        self.write_csv_rows(&TASK_CSV_HEADERS, &rows)
+    }
+}
 
-        let updated_project_csv = convert_project_to_csv(updated_project)?;
-        {
-            // 元のファイルを開きます。
-            let file = File::open(&self.project_file_path).map_err(|e| e.to_string())?;
-            let mut rdr = csv::Reader::from_reader(file);
-            let mut wtr = csv::Writer::from_writer(&mut temp_file);
-
-            // もしもsuffixが変更されていたらフォルダ構成を変更する
-
-            // CSVリーダーを使用して、プロジェクトを1つずつ処理します。
-            for result in rdr.deserialize() {
-                let mut project: ProjectCSV = result.map_err(|e| e.to_string())?;
-                
-                // 見つけたプロジェクトが更新すべきものであれば、更新します。
-                if project.id == updated_project_csv.id {
-                    let old_folder_path = PathBuf::from(project.folder_path);
-                    let old_suffix =  old_folder_path.file_name().unwrap().to_string_lossy().to_string();
-                    
-                    // もしもsuffixが更新されていたら、フォルダ構造を更新
-                    if old_suffix != new_suffix{
-                        rename_project_directory(old_folder_path, &new_suffix)?
-                    }
-                    project = updated_project_csv.clone();
-                
-                }
+fn convert_csv_to_task(csv: TaskCSV) -> Result<Task, AppError> {
+    Ok(Task {
+        due_date: parse_optional_date("due_date", csv.due_date.as_deref()).map_err(|e| AppError::parse(format!("{} (task id={})", e, csv.id)))?,
+        id: csv.id,
+        project_id: csv.project_id,
+        parent_id: csv.parent_id,
+        title: csv.title,
+        description: csv.description,
+        done: csv.done,
+        sort_order: csv.sort_order,
+    })
+}
 
-                // 一時ファイルに書き込みます。
-                wtr.serialize(&project).map_err(|e| e.to_string())?;
-            }
-            // 一時ファイルを元のファイル名にリネームする前に、元のファイルを閉じることを確認します。
-            drop(wtr);
-            drop(rdr);
+fn convert_task_to_csv(task: &Task) -> TaskCSV {
+    TaskCSV {
+        id: task.id.clone(),
+        project_id: task.project_id.clone(),
+        parent_id: task.parent_id.clone(),
+        title: task.title.clone(),
+        description: task.description.clone(),
+        due_date: task.due_date.as_ref().map(format_date),
+        done: task.done,
+        sort_order: task.sort_order,
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeEntryCSV {
+    pub id: String,
+    pub project_id: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub note: String,
+}
+
+pub struct TimeEntryFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for TimeEntryFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl TimeEntryFileRepository {
+    pub fn new(data_root: &Path) -> Result<Self, AppError> {
+        // projects.csvと同じフォルダに保存する
+        let csv_file_directory_path = Self::get_csv_file_directory_path(data_root);
+        let time_entry_file_path = csv_file_directory_path.join("time_entries.csv");
+        Self::ensure_directory_exists(&csv_file_directory_path)?;
+        Self::ensure_csv_file_exists(&time_entry_file_path, &TIME_ENTRY_CSV_HEADERS)?;
+        Ok(Self {
+            file_path: time_entry_file_path.to_string_lossy().to_string(),
+        })
+    }
+}
+
+/// 計測中のタイマーが2つにならないように、記録の確認はロックを持ったまま行う
+impl Repository<TimeEntry> for TimeEntryFileRepository {
+    fn add(&self, mut new_entry: TimeEntry) -> Result<String, AppError> {
+        let _lock = self.lock_store()?;
+        validate_time_entry(&self.fetch()?, &new_entry)?;
+        let rows: Vec<TimeEntryCSV> = self.read_csv_rows()?;
+        let new_id = self.next_id(rows.iter().filter_map(|row| row.id.parse::<i32>().ok()))?;
+        new_entry.id = new_id.to_string();
+        self.append_csv_row(&convert_time_entry_to_csv(&new_entry))?;
+        Ok(new_entry.id)
+    }
+
+    fn restore(&self, entry: TimeEntry) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let rows: Vec<TimeEntryCSV> = self.read_csv_rows()?;
+        if rows.iter().any(|row| row.id == entry.id) {
+            return Err(AppError::conflict(Entity::TimeEntry, &entry.id));
         }
-        // 元のファイルと一時ファイルを入れ替えます。
-        std::fs::rename(temp_file.path(), &self.project_file_path).map_err(|e| e.to_string())?;
-        
-        // todo: フォルダ名変更チェック
+        self.append_csv_row(&convert_time_entry_to_csv(&entry))
+    }
+
+    fn get(&self, id: &str) -> Result<Option<TimeEntry>, AppError> {
+        let _lock = self.lock_store()?;
+        let entries = self.fetch()?;
+        Ok(entries.into_iter().find(|entry| entry.id == id))
+    }
+
+    fn update(&self, updated_entry: TimeEntry) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        validate_time_entry(&self.fetch()?, &updated_entry)?;
+        let mut rows: Vec<TimeEntryCSV> = self.read_csv_rows()?;
+        let row = rows
+            .iter_mut()
+            .find(|row| row.id == updated_entry.id)
+            .ok_or_else(|| AppError::not_found(Entity::TimeEntry, &updated_entry.id))?;
+        *row = convert_time_entry_to_csv(&updated_entry);
+        self.write_csv_rows(&TIME_ENTRY_CSV_HEADERS, &rows)
+    }
+
+    fn fetch(&self) -> Result<Vec<TimeEntry>, AppError> {
+        let _lock = self.lock_store()?;
+        let rows: Vec<TimeEntryCSV> = self.read_csv_rows()?;
+        rows.into_iter().map(convert_csv_to_time_entry).collect()
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut rows: Vec<TimeEntryCSV> = self.read_csv_rows()?;
+        let count = rows.len();
+        rows.retain(|row| row.id != id);
+        if rows.len() == count {
+            return Err(AppError::not_found(Entity::TimeEntry, id));
+        }
+        self.write_csv_rows(&TIME_ENTRY_CSV_HEADERS, &rows)
+    }
+}
+
+fn convert_csv_to_time_entry(csv: TimeEntryCSV) -> Result<TimeEntry, AppError> {
+    let with_id = |e: AppError| AppError::parse(format!("{} (time entry id={})", e, csv.id));
+    Ok(TimeEntry {
+        started_at: parse_datetime("started_at", &csv.started_at).map_err(with_id)?,
+        ended_at: parse_optional_datetime("ended_at", csv.ended_at.as_deref()).map_err(with_id)?,
+        id: csv.id,
+        project_id: csv.project_id,
+        note: csv.note,
+    })
+}
+
+fn convert_time_entry_to_csv(entry: &TimeEntry) -> TimeEntryCSV {
+    TimeEntryCSV {
+        id: entry.id.clone(),
+        project_id: entry.project_id.clone(),
+        started_at: format_datetime(&entry.started_at),
+        ended_at: entry.ended_at.as_ref().map(format_datetime),
+        note: entry.note.clone(),
+    }
+}
+
+
+/// 請求書は明細を入れ子で持つので、csvではなくjsonで保存する
+pub struct InvoiceFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for InvoiceFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl InvoiceFileRepository {
+    pub fn new(data_root: &Path) -> Result<Self, AppError> {
+        let csv_file_directory_path = Self::get_csv_file_directory_path(data_root);
+        let invoice_file_path = csv_file_directory_path.join("invoices.json");
+        Self::ensure_directory_exists(&csv_file_directory_path)?;
+        if !invoice_file_path.exists() {
+            std::fs::write(&invoice_file_path, "[]")?;
+        }
+        Ok(Self {
+            file_path: invoice_file_path.to_string_lossy().to_string(),
+        })
+    }
+
+    fn write_invoices(&self, invoices: &[Invoice]) -> Result<(), AppError> {
+        let mut temp_file = self.new_temp_file()?;
+        serde_json::to_writer_pretty(&mut temp_file, invoices)?;
+        std::fs::rename(temp_file.path(), &self.file_path)?;
+        Ok(())
+    }
+}
+
+impl Repository<Invoice> for InvoiceFileRepository {
+    fn add(&self, mut new_invoice: Invoice) -> Result<String, AppError> {
+        let _lock = self.lock_store()?;
+        let mut invoices = self.fetch()?;
+        let invoice_numbers = invoices.iter().map(|invoice| invoice.invoice_number.clone()).collect::<Vec<String>>();
+        assign_invoice_number(&invoice_numbers, &mut new_invoice)?;
+        let new_id = self.next_id(invoices.iter().filter_map(|invoice| invoice.id.parse::<i32>().ok()))?;
+        new_invoice.id = new_id.to_string();
+        invoices.push(new_invoice);
+        self.write_invoices(&invoices)?;
+        Ok(new_id.to_string())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Invoice>, AppError> {
+        let _lock = self.lock_store()?;
+        let invoices = self.fetch()?;
+        Ok(invoices.into_iter().find(|invoice| invoice.id == id))
+    }
+
+    fn update(&self, updated_invoice: Invoice) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut invoices = self.fetch()?;
+        let invoice = invoices
+            .iter_mut()
+            .find(|invoice| invoice.id == updated_invoice.id)
+            .ok_or_else(|| AppError::not_found(Entity::Invoice, &updated_invoice.id))?;
+        *invoice = updated_invoice;
+        self.write_invoices(&invoices)
+    }
+
+    fn fetch(&self) -> Result<Vec<Invoice>, AppError> {
+        let _lock = self.lock_store()?;
+        let file = File::open(&self.file_path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(AppError::from)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut invoices = self.fetch()?;
+        let count = invoices.len();
+        invoices.retain(|invoice| invoice.id != id);
+        if invoices.len() == count {
+            return Err(AppError::not_found(Entity::Invoice, id));
+        }
+        self.write_invoices(&invoices)
+    }
+}
+
+pub struct FolderTemplateFileRepository {
+    file_path: String,
+}
+
+impl FileRepository for FolderTemplateFileRepository{
+    fn get_file_path(&self) -> &Path {
+        Path::new(&self.file_path)
+    }
+}
+
+impl FolderTemplateFileRepository {
+    pub fn new(data_root: &Path) -> Result<Self, AppError> {
+        let csv_file_directory_path = Self::get_csv_file_directory_path(data_root);
+        let template_file_path = csv_file_directory_path.join("folder_templates.json");
+        Self::ensure_directory_exists(&csv_file_directory_path)?;
+        if !template_file_path.exists() {
+            std::fs::write(&template_file_path, "[]")?;
+        }
+        Ok(Self {
+            file_path: template_file_path.to_string_lossy().to_string(),
+        })
+    }
+
+    fn write_templates(&self, templates: &[FolderTemplate]) -> Result<(), AppError> {
+        let mut temp_file = self.new_temp_file()?;
+        serde_json::to_writer_pretty(&mut temp_file, templates)?;
+        std::fs::rename(temp_file.path(), &self.file_path)?;
         Ok(())
     }
 }
 
+/// 標準のテンプレートは保存せず、ユーザーが作ったテンプレートだけを持つ
+impl Repository<FolderTemplate> for FolderTemplateFileRepository {
+    fn add(&self, mut new_template: FolderTemplate) -> Result<String, AppError> {
+        let _lock = self.lock_store()?;
+        let mut templates = self.fetch()?;
+        let new_id = self.next_id(templates.iter().filter_map(|template| template.id.parse::<i32>().ok()))?;
+        new_template.id = new_id.to_string();
+        templates.push(new_template);
+        self.write_templates(&templates)?;
+        Ok(new_id.to_string())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<FolderTemplate>, AppError> {
+        let _lock = self.lock_store()?;
+        let templates = self.fetch()?;
+        Ok(templates.into_iter().find(|template| template.id == id))
+    }
+
+    fn update(&self, updated_template: FolderTemplate) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut templates = self.fetch()?;
+        let template = templates
+            .iter_mut()
+            .find(|template| template.id == updated_template.id)
+            .ok_or_else(|| AppError::not_found(Entity::FolderTemplate, &updated_template.id))?;
+        *template = updated_template;
+        self.write_templates(&templates)
+    }
+
+    fn fetch(&self) -> Result<Vec<FolderTemplate>, AppError> {
+        let _lock = self.lock_store()?;
+        let file = File::open(&self.file_path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(AppError::from)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _lock = self.lock_store()?;
+        let mut templates = self.fetch()?;
+        let count = templates.len();
+        templates.retain(|template| template.id != id);
+        if templates.len() == count {
+            return Err(AppError::not_found(Entity::FolderTemplate, id));
+        }
+        self.write_templates(&templates)
+    }
+}
+
 
-fn convert_csv_to_project(csv: ProjectCSV, client: Client) -> Project {
-    let csv_folder_path = PathBuf::from(csv.folder_path);
-    let folder_path_suffix = csv_folder_path.file_name().unwrap().to_string_lossy().to_string();
-    Project {
+fn convert_csv_to_client(csv: ClientCSV, contacts: Vec<ClientContact>) -> Client {
+    Client {
+        id: csv.id,
+        name: csv.name,
+        contact_person: csv.contact_person,
+        email: csv.email,
+        phone: csv.phone,
+        postal_code: csv.postal_code,
+        address: csv.address,
+        billing: BillingDetails {
+            billing_name: csv.billing_name,
+            billing_address: csv.billing_address,
+            billing_email: csv.billing_email,
+        },
+        notes: csv.notes,
+        contacts,
+    }
+}
+
+fn convert_client_to_csv(client: &Client) -> ClientCSV {
+    ClientCSV {
+        id: client.id.clone(),
+        name: client.name.clone(),
+        contact_person: client.contact_person.clone(),
+        email: client.email.clone(),
+        phone: client.phone.clone(),
+        postal_code: client.postal_code.clone(),
+        address: client.address.clone(),
+        billing_name: client.billing.billing_name.clone(),
+        billing_address: client.billing.billing_address.clone(),
+        billing_email: client.billing.billing_email.clone(),
+        notes: client.notes.clone(),
+    }
+}
+
+fn convert_csv_to_contact(csv: &ClientContactCSV) -> ClientContact {
+    ClientContact {
+        name: csv.name.clone(),
+        role: csv.role.clone(),
+        email: csv.email.clone(),
+        phone: csv.phone.clone(),
+    }
+}
+
+fn convert_contact_to_csv(client_id: &str, contact: &ClientContact) -> ClientContactCSV {
+    ClientContactCSV {
+        client_id: client_id.to_string(),
+        name: contact.name.clone(),
+        role: contact.role.clone(),
+        email: contact.email.clone(),
+        phone: contact.phone.clone(),
+    }
+}
+
+fn convert_csv_to_project(csv: ProjectCSV, client: Client) -> Result<Project, AppError> {
+    // どの行が壊れているか分かるように、エラーにはidを含める
+    let with_id = |e: AppError| AppError::parse(format!("{} (id={})", e, csv.id));
+    Ok(Project {
+        order_date: parse_date("order_date", &csv.order_date).map_err(with_id)?,
+        due_date: parse_date("due_date", &csv.due_date).map_err(with_id)?,
+        completion_date: parse_optional_date("completion_date", csv.completion_date.as_deref()).map_err(with_id)?,
+        deleted_at: parse_optional_datetime("deleted_at", csv.deleted_at.as_deref()).map_err(with_id)?,
+        archived_at: parse_optional_datetime("archived_at", csv.archived_at.as_deref()).map_err(with_id)?,
         id: csv.id,
         title: csv.title,
         description: csv.description,
-        order_date: csv.order_date, // 実際のアプリケーションでは日付の変換が必要かもしれません
-        due_date: csv.due_date, // 実際のアプリケーションでは日付の変換が必要かもしれません
-        completion_date: csv.completion_date, // 実際のアプリケーションでは日付の変換が必要かもしれません
         client: client, // 仮定により、この関数の呼び出し時にはすでに取得しています
         status: csv.status,
-        folder_path_suffix: Some(folder_path_suffix),
-    }
+        folder_path_suffix: Some(csv.folder_path_suffix),
+        revision: csv.revision,
+        template_id: csv.template_id.filter(|id| !id.is_empty()),
+    })
 }
 
 
-fn convert_project_to_csv(project:Project) -> Result<ProjectCSV,String>{
+fn convert_project_to_csv(project:Project) -> Result<ProjectCSV,AppError>{
     if project.id =="".to_string() && project.folder_path_suffix == None{
-        return  Err("不正なプロジェクトをcsvに渡そうとしています".to_string())
-    }
-    let folder_path = match project.folder_path_suffix  {
-        Some(suffix) =>
-        get_project_directory_path(&suffix).to_string_lossy().to_string(),  
-        None =>
-                get_project_directory_path(&project.id).to_string_lossy().to_string(),
-            
-    };
+        return  Err(AppError::validation(tr!("project.invalid_for_csv")))
+    }
+    let folder_path_suffix = get_folder_path_suffix(&project);
     Ok(ProjectCSV{
         id: project.id.clone(),
         title: project.title.clone(),
         description: project.description.clone(),
-        order_date: project.order_date.clone(), // 実際のアプリケーションでは日付の変換が必要かもしれません
-        due_date: project.due_date.clone(), // 実際のアプリケーションでは日付の変換が必要かもしれません
-        completion_date: project.completion_date.clone(), // 実際のアプリケーションでは日付の変換が必要かもしれません
+        order_date: format_date(&project.order_date),
+        due_date: format_date(&project.due_date),
+        completion_date: project.completion_date.as_ref().map(format_date),
         client_id: project.client.id.clone(), // 仮定により、この関数の呼び出し時にはすでに取得しています
         status: project.status.clone(),
-        folder_path: folder_path
+        folder_path_suffix: folder_path_suffix,
+        deleted_at: project.deleted_at.as_ref().map(format_datetime),
+        archived_at: project.archived_at.as_ref().map(format_datetime),
+        revision: project.revision,
+        template_id: project.template_id.clone(),
     
 })
    
 }
 
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn create_repositories(root: &TestDataRoot) -> (ProjectFileRepository, ClientFileRepository) {
+        let project_repo = ProjectFileRepository::new(root.path()).unwrap();
+        let client_repo = ClientFileRepository::new(&project_repo.get_self_client_file_path());
+        (project_repo, client_repo)
+    }
+
+    // 書き込みの途中で作った一時ファイルが残っていないか
+    fn leftover_temp_files(root: &TestDataRoot) -> Vec<String> {
+        std::fs::read_dir(root.csv_directory())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn delete_project_removes_row_and_status_history() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let other_id = project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap();
+
+        project_repo.delete(&id).unwrap();
+
+        let ids = project_repo.fetch().unwrap().into_iter().map(|project| project.id).collect::<Vec<String>>();
+        assert_eq!(ids, vec![other_id.clone()]);
+        let history_repo = StatusHistoryFileRepository::new(&project_repo.get_self_status_history_file_path());
+        assert!(history_repo.fetch_by_project(&id).unwrap().is_empty());
+        assert_eq!(history_repo.fetch_by_project(&other_id).unwrap().len(), 1);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn delete_missing_project_returns_not_found() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+
+        let result = project_repo.delete("99");
+
+        assert_eq!(result, Err(AppError::not_found(Entity::Project, "99")));
+        assert_eq!(project_repo.fetch().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn delete_client_removes_client_and_contacts() {
+        let root = TestDataRoot::new();
+        let (_, client_repo) = create_repositories(&root);
+        let mut client = sample_client("", "取引先");
+        client.contacts.push(ClientContact { name: "経理".to_string(), ..ClientContact::default() });
+        let id = client_repo.add(client).unwrap();
+        let other_id = client_repo.add(sample_client("", "別の取引先")).unwrap();
+
+        client_repo.delete(&id).unwrap();
+
+        let clients = client_repo.fetch().unwrap();
+        assert_eq!(clients.iter().map(|client| client.id.clone()).collect::<Vec<String>>(), vec![other_id]);
+        assert!(client_repo.contacts.read_csv_rows::<ClientContactCSV>().unwrap().is_empty());
+        assert_eq!(client_repo.delete(&id), Err(AppError::not_found(Entity::Client, &id)));
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn status_change_records_history_and_completion_date() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.status = ProjectStatus::InProgress;
+        project_repo.update(project).unwrap();
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.status = ProjectStatus::Completed;
+        project_repo.update(project).unwrap();
+
+        let history_repo = StatusHistoryFileRepository::new(&project_repo.get_self_status_history_file_path());
+        let statuses = history_repo
+            .fetch_by_project(&id)
+            .unwrap()
+            .into_iter()
+            .map(|history| (history.from_status, history.to_status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (None, ProjectStatus::NotStarted),
+                (Some(ProjectStatus::NotStarted), ProjectStatus::InProgress),
+                (Some(ProjectStatus::InProgress), ProjectStatus::Completed),
+            ]
+        );
+        assert!(project_repo.get(&id).unwrap().unwrap().completion_date.is_some());
+    }
+
+    #[test]
+    fn invalid_status_change_is_rejected_without_writing() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.status = ProjectStatus::Invoiced;
+        assert!(matches!(project_repo.update(project), Err(AppError::Validation(_))));
+
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.status, ProjectStatus::NotStarted);
+        assert_eq!(saved.revision, 0);
+        let history_repo = StatusHistoryFileRepository::new(&project_repo.get_self_status_history_file_path());
+        assert_eq!(history_repo.fetch_by_project(&id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn status_change_is_rolled_back_when_a_later_write_fails() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let history_path = project_repo.get_self_status_history_file_path();
+        let history_before = std::fs::read_to_string(&history_path).unwrap();
+        // 監査ログをフォルダにして、ステータス履歴を書いた後の書き込みを失敗させる
+        let audit_log_path = project_repo.get_self_audit_log_file_path();
+        std::fs::remove_file(&audit_log_path).unwrap();
+        std::fs::create_dir(&audit_log_path).unwrap();
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.status = ProjectStatus::InProgress;
+        assert!(project_repo.update(project).is_err());
+
+        assert_eq!(std::fs::read_to_string(&history_path).unwrap(), history_before);
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.status, ProjectStatus::NotStarted);
+        assert_eq!(saved.revision, 0);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn client_and_contacts_are_rolled_back_together() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let mut client = sample_client("", "取引先");
+        client.contacts.push(ClientContact { name: "経理".to_string(), ..ClientContact::default() });
+        let id = client_repo.add(client).unwrap();
+        let clients_path = project_repo.get_self_client_file_path();
+        let contacts_path = Path::new(&clients_path).with_file_name(CLIENT_CONTACT_FILE_NAME);
+        let clients_before = std::fs::read_to_string(&clients_path).unwrap();
+        let contacts_before = std::fs::read_to_string(&contacts_path).unwrap();
+        // 監査ログをフォルダにして、両方のcsvを書いた後の書き込みを失敗させる
+        let audit_log_path = project_repo.get_self_audit_log_file_path();
+        std::fs::remove_file(&audit_log_path).unwrap();
+        std::fs::create_dir(&audit_log_path).unwrap();
+
+        let mut updated = client_repo.get(&id).unwrap().unwrap();
+        updated.name = "新しい名前".to_string();
+        updated.contacts = vec![ClientContact { name: "営業".to_string(), ..ClientContact::default() }];
+        assert!(client_repo.update(updated).is_err());
+        assert!(client_repo.add(sample_client("", "別の取引先")).is_err());
+        assert!(client_repo.delete(&id).is_err());
+
+        assert_eq!(std::fs::read_to_string(&clients_path).unwrap(), clients_before);
+        assert_eq!(std::fs::read_to_string(&contacts_path).unwrap(), contacts_before);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn stale_revision_is_rejected() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut first = project_repo.get(&id).unwrap().unwrap();
+        let mut second = first.clone();
+
+        first.title = "先に保存".to_string();
+        project_repo.update(first).unwrap();
+        second.title = "後から保存".to_string();
+        assert_eq!(project_repo.update(second), Err(AppError::conflict(Entity::Project, &id)));
+
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.title, "先に保存");
+        assert_eq!(saved.revision, 1);
+    }
+
+    // プロジェクト・クライアント・ステータス履歴のcsvと、プロジェクトのフォルダの中身
+    fn snapshot(root: &TestDataRoot) -> Vec<(String, String)> {
+        let mut files = Vec::new();
+        for name in ["projects.csv", "clients.csv", CLIENT_CONTACT_FILE_NAME, "project_status_history.csv"] {
+            files.push((name.to_string(), std::fs::read_to_string(root.csv_directory().join(name)).unwrap()));
+        }
+        let project_directory = root.path().join("project");
+        if project_directory.is_dir() {
+            for entry in std::fs::read_dir(project_directory).unwrap() {
+                files.push((entry.unwrap().file_name().to_string_lossy().to_string(), String::new()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    // ファイルかフォルダをフォルダに置き換えて、そこへの書き込みを失敗させる
+    fn replace_with_directory(path: &Path) {
+        if path.is_dir() {
+            std::fs::remove_dir_all(path).unwrap();
+        } else {
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::create_dir(path).unwrap();
+    }
+
+    #[test]
+    fn add_failing_at_folder_creation_removes_new_client() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let before = snapshot(&root);
+        // プロジェクトのフォルダを置く場所をファイルにして、2番目の書き込み(フォルダの作成)を失敗させる
+        let project_directory = root.path().join("project");
+        let _ = std::fs::remove_dir_all(&project_directory);
+        std::fs::write(&project_directory, "").unwrap();
+
+        assert!(project_repo.add(sample_project("案件A", sample_client("", "新しい取引先"))).is_err());
+
+        std::fs::remove_file(&project_directory).unwrap();
+        assert_eq!(snapshot(&root), before);
+    }
+
+    #[test]
+    fn add_failing_at_status_history_removes_row_folder_and_client() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let before = snapshot(&root);
+        // 4番目の書き込み(ステータス履歴)を失敗させる
+        let history_path = PathBuf::from(project_repo.get_self_status_history_file_path());
+        let history = std::fs::read_to_string(&history_path).unwrap();
+        replace_with_directory(&history_path);
+
+        assert!(project_repo.add(sample_project("案件A", sample_client("", "新しい取引先"))).is_err());
+
+        std::fs::remove_dir(&history_path).unwrap();
+        std::fs::write(&history_path, history).unwrap();
+        assert_eq!(snapshot(&root), before);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn add_failing_at_last_step_removes_everything() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let before = snapshot(&root);
+        // 最後の書き込み(監査ログ)を失敗させる
+        replace_with_directory(Path::new(&project_repo.get_self_audit_log_file_path()));
+
+        assert!(project_repo.add(sample_project("案件A", sample_client("", "新しい取引先"))).is_err());
+
+        assert_eq!(snapshot(&root), before);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn update_failing_after_rename_restores_folder_name() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let before = snapshot(&root);
+        replace_with_directory(Path::new(&project_repo.get_self_audit_log_file_path()));
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.folder_path_suffix = Some("新しいフォルダ".to_string());
+        project.status = ProjectStatus::InProgress;
+        project.client = sample_client("", "新しい取引先");
+        assert!(project_repo.update(project).is_err());
+
+        assert_eq!(snapshot(&root), before);
+        assert!(root.path().join("project").join(id).is_dir());
+    }
+
+    #[test]
+    fn audit_log_is_kept_outside_csv_directory() {
+        let root = TestDataRoot::new();
+        std::fs::create_dir_all(root.csv_directory()).unwrap();
+        // filesフォルダに置いていた頃の監査ログ
+        let legacy = "timestamp,entity,entity_id,action,changes,user\n2024-01-02T03:04:05+09:00,Project,1,Add,[],user\n";
+        std::fs::write(root.csv_directory().join(AUDIT_LOG_FILE_NAME), legacy).unwrap();
+
+        let (project_repo, _) = create_repositories(&root);
+
+        let audit_log_path = PathBuf::from(project_repo.get_self_audit_log_file_path());
+        assert_eq!(audit_log_path, root.path().join(AUDIT_DIRECTORY_NAME).join(AUDIT_LOG_FILE_NAME));
+        assert_eq!(std::fs::read_to_string(&audit_log_path).unwrap(), legacy);
+        assert!(!root.csv_directory().join(AUDIT_LOG_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn audit_log_append_is_undone_on_rollback() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let audit_log = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        let before = audit_log.fetch().unwrap();
+        let entry = folder_rename_audit_log_entry("1", "古い", "新しい");
+
+        let mut unit_of_work = UnitOfWork::new();
+        append_audit_log(&mut unit_of_work, &audit_log, &[entry]).unwrap();
+        assert_eq!(audit_log.fetch().unwrap().len(), before.len() + 1);
+        let _ = unit_of_work.check::<()>(Err(AppError::validation("後の書き込みの失敗")));
+
+        assert_eq!(audit_log.fetch().unwrap().len(), before.len());
+    }
+
+    #[test]
+    fn delete_failing_at_audit_log_keeps_row_and_history() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let before = snapshot(&root);
+        replace_with_directory(Path::new(&project_repo.get_self_audit_log_file_path()));
+
+        assert!(project_repo.delete(&id).is_err());
+
+        assert_eq!(snapshot(&root), before);
+        assert!(leftover_temp_files(&root).is_empty());
+    }
+
+    #[test]
+    fn client_changes_are_recorded_in_audit_log() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let audit_log = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        let id = client_repo.add(sample_client("", "取引先")).unwrap();
+        let mut client = client_repo.get(&id).unwrap().unwrap();
+        client.name = "新しい名前".to_string();
+        client_repo.update(client.clone()).unwrap();
+        // 変更のない更新は記録しない
+        client_repo.update(client).unwrap();
+        client_repo.delete(&id).unwrap();
+
+        let entries = audit_log.fetch().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.action).collect::<Vec<AuditAction>>(), vec![AuditAction::Add, AuditAction::Update, AuditAction::Delete]);
+        assert_eq!(entries[1].changes, vec![FieldChange { field: "name".to_string(), before: Some("取引先".to_string()), after: Some("新しい名前".to_string()) }]);
+    }
+
+    #[test]
+    fn add_all_is_undone_with_clients_folders_and_audit_log() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let audit_log = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        let before = snapshot(&root);
+        // 2件目のフォルダ名になるidのフォルダを先に作り、途中で失敗させる
+        std::fs::create_dir_all(get_project_directory_path("2")).unwrap();
+        let projects = vec![sample_project("案件A", sample_client("", "新規")), sample_project("案件B", sample_client("", "新規"))];
+
+        assert!(project_repo.add_all(projects).is_err());
+
+        assert!(project_repo.fetch().unwrap().is_empty());
+        assert!(client_repo.fetch().unwrap().is_empty());
+        assert!(audit_log.fetch().unwrap().is_empty());
+        std::fs::remove_dir(get_project_directory_path("2")).unwrap();
+        assert_eq!(snapshot(&root), before);
+    }
+
+    #[test]
+    fn add_all_creates_same_new_client_once() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let projects = vec![sample_project("案件A", sample_client("", "新規")), sample_project("案件B", sample_client("", "新規"))];
+
+        let ids = project_repo.add_all(projects).unwrap();
+
+        assert_eq!(ids, vec!["1", "2"]);
+        let clients = client_repo.fetch().unwrap();
+        assert_eq!(clients.len(), 1);
+        assert!(ids.iter().all(|id| project_repo.get(id).unwrap().unwrap().client.id == clients[0].id));
+    }
+
+    #[test]
+    fn deleted_ids_are_not_reused() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let last_id = project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap();
+        project_repo.delete(&last_id).unwrap();
+        let client_id = client_repo.add(sample_client("", "消す取引先")).unwrap();
+        client_repo.delete(&client_id).unwrap();
+
+        assert_eq!(project_repo.add(sample_project("案件C", sample_client("1", "取引先"))).unwrap(), "3");
+        assert_eq!(client_repo.add(sample_client("", "新しい取引先")).unwrap(), (client_id.parse::<i32>().unwrap() + 1).to_string());
+
+        let task_repo = TaskFileRepository::new(root.path()).unwrap();
+        let task = Task { id: String::new(), project_id: "1".to_string(), parent_id: None, title: "作業".to_string(), description: String::new(), due_date: None, done: false, sort_order: 0 };
+        let task_id = task_repo.add(task.clone()).unwrap();
+        task_repo.delete(&task_id).unwrap();
+        assert_ne!(task_repo.add(task).unwrap(), task_id);
+    }
+
+    #[test]
+    fn failed_update_leaves_no_audit_entries_for_new_client() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let audit_log = AuditLogFileRepository::new(&project_repo.get_self_audit_log_file_path());
+        let entries_before = audit_log.fetch().unwrap().len();
+        let clients_before = client_repo.fetch().unwrap().len();
+        // ステータス履歴の書き込みを失敗させる
+        replace_with_directory(&root.csv_directory().join("project_status_history.csv"));
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.client = sample_client("", "新規");
+        project.status = ProjectStatus::InProgress;
+        assert!(project_repo.update(project).is_err());
+
+        assert_eq!(client_repo.fetch().unwrap().len(), clients_before);
+        assert_eq!(audit_log.fetch().unwrap().len(), entries_before);
+        assert_eq!(project_repo.get(&id).unwrap().unwrap().status, ProjectStatus::NotStarted);
+    }
+
+    fn blank_invoice() -> Invoice {
+        Invoice {
+            id: String::new(),
+            invoice_number: String::new(),
+            project_id: "1".to_string(),
+            client_id: "1".to_string(),
+            issue_date: crate::repositories::test_support::date("2024-04-30"),
+            payment_due_date: crate::repositories::test_support::date("2024-05-31"),
+            line_items: Vec::new(),
+            tax_rate: 0.1,
+            file_path: None,
+        }
+    }
+
+    #[test]
+    fn invoice_numbers_are_assigned_under_lock() {
+        let root = TestDataRoot::new();
+        InvoiceFileRepository::new(root.path()).unwrap();
+        // 別々のウィンドウから同時に保存しても、同じ番号にならない
+        let handles = (0..4)
+            .map(|_| {
+                let data_root = root.path().to_path_buf();
+                std::thread::spawn(move || InvoiceFileRepository::new(&data_root).unwrap().add(blank_invoice()).unwrap())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let invoice_repo = InvoiceFileRepository::new(root.path()).unwrap();
+        let mut numbers = invoice_repo.fetch().unwrap().into_iter().map(|invoice| invoice.invoice_number).collect::<Vec<String>>();
+        numbers.sort();
+        assert_eq!(numbers, vec!["INV-2024-0001", "INV-2024-0002", "INV-2024-0003", "INV-2024-0004"]);
+
+        let duplicate = Invoice { invoice_number: "INV-2024-0001".to_string(), ..blank_invoice() };
+        assert!(matches!(invoice_repo.add(duplicate), Err(AppError::Validation(_))));
+    }
+}
@@ -1,2 +1,11 @@
 pub mod file_repository;
-pub mod repository_trait;
\ No newline at end of file
+pub mod repository_trait;
+pub mod sqlite_repository;
+pub mod settings_repository;
+pub mod csv_migration;
+pub mod file_lock;
+pub mod unit_of_work;
+pub mod search_index_repository;
+pub mod backup_repository;
+#[cfg(test)]
+pub mod test_support;
\ No newline at end of file
@@ -1,8 +1,58 @@
 use std::result::Result;
 
+use crate::domain::{error::AppError, models::audit_log::AuditLogEntry, models::project_status_history::ProjectStatusHistory};
+use crate::tr;
+
+use super::unit_of_work::UnitOfWork;
+
 pub trait Repository<T> {
-    fn add(&self,  item: T) -> Result<String, String>;
-    fn get(&self, id: &str) -> Result<Option<T>, String>;
-    fn update(&self, item:T) -> Result<(),String>;
+    fn add(&self,  item: T) -> Result<String, AppError>;
+    fn get(&self, id: &str) -> Result<Option<T>, AppError>;
+    fn update(&self, item:T) -> Result<(),AppError>;
+    fn fetch(&self) -> Result<Vec<T>, AppError>;
+    fn delete(&self, id: &str) -> Result<(), AppError>;
+    /// まとめて追加し、追加したidを返す。途中で失敗したら、それまでに追加したものも削除する
+    fn add_all(&self, items: Vec<T>) -> Result<Vec<String>, AppError> {
+        let mut unit_of_work = UnitOfWork::new();
+        let mut ids = Vec::new();
+        for item in items {
+            ids.push(unit_of_work.run(&tr!("step.add"), || self.add(item), |id| self.delete(id))?);
+        }
+        unit_of_work.commit();
+        Ok(ids)
+    }
+    /// 更新し、更新する前と後の状態を返す。元に戻す・やり直しの記録に使う。
+    /// 前後の状態は、他の書き込みが割り込まないように更新と同じロックの中で読み込む
+    fn update_with_history(&self, _item: T) -> Result<UpdatedItem<T>, AppError> {
+        Err(AppError::validation(tr!("store.history_not_supported")))
+    }
+    /// 元に戻す・やり直しで、記録しておいた状態に書き戻す。業務上の遷移の制限があるものは確かめずに書き戻す
+    fn revert(&self, _item: T) -> Result<UpdatedItem<T>, AppError> {
+        Err(AppError::validation(tr!("store.history_not_supported")))
+    }
+    /// 削除したものを同じidで書き戻す。まとめて削除する途中で失敗した時の取り消しに使う
+    fn restore(&self, _item: T) -> Result<(), AppError> {
+        Err(AppError::validation(tr!("store.restore_not_supported")))
+    }
     // 他の必要なメソッドをここに追加
 }
+
+/// 更新する前と後の状態
+#[derive(Debug, Clone)]
+pub struct UpdatedItem<T> {
+    pub before: T,
+    pub after: T,
+    pub created_client_id: Option<String>, // 更新と一緒に追加したクライアント
+}
+
+/// プロジェクトのステータス変更履歴の保存先
+pub trait StatusHistoryRepository {
+    fn add(&self, item: ProjectStatusHistory) -> Result<(), AppError>;
+    fn fetch_by_project(&self, project_id: &str) -> Result<Vec<ProjectStatusHistory>, AppError>;
+    fn delete_by_project(&self, project_id: &str) -> Result<(), AppError>;
+}
+
+/// 監査ログの保存先。書き込みはProjectとClientのrepositoryが変更と一緒に行う
+pub trait AuditLogRepository {
+    fn fetch(&self) -> Result<Vec<AuditLogEntry>, AppError>;
+}
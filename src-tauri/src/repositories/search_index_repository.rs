@@ -0,0 +1,45 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use tempfile::NamedTempFile;
+
+use crate::domain::businesslogic::search_index::SearchIndex;
+use crate::domain::error::AppError;
+use crate::tr;
+
+/// ファイル検索の索引をデータフォルダのcacheに保存する。
+/// 消えても作り直せるので、csvのバックアップやデータフォルダの移行の対象にはしない。
+pub struct SearchIndexFileRepository {
+    file_path: PathBuf,
+}
+
+impl SearchIndexFileRepository {
+    pub fn new(data_root: &Path) -> Self {
+        Self {
+            file_path: data_root.join("cache").join("search_index.json"),
+        }
+    }
+
+    /// 索引を読み込む。まだ作っていなければ空の索引を返す
+    pub fn load(&self) -> Result<SearchIndex, AppError> {
+        if !self.file_path.exists() {
+            return Ok(SearchIndex::default());
+        }
+        let file = File::open(&self.file_path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, index: &SearchIndex) -> Result<(), AppError> {
+        let directory = self.file_path.parent().ok_or_else(|| AppError::io(tr!("file.parent_directory_missing", self.file_path.display())))?;
+        fs::create_dir_all(directory)?;
+        let temp_file = NamedTempFile::new_in(directory)?;
+        // dropに任せると書き込みのエラーに気づけないので、置き換える前にflushする
+        let mut writer = BufWriter::new(temp_file.as_file());
+        serde_json::to_writer(&mut writer, index)?;
+        writer.flush()?;
+        drop(writer);
+        temp_file.persist(&self.file_path)?;
+        Ok(())
+    }
+}
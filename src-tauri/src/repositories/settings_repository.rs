@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tempfile::NamedTempFile;
+
+use crate::domain::businesslogic::data_directory::{fallback_app_directory, APP_DIRECTORY_NAME};
+use crate::domain::error::AppError;
+use crate::domain::models::settings::Settings;
+use crate::tr;
+
+/// 設定をjsonファイルに保存する。
+/// データフォルダを変更しても設定を見失わないように、データフォルダの外(OSの設定フォルダ)に置く。
+pub struct SettingsFileRepository {
+    file_path: PathBuf,
+}
+
+impl SettingsFileRepository {
+    pub fn new() -> Self {
+        let config_directory = dirs::config_dir()
+            .map(|dir| dir.join(APP_DIRECTORY_NAME))
+            .unwrap_or_else(fallback_app_directory);
+        Self {
+            file_path: config_directory.join("settings.json"),
+        }
+    }
+
+    /// 設定を読み込む。ファイルがなければ初期設定を返す
+    pub fn load(&self) -> Result<Settings, AppError> {
+        if !self.file_path.exists() {
+            return Ok(Settings::default());
+        }
+        let contents = fs::read_to_string(&self.file_path)?;
+        serde_json::from_str(&contents).map_err(|e| AppError::parse(tr!("settings.read_failed", self.file_path.display(), e)))
+    }
+
+    pub fn save(&self, settings: &Settings) -> Result<(), AppError> {
+        let directory = self.file_path.parent().ok_or_else(|| AppError::io(tr!("settings.directory_missing")))?;
+        fs::create_dir_all(directory)?;
+        // 書き込み途中で落ちても設定が壊れないように、同じフォルダの一時ファイルから入れ替える
+        let mut temp_file = NamedTempFile::new_in(directory)?;
+        serde_json::to_writer_pretty(&mut temp_file, settings)?;
+        temp_file.persist(&self.file_path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1694 @@
+use crate::domain::error::{AppError, Entity};
+use crate::domain::models::client::{BillingDetails, Client, ClientContact};
+use crate::domain::models::project::{Project, ProjectStatus};
+use crate::domain::models::project_status_history::ProjectStatusHistory;
+use crate::domain::models::task::Task;
+use crate::domain::models::time_entry::TimeEntry;
+use crate::domain::models::invoice::{Invoice, InvoiceLineItem, LineItemKind};
+use crate::domain::models::folder_template::{FolderTemplate, TemplateFile};
+use crate::domain::models::audit_log::{AuditAction, AuditEntity, AuditLogEntry, FieldChange};
+
+use crate::domain::businesslogic::project_date::{format_date, format_datetime, parse_date, parse_datetime, parse_optional_date, parse_optional_datetime, validate_project_dates};
+use crate::domain::businesslogic::project_status::{apply_status_change, initial_status_history, normalize_completion_date, record_status_change};
+use crate::domain::businesslogic::project_file::{create_project_directories, delete_project_directories, get_folder_path_suffix, get_project_directory_path, is_project_folder_missing, rename_project_directory};
+use crate::domain::businesslogic::folder_template::resolve_folder_template;
+use crate::domain::businesslogic::invoice::assign_invoice_number;
+use crate::domain::businesslogic::time_tracking::validate_time_entry;
+use crate::domain::businesslogic::audit_log::{folder_rename_audit_log_entry, new_audit_log_entry, project_audit_log_entry};
+use crate::tr;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Type, ValueRef};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row, Transaction, TransactionBehavior};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::repository_trait::{AuditLogRepository, Repository, StatusHistoryRepository, UpdatedItem};
+use super::unit_of_work::UnitOfWork;
+
+/// テーブル定義。起動のたびに実行されるので、すべて IF NOT EXISTS で書く。
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS clients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    contact_person TEXT NOT NULL,
+    email TEXT NOT NULL DEFAULT '',
+    phone TEXT NOT NULL DEFAULT '',
+    postal_code TEXT NOT NULL DEFAULT '',
+    address TEXT NOT NULL DEFAULT '',
+    billing_name TEXT NOT NULL DEFAULT '',
+    billing_address TEXT NOT NULL DEFAULT '',
+    billing_email TEXT NOT NULL DEFAULT '',
+    notes TEXT NOT NULL DEFAULT ''
+);
+CREATE INDEX IF NOT EXISTS idx_clients_name ON clients(name);
+
+CREATE TABLE IF NOT EXISTS client_contacts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    client_id INTEGER NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    role TEXT NOT NULL,
+    email TEXT NOT NULL,
+    phone TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_client_contacts_client_id ON client_contacts(client_id, position);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    order_date TEXT NOT NULL,
+    due_date TEXT NOT NULL,
+    completion_date TEXT,
+    client_id INTEGER NOT NULL REFERENCES clients(id) ON DELETE RESTRICT,
+    status TEXT NOT NULL,
+    folder_path_suffix TEXT UNIQUE,
+    deleted_at TEXT,
+    revision INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_projects_client_id ON projects(client_id);
+CREATE INDEX IF NOT EXISTS idx_projects_status ON projects(status);
+CREATE INDEX IF NOT EXISTS idx_projects_due_date ON projects(due_date);
+
+CREATE TABLE IF NOT EXISTS project_status_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    changed_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_project_status_history_project_id ON project_status_history(project_id);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES tasks(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    due_date TEXT,
+    done INTEGER NOT NULL DEFAULT 0,
+    sort_order INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_tasks_project_id ON tasks(project_id, sort_order);
+CREATE INDEX IF NOT EXISTS idx_tasks_parent_id ON tasks(parent_id);
+
+CREATE TABLE IF NOT EXISTS time_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    note TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_time_entries_project_id ON time_entries(project_id);
+-- 計測中(ended_atがNULL)のタイマーは1つだけ
+CREATE UNIQUE INDEX IF NOT EXISTS idx_time_entries_running ON time_entries((ended_at IS NULL)) WHERE ended_at IS NULL;
+
+-- 請求書は帳簿としてプロジェクトを削除しても残すので、projectsへの外部キーは張らない
+CREATE TABLE IF NOT EXISTS invoices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    invoice_number TEXT NOT NULL UNIQUE,
+    project_id INTEGER NOT NULL,
+    client_id INTEGER NOT NULL,
+    issue_date TEXT NOT NULL,
+    payment_due_date TEXT NOT NULL,
+    tax_rate REAL NOT NULL,
+    file_path TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_invoices_project_id ON invoices(project_id);
+
+CREATE TABLE IF NOT EXISTS invoice_line_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    invoice_id INTEGER NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit_price INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_invoice_line_items_invoice_id ON invoice_line_items(invoice_id, position);
+
+-- フォルダとファイルの一覧はJSONの配列で保存する
+CREATE TABLE IF NOT EXISTS folder_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    directories TEXT NOT NULL,
+    files TEXT NOT NULL
+);
+";
+
+/// 監査ログのテーブル定義。バックアップから復元した時に巻き戻らないように、別のデータベースファイルをauditとしてATTACHして置く。
+/// 監査ログは削除したデータの記録も残すので、外部キーは張らない。changesはJSONの配列
+const AUDIT_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit.audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    entity TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    user TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit.idx_audit_log_entity ON audit_log(entity, entity_id);
+";
+
+/// 既存のデータベースに後から追加した列。(テーブル名, 列名, 列定義)
+const ADDED_COLUMNS: [(&str, &str, &str); 12] = [
+    ("projects", "deleted_at", "TEXT"),
+    ("projects", "revision", "INTEGER NOT NULL DEFAULT 0"),
+    ("projects", "template_id", "TEXT"),
+    ("projects", "archived_at", "TEXT"),
+    ("clients", "email", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "phone", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "postal_code", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "address", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "billing_name", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "billing_address", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "billing_email", "TEXT NOT NULL DEFAULT ''"),
+    ("clients", "notes", "TEXT NOT NULL DEFAULT ''"),
+];
+
+const SELECT_PROJECT: &str = "
+SELECT p.id, p.title, p.description, p.order_date, p.due_date, p.completion_date, p.status, p.folder_path_suffix, p.deleted_at, p.revision, p.template_id, p.archived_at,
+       c.id, c.name, c.contact_person, c.email, c.phone, c.postal_code, c.address, c.billing_name, c.billing_address, c.billing_email, c.notes
+FROM projects p
+INNER JOIN clients c ON c.id = p.client_id
+";
+
+// sqliteのデータベースファイルのパス
+pub fn get_database_file_path(data_root: &Path) -> PathBuf {
+    data_root.join("files/task_manager.db")
+}
+
+/// 使用中のデータベースを、書き込みの途中でも壊れないようにVACUUM INTOでdestinationにコピーする
+pub fn copy_database(source: &Path, destination: &Path) -> Result<(), AppError> {
+    let conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.execute("VACUUM INTO ?1", params![destination.to_string_lossy()])?;
+    Ok(())
+}
+
+// 監査ログのデータベースファイルのパス。filesフォルダの外に置く
+pub fn get_audit_database_file_path(data_root: &Path) -> PathBuf {
+    data_root.join("audit/audit_log.db")
+}
+
+/// データフォルダのデータベースを開き、テーブルがなければ作成する。
+/// 返したコネクションはProject/Clientのリポジトリで共有する。
+pub fn open_database(data_root: &Path) -> Result<Arc<Mutex<Connection>>, AppError> {
+    let path = get_database_file_path(data_root);
+    let audit_path = get_audit_database_file_path(data_root);
+    for parent in [path.parent(), audit_path.parent()].into_iter().flatten() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(&path)?;
+    // 外部キー制約はコネクションごとに有効化が必要
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    conn.execute_batch(SCHEMA)?;
+    for (table, column, definition) in ADDED_COLUMNS {
+        ensure_column(&conn, table, column, definition)?;
+    }
+    conn.execute("ATTACH DATABASE ?1 AS audit", params![audit_path.to_string_lossy()])?;
+    conn.execute_batch(AUDIT_SCHEMA)?;
+    move_legacy_audit_log(&conn)?;
+    Ok(Arc::new(Mutex::new(conn)))
+}
+
+/// 以前はメインのデータベースに置いていた監査ログを、監査ログのデータベースへ移す
+fn move_legacy_audit_log(conn: &Connection) -> Result<(), AppError> {
+    let exists = conn
+        .query_row("SELECT 1 FROM main.sqlite_master WHERE type = 'table' AND name = 'audit_log'", [], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        conn.execute_batch(
+            "BEGIN;
+            INSERT INTO audit.audit_log (timestamp, entity, entity_id, action, changes, user)
+                SELECT timestamp, entity, entity_id, action, changes, user FROM main.audit_log ORDER BY id;
+            DROP TABLE main.audit_log;
+            COMMIT;",
+        )?;
+    }
+    Ok(())
+}
+
+/// テーブルに列がなければ追加する。古いバージョンで作成したデータベース用。
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), AppError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    if !columns.iter().any(|name| name == column) {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, definition))?;
+    }
+    Ok(())
+}
+
+impl ToSql for ProjectStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let status = match self {
+            ProjectStatus::NotStarted => "NotStarted",
+            ProjectStatus::InProgress => "InProgress",
+            ProjectStatus::OnHold => "OnHold",
+            ProjectStatus::Delivered => "Delivered",
+            ProjectStatus::Completed => "Completed",
+            ProjectStatus::Invoiced => "Invoiced",
+            ProjectStatus::Cancelled => "Cancelled",
+        };
+        Ok(status.into())
+    }
+}
+
+impl FromSql for ProjectStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "NotStarted" => Ok(ProjectStatus::NotStarted),
+            "InProgress" => Ok(ProjectStatus::InProgress),
+            "OnHold" => Ok(ProjectStatus::OnHold),
+            "Delivered" => Ok(ProjectStatus::Delivered),
+            "Completed" => Ok(ProjectStatus::Completed),
+            "Invoiced" => Ok(ProjectStatus::Invoiced),
+            "Cancelled" => Ok(ProjectStatus::Cancelled),
+            other => Err(FromSqlError::Other(format!("unknown project status: {}", other).into())),
+        }
+    }
+}
+
+impl ToSql for AuditEntity {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let entity = match self {
+            AuditEntity::Project => "Project",
+            AuditEntity::Client => "Client",
+        };
+        Ok(entity.into())
+    }
+}
+
+impl FromSql for AuditEntity {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "Project" => Ok(AuditEntity::Project),
+            "Client" => Ok(AuditEntity::Client),
+            other => Err(FromSqlError::Other(format!("unknown audit entity: {}", other).into())),
+        }
+    }
+}
+
+impl ToSql for AuditAction {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let action = match self {
+            AuditAction::Add => "Add",
+            AuditAction::Update => "Update",
+            AuditAction::Delete => "Delete",
+            AuditAction::RenameFolder => "RenameFolder",
+        };
+        Ok(action.into())
+    }
+}
+
+impl FromSql for AuditAction {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "Add" => Ok(AuditAction::Add),
+            "Update" => Ok(AuditAction::Update),
+            "Delete" => Ok(AuditAction::Delete),
+            "RenameFolder" => Ok(AuditAction::RenameFolder),
+            other => Err(FromSqlError::Other(format!("unknown audit action: {}", other).into())),
+        }
+    }
+}
+
+/// 文字列のidをsqliteのINTEGERに変換する。新規作成時の空文字などはNoneになる。
+fn parse_id(id: &str) -> Option<i64> {
+    id.parse::<i64>().ok()
+}
+
+fn row_to_client(row: &Row, offset: usize) -> rusqlite::Result<Client> {
+    let id: i64 = row.get(offset)?;
+    Ok(Client {
+        id: id.to_string(),
+        name: row.get(offset + 1)?,
+        contact_person: row.get(offset + 2)?,
+        email: row.get(offset + 3)?,
+        phone: row.get(offset + 4)?,
+        postal_code: row.get(offset + 5)?,
+        address: row.get(offset + 6)?,
+        billing: BillingDetails {
+            billing_name: row.get(offset + 7)?,
+            billing_address: row.get(offset + 8)?,
+            billing_email: row.get(offset + 9)?,
+        },
+        notes: row.get(offset + 10)?,
+        contacts: Vec::new(),
+    })
+}
+
+const SELECT_CLIENT: &str = "SELECT id, name, contact_person, email, phone, postal_code, address, billing_name, billing_address, billing_email, notes FROM clients";
+
+/// クライアントの担当者を読み込む
+fn fetch_contacts(conn: &Connection, client_id: &str) -> Result<Vec<ClientContact>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT name, role, email, phone FROM client_contacts WHERE client_id = ?1 ORDER BY position")?;
+    let contacts = stmt
+        .query_map(params![parse_id(client_id)], |row| {
+            Ok(ClientContact {
+                name: row.get(0)?,
+                role: row.get(1)?,
+                email: row.get(2)?,
+                phone: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<ClientContact>>>()?;
+    Ok(contacts)
+}
+
+/// すべてのクライアントの担当者を1回のクエリで読み込み、クライアントのidごとにまとめる
+fn fetch_all_contacts(conn: &Connection) -> Result<HashMap<String, Vec<ClientContact>>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT client_id, name, role, email, phone FROM client_contacts ORDER BY client_id, position")?;
+    let rows = stmt
+        .query_map([], |row| {
+            let client_id: i64 = row.get(0)?;
+            Ok((
+                client_id.to_string(),
+                ClientContact {
+                    name: row.get(1)?,
+                    role: row.get(2)?,
+                    email: row.get(3)?,
+                    phone: row.get(4)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<(String, ClientContact)>>>()?;
+    let mut contacts: HashMap<String, Vec<ClientContact>> = HashMap::new();
+    for (client_id, contact) in rows {
+        contacts.entry(client_id).or_default().push(contact);
+    }
+    Ok(contacts)
+}
+
+/// クライアントの担当者を入れ替える
+fn replace_contacts(tx: &Transaction, client_id: &str, contacts: &[ClientContact]) -> Result<(), AppError> {
+    let client_id = parse_id(client_id);
+    tx.execute("DELETE FROM client_contacts WHERE client_id = ?1", params![client_id])?;
+    for (position, contact) in contacts.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO client_contacts (client_id, position, name, role, email, phone) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![client_id, position as i64, contact.name, contact.role, contact.email, contact.phone],
+        )?;
+    }
+    Ok(())
+}
+
+/// 日付の変換エラーをrusqliteのエラーにする
+fn conversion_error(index: usize) -> impl Fn(AppError) -> rusqlite::Error {
+    move |e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))
+}
+
+fn row_to_project(row: &Row) -> rusqlite::Result<Project> {
+    let id: i64 = row.get(0)?;
+    // 日付はcsvと同じ書式の文字列で保存しているので、同じ関数で変換する
+    let order_date: String = row.get(3)?;
+    let due_date: String = row.get(4)?;
+    let completion_date: Option<String> = row.get(5)?;
+    let deleted_at: Option<String> = row.get(8)?;
+    let archived_at: Option<String> = row.get(11)?;
+    Ok(Project {
+        id: id.to_string(),
+        title: row.get(1)?,
+        description: row.get(2)?,
+        order_date: parse_date("order_date", &order_date).map_err(conversion_error(3))?,
+        due_date: parse_date("due_date", &due_date).map_err(conversion_error(4))?,
+        completion_date: parse_optional_date("completion_date", completion_date.as_deref()).map_err(conversion_error(5))?,
+        status: row.get(6)?,
+        folder_path_suffix: row.get(7)?,
+        deleted_at: parse_optional_datetime("deleted_at", deleted_at.as_deref()).map_err(conversion_error(8))?,
+        revision: row.get(9)?,
+        template_id: row.get(10)?,
+        archived_at: parse_optional_datetime("archived_at", archived_at.as_deref()).map_err(conversion_error(11))?,
+        client: row_to_client(row, 12)?, // 担当者の一覧は別に読み込む
+    })
+}
+
+fn client_exists(tx: &Transaction, id: &str) -> Result<bool, AppError> {
+    let Some(id) = parse_id(id) else {
+        return Ok(false);
+    };
+    tx.query_row("SELECT 1 FROM clients WHERE id = ?1", params![id], |_| Ok(()))
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(AppError::from)
+}
+
+/// 監査ログを書き込む。変更と同じトランザクションで書き込み、変更がロールバックされたら監査ログも残らない
+fn insert_audit_log(tx: &Transaction, entry: &AuditLogEntry) -> Result<(), AppError> {
+    tx.execute(
+        "INSERT INTO audit.audit_log (timestamp, entity, entity_id, action, changes, user) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            format_datetime(&entry.timestamp),
+            entry.entity,
+            entry.entity_id,
+            entry.action,
+            serde_json::to_string(&entry.changes)?,
+            entry.user,
+        ],
+    )?;
+    Ok(())
+}
+
+/// クライアントを担当者と一緒に読み込む
+fn query_client(conn: &Connection, id: i64) -> Result<Option<Client>, AppError> {
+    let client = conn
+        .query_row(&format!("{} WHERE id = ?1", SELECT_CLIENT), params![id], |row| row_to_client(row, 0))
+        .optional()?;
+    match client {
+        Some(mut client) => {
+            client.contacts = fetch_contacts(conn, &client.id)?;
+            Ok(Some(client))
+        }
+        None => Ok(None),
+    }
+}
+
+/// プロジェクトをクライアントの担当者と一緒に読み込む
+fn query_project(conn: &Connection, id: i64) -> Result<Option<Project>, AppError> {
+    let project = conn
+        .query_row(&format!("{} WHERE p.id = ?1", SELECT_PROJECT), params![id], row_to_project)
+        .optional()?;
+    match project {
+        Some(mut project) => {
+            project.client.contacts = fetch_contacts(conn, &project.client.id)?;
+            Ok(Some(project))
+        }
+        None => Ok(None),
+    }
+}
+
+fn insert_status_history(tx: &Transaction, history: &ProjectStatusHistory) -> Result<(), AppError> {
+    tx.execute(
+        "INSERT INTO project_status_history (project_id, from_status, to_status, changed_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            parse_id(&history.project_id),
+            history.from_status,
+            history.to_status,
+            format_datetime(&history.changed_at),
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_client(tx: &Transaction, client: &Client) -> Result<String, AppError> {
+    tx.execute(
+        "INSERT INTO clients (name, contact_person, email, phone, postal_code, address, billing_name, billing_address, billing_email, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            client.name,
+            client.contact_person,
+            client.email,
+            client.phone,
+            client.postal_code,
+            client.address,
+            client.billing.billing_name,
+            client.billing.billing_address,
+            client.billing.billing_email,
+            client.notes,
+        ],
+    )?;
+    let new_id = tx.last_insert_rowid().to_string();
+    replace_contacts(tx, &new_id, &client.contacts)?;
+    // プロジェクトと一緒に追加したクライアントも記録する
+    let added_client = Client { id: new_id.clone(), ..client.clone() };
+    insert_audit_log(tx, &new_audit_log_entry(AuditEntity::Client, &new_id, AuditAction::Add, None, Some(&added_client))?)?;
+    Ok(new_id)
+}
+
+pub struct SqliteClientRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteClientRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl Repository<Client> for SqliteClientRepository {
+    fn add(&self, new_client: Client) -> Result<String, AppError> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let new_id = insert_client(&tx, &new_client)?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Client>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        query_client(&conn, id)
+    }
+
+    fn update(&self, updated_client: Client) -> Result<(), AppError> {
+        self.update_with_history(updated_client)?;
+        Ok(())
+    }
+
+    fn update_with_history(&self, updated_client: Client) -> Result<UpdatedItem<Client>, AppError> {
+        let id = parse_id(&updated_client.id).ok_or_else(|| AppError::validation(tr!("client.invalid_id", updated_client.id)))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let old_client = query_client(&tx, id)?.ok_or_else(|| AppError::not_found(Entity::Client, &updated_client.id))?;
+        let updated = tx
+            .execute(
+                "UPDATE clients SET name = ?1, contact_person = ?2, email = ?3, phone = ?4, postal_code = ?5, address = ?6,
+                 billing_name = ?7, billing_address = ?8, billing_email = ?9, notes = ?10 WHERE id = ?11",
+                params![
+                    updated_client.name,
+                    updated_client.contact_person,
+                    updated_client.email,
+                    updated_client.phone,
+                    updated_client.postal_code,
+                    updated_client.address,
+                    updated_client.billing.billing_name,
+                    updated_client.billing.billing_address,
+                    updated_client.billing.billing_email,
+                    updated_client.notes,
+                    id,
+                ],
+            )?;
+        if updated == 0 {
+            return Err(AppError::not_found(Entity::Client, &updated_client.id));
+        }
+        replace_contacts(&tx, &updated_client.id, &updated_client.contacts)?;
+        let entry = new_audit_log_entry(AuditEntity::Client, &updated_client.id, AuditAction::Update, Some(&old_client), Some(&updated_client))?;
+        if !entry.changes.is_empty() {
+            insert_audit_log(&tx, &entry)?;
+        }
+        let after = query_client(&tx, id)?.ok_or_else(|| AppError::not_found(Entity::Client, &updated_client.id))?;
+        tx.commit()?;
+        Ok(UpdatedItem { before: old_client, after, created_client_id: None })
+    }
+
+    fn revert(&self, client: Client) -> Result<UpdatedItem<Client>, AppError> {
+        self.update_with_history(client)
+    }
+
+    fn fetch(&self) -> Result<Vec<Client>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY id", SELECT_CLIENT))?;
+        let mut clients = stmt
+            .query_map([], |row| row_to_client(row, 0))?
+            .collect::<rusqlite::Result<Vec<Client>>>()?;
+        let mut contacts = fetch_all_contacts(&conn)?;
+        for client in clients.iter_mut() {
+            client.contacts = contacts.remove(&client.id).unwrap_or_default();
+        }
+        Ok(clients)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::Client, id))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let old_client = query_client(&tx, parsed_id)?.ok_or_else(|| AppError::not_found(Entity::Client, id))?;
+        // プロジェクトから参照されている場合は外部キー制約で失敗する
+        tx.execute("DELETE FROM clients WHERE id = ?1", params![parsed_id])?;
+        insert_audit_log(&tx, &new_audit_log_entry(AuditEntity::Client, id, AuditAction::Delete, Some(&old_client), None)?)?;
+        tx.commit().map_err(AppError::from)
+    }
+}
+
+pub struct SqliteProjectRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteProjectRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// プロジェクトを更新し、更新する前と後のプロジェクトを返す。check_status_transitionがfalseの時は、ステータスの遷移表を確かめない
+    fn save_update(&self, mut updated_project: Project, check_status_transition: bool) -> Result<UpdatedItem<Project>, AppError> {
+        let id = parse_id(&updated_project.id).ok_or_else(|| AppError::validation(tr!("project.invalid_id", updated_project.id)))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+
+        let old_project = query_project(&tx, id)?.ok_or_else(|| AppError::not_found(Entity::Project, &updated_project.id))?;
+        // 読み込んだ後に他の画面で更新されていたら保存しない
+        if old_project.revision != updated_project.revision {
+            return Err(AppError::conflict(Entity::Project, &updated_project.id));
+        }
+        let old_suffix = get_folder_path_suffix(&old_project);
+
+        // ステータスの遷移を確認し、完了日をステータスに合わせる
+        let status_history = if check_status_transition {
+            apply_status_change(&old_project, &mut updated_project)?
+        } else {
+            record_status_change(&old_project, &mut updated_project)
+        };
+        validate_project_dates(&updated_project)?;
+        let new_suffix = get_folder_path_suffix(&updated_project);
+
+        let new_folder_path = get_project_directory_path(&new_suffix);
+        // 元のフォルダがアプリの外で移動・削除されている時は、フォルダを動かさずにsuffixだけを付け替える
+        let rename_folder = old_suffix != new_suffix && !is_project_folder_missing(&old_project);
+        if rename_folder && new_folder_path.exists() {
+            return Err(AppError::duplicate_folder(&new_suffix));
+        }
+
+        // 指定されたクライアントが新規の時はクライアントを追加する。
+        let mut created_client_id = None;
+        if !client_exists(&tx, &updated_project.client.id)? {
+            updated_project.client.id = insert_client(&tx, &updated_project.client)?;
+            created_client_id = Some(updated_project.client.id.clone());
+        }
+
+        tx.execute(
+            "UPDATE projects SET title = ?1, description = ?2, order_date = ?3, due_date = ?4, completion_date = ?5,
+             client_id = ?6, status = ?7, folder_path_suffix = ?8, deleted_at = ?9, archived_at = ?10, revision = revision + 1 WHERE id = ?11",
+            params![
+                updated_project.title,
+                updated_project.description,
+                format_date(&updated_project.order_date),
+                format_date(&updated_project.due_date),
+                updated_project.completion_date.as_ref().map(format_date),
+                parse_id(&updated_project.client.id),
+                updated_project.status,
+                new_suffix,
+                updated_project.deleted_at.as_ref().map(format_datetime),
+                updated_project.archived_at.as_ref().map(format_datetime),
+                id,
+            ],
+        )?;
+        if let Some(status_history) = status_history {
+            insert_status_history(&tx, &status_history)?;
+        }
+
+        // 変更した項目を監査ログに残す。updateではテンプレートを変えないので、比べる前に元の値にしておく
+        updated_project.template_id = old_project.template_id.clone();
+        updated_project.folder_path_suffix = Some(new_suffix.clone());
+        let entry = project_audit_log_entry(AuditAction::Update, Some(&old_project), Some(&updated_project))?;
+        if !entry.changes.is_empty() {
+            insert_audit_log(&tx, &entry)?;
+        }
+        if rename_folder {
+            insert_audit_log(&tx, &folder_rename_audit_log_entry(&updated_project.id, &old_suffix, &new_suffix))?;
+        }
+        let after = query_project(&tx, id)?.ok_or_else(|| AppError::not_found(Entity::Project, &updated_project.id))?;
+
+        // もしもsuffixが更新されていたら、フォルダ構造を更新
+        // リネームに失敗した場合はtxがdropされてロールバックされ、コミットに失敗した場合はフォルダ名を元に戻す
+        let mut unit_of_work = UnitOfWork::new();
+        if rename_folder {
+            unit_of_work.run(
+                &tr!("step.rename_project_folder"),
+                || rename_project_directory(get_project_directory_path(&old_suffix), &new_suffix),
+                |_| rename_project_directory(new_folder_path.clone(), &old_suffix),
+            )?;
+        }
+
+        unit_of_work.check(tx.commit().map_err(AppError::from))?;
+        unit_of_work.commit();
+        Ok(UpdatedItem { before: old_project, after, created_client_id })
+    }
+}
+
+/// プロジェクトを追加する。フォルダの作成はunit_of_workに積むので、トランザクションのコミットに失敗したら呼び出し側で取り消す。
+/// new_clientsは追加した新しいクライアントの名前とidで、同じ名前の新しいクライアントは1つだけ追加する
+fn insert_project(tx: &Transaction, unit_of_work: &mut UnitOfWork, templates: &[FolderTemplate], mut new_project: Project, new_clients: &mut HashMap<String, String>) -> Result<String, AppError> {
+    // 完了日をステータスに合わせてから保存する
+    normalize_completion_date(&mut new_project);
+    validate_project_dates(&new_project)?;
+    let template = resolve_folder_template(templates.to_vec(), new_project.template_id.as_deref())?;
+
+    // 指定されたクライアントが新規の時はクライアントを追加する。
+    if !client_exists(tx, &new_project.client.id)? {
+        new_project.client.id = match new_clients.get(&new_project.client.name) {
+            Some(client_id) => client_id.clone(),
+            None => {
+                let client_id = insert_client(tx, &new_project.client)?;
+                new_clients.insert(new_project.client.name.clone(), client_id.clone());
+                client_id
+            }
+        };
+    }
+
+    tx.execute(
+        "INSERT INTO projects (title, description, order_date, due_date, completion_date, client_id, status, folder_path_suffix, deleted_at, template_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            new_project.title,
+            new_project.description,
+            format_date(&new_project.order_date),
+            format_date(&new_project.due_date),
+            new_project.completion_date.as_ref().map(format_date),
+            parse_id(&new_project.client.id),
+            new_project.status,
+            new_project.folder_path_suffix,
+            new_project.deleted_at.as_ref().map(format_datetime),
+            new_project.template_id,
+        ],
+    )?;
+    new_project.id = tx.last_insert_rowid().to_string();
+
+    // suffixが指定されていなければidをフォルダ名にする
+    let project_folder_suffix = get_folder_path_suffix(&new_project);
+    tx.execute(
+        "UPDATE projects SET folder_path_suffix = ?1 WHERE id = ?2",
+        params![project_folder_suffix, parse_id(&new_project.id)],
+    )?;
+
+    insert_status_history(tx, &initial_status_history(&new_project))?;
+    new_project.folder_path_suffix = Some(project_folder_suffix.clone());
+    insert_audit_log(tx, &project_audit_log_entry(AuditAction::Add, None, Some(&new_project))?)?;
+
+    // プロジェクトディレクトリの作成
+    let project_path = get_project_directory_path(&project_folder_suffix);
+    if project_path.exists() {
+        return Err(AppError::duplicate_folder(&project_folder_suffix));
+    }
+    let (created_path, created_template, created_project) = (project_path.clone(), template.clone(), new_project.clone());
+    unit_of_work.run(
+        &tr!("step.create_project_folder"),
+        || create_project_directories(project_path.clone(), &template, &new_project),
+        move |_| delete_project_directories(created_path, &created_template, &created_project),
+    )?;
+    Ok(new_project.id)
+}
+
+impl Repository<Project> for SqliteProjectRepository {
+    fn add(&self, new_project: Project) -> Result<String, AppError> {
+        let ids = self.add_all(vec![new_project])?;
+        Ok(ids.into_iter().next().unwrap_or_default())
+    }
+
+    /// まとめて1つのトランザクションで追加する。途中で失敗したら、作ったフォルダも削除する
+    fn add_all(&self, new_projects: Vec<Project>) -> Result<Vec<String>, AppError> {
+        let mut conn = self.conn.lock()?;
+        let templates = fetch_folder_templates(&conn)?;
+        let tx = conn.transaction()?;
+        let mut new_clients = HashMap::new();
+        let mut unit_of_work = UnitOfWork::new();
+        let mut ids = Vec::new();
+        for new_project in new_projects {
+            let id = insert_project(&tx, &mut unit_of_work, &templates, new_project, &mut new_clients);
+            ids.push(unit_of_work.check(id)?);
+        }
+
+        // コミットに失敗したらフォルダも削除する
+        unit_of_work.check(tx.commit().map_err(AppError::from))?;
+        unit_of_work.commit();
+        Ok(ids)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Project>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        query_project(&conn, id)
+    }
+
+    fn update(&self, updated_project: Project) -> Result<(), AppError> {
+        self.save_update(updated_project, true)?;
+        Ok(())
+    }
+
+    fn update_with_history(&self, updated_project: Project) -> Result<UpdatedItem<Project>, AppError> {
+        self.save_update(updated_project, true)
+    }
+
+    fn revert(&self, project: Project) -> Result<UpdatedItem<Project>, AppError> {
+        self.save_update(project, false)
+    }
+
+    fn fetch(&self) -> Result<Vec<Project>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY p.id", SELECT_PROJECT))?;
+        let mut projects = stmt
+            .query_map([], row_to_project)?
+            .collect::<rusqlite::Result<Vec<Project>>>()?;
+        // 同じクライアントのプロジェクトが複数あるので、取り出さずに複製する
+        let contacts = fetch_all_contacts(&conn)?;
+        for project in projects.iter_mut() {
+            project.client.contacts = contacts.get(&project.client.id).cloned().unwrap_or_default();
+        }
+        Ok(projects)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let old_project = query_project(&tx, parsed_id)?.ok_or_else(|| AppError::not_found(Entity::Project, id))?;
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![parsed_id])?;
+        insert_audit_log(&tx, &project_audit_log_entry(AuditAction::Delete, Some(&old_project), None)?)?;
+        tx.commit().map_err(AppError::from)
+    }
+}
+
+pub struct SqliteStatusHistoryRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStatusHistoryRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl StatusHistoryRepository for SqliteStatusHistoryRepository {
+    fn add(&self, history: ProjectStatusHistory) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        insert_status_history(&tx, &history)?;
+        tx.commit().map_err(AppError::from)
+    }
+
+    fn fetch_by_project(&self, project_id: &str) -> Result<Vec<ProjectStatusHistory>, AppError> {
+        let Some(parsed_id) = parse_id(project_id) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare("SELECT project_id, from_status, to_status, changed_at FROM project_status_history WHERE project_id = ?1 ORDER BY id")?;
+        let histories = stmt
+            .query_map(params![parsed_id], |row| {
+                let project_id: i64 = row.get(0)?;
+                let changed_at: String = row.get(3)?;
+                Ok(ProjectStatusHistory {
+                    project_id: project_id.to_string(),
+                    from_status: row.get(1)?,
+                    to_status: row.get(2)?,
+                    changed_at: parse_datetime("changed_at", &changed_at).map_err(conversion_error(3))?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<ProjectStatusHistory>>>()?;
+        Ok(histories)
+    }
+
+    fn delete_by_project(&self, project_id: &str) -> Result<(), AppError> {
+        let Some(parsed_id) = parse_id(project_id) else {
+            return Ok(());
+        };
+        let conn = self.conn.lock()?;
+        conn.execute("DELETE FROM project_status_history WHERE project_id = ?1", params![parsed_id])?;
+        Ok(())
+    }
+}
+
+const SELECT_TASK: &str = "SELECT id, project_id, parent_id, title, description, due_date, done, sort_order FROM tasks";
+
+fn row_to_task(row: &Row) -> rusqlite::Result<Task> {
+    let id: i64 = row.get(0)?;
+    let project_id: i64 = row.get(1)?;
+    let parent_id: Option<i64> = row.get(2)?;
+    let due_date: Option<String> = row.get(5)?;
+    Ok(Task {
+        id: id.to_string(),
+        project_id: project_id.to_string(),
+        parent_id: parent_id.map(|parent_id| parent_id.to_string()),
+        title: row.get(3)?,
+        description: row.get(4)?,
+        due_date: parse_optional_date("due_date", due_date.as_deref()).map_err(conversion_error(5))?,
+        done: row.get(6)?,
+        sort_order: row.get(7)?,
+    })
+}
+
+pub struct SqliteTaskRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteTaskRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+// idがNoneなら新しいidを振る
+fn insert_task(conn: &Connection, task: &Task, id: Option<i64>) -> Result<String, AppError> {
+    conn.execute(
+        "INSERT INTO tasks (id, project_id, parent_id, title, description, due_date, done, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            parse_id(&task.project_id),
+            task.parent_id.as_deref().and_then(parse_id),
+            task.title,
+            task.description,
+            task.due_date.as_ref().map(format_date),
+            task.done,
+            task.sort_order,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid().to_string())
+}
+
+impl Repository<Task> for SqliteTaskRepository {
+    fn add(&self, new_task: Task) -> Result<String, AppError> {
+        let conn = self.conn.lock()?;
+        insert_task(&conn, &new_task, None)
+    }
+
+    fn restore(&self, task: Task) -> Result<(), AppError> {
+        let id = parse_id(&task.id).ok_or_else(|| AppError::not_found(Entity::Task, &task.id))?;
+        let conn = self.conn.lock()?;
+        insert_task(&conn, &task, Some(id))?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Task>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        conn.query_row(&format!("{} WHERE id = ?1", SELECT_TASK), params![id], row_to_task)
+            .optional()
+            .map_err(AppError::from)
+    }
+
+    fn update(&self, updated_task: Task) -> Result<(), AppError> {
+        let id = parse_id(&updated_task.id).ok_or_else(|| AppError::not_found(Entity::Task, &updated_task.id))?;
+        let conn = self.conn.lock()?;
+        let updated = conn
+            .execute(
+                "UPDATE tasks SET project_id = ?1, parent_id = ?2, title = ?3, description = ?4, due_date = ?5,
+                 done = ?6, sort_order = ?7 WHERE id = ?8",
+                params![
+                    parse_id(&updated_task.project_id),
+                    updated_task.parent_id.as_deref().and_then(parse_id),
+                    updated_task.title,
+                    updated_task.description,
+                    updated_task.due_date.as_ref().map(format_date),
+                    updated_task.done,
+                    updated_task.sort_order,
+                    id,
+                ],
+            )?;
+        if updated == 0 {
+            return Err(AppError::not_found(Entity::Task, &updated_task.id));
+        }
+        Ok(())
+    }
+
+    fn fetch(&self) -> Result<Vec<Task>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY project_id, sort_order, id", SELECT_TASK))?;
+        let tasks = stmt
+            .query_map([], row_to_task)?
+            .collect::<rusqlite::Result<Vec<Task>>>()?;
+        Ok(tasks)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::Task, id))?;
+        let conn = self.conn.lock()?;
+        // サブタスクは外部キー制約で一緒に削除される
+        let deleted = conn
+            .execute("DELETE FROM tasks WHERE id = ?1", params![parsed_id])?;
+        if deleted == 0 {
+            return Err(AppError::not_found(Entity::Task, id));
+        }
+        Ok(())
+    }
+}
+
+const SELECT_TIME_ENTRY: &str = "SELECT id, project_id, started_at, ended_at, note FROM time_entries";
+
+fn row_to_time_entry(row: &Row) -> rusqlite::Result<TimeEntry> {
+    let id: i64 = row.get(0)?;
+    let project_id: i64 = row.get(1)?;
+    let started_at: String = row.get(2)?;
+    let ended_at: Option<String> = row.get(3)?;
+    Ok(TimeEntry {
+        id: id.to_string(),
+        project_id: project_id.to_string(),
+        started_at: parse_datetime("started_at", &started_at).map_err(conversion_error(2))?,
+        ended_at: parse_optional_datetime("ended_at", ended_at.as_deref()).map_err(conversion_error(3))?,
+        note: row.get(4)?,
+    })
+}
+
+pub struct SqliteTimeEntryRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteTimeEntryRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+// idがNoneなら新しいidを振る
+fn insert_time_entry(conn: &Connection, entry: &TimeEntry, id: Option<i64>) -> Result<String, AppError> {
+    conn.execute(
+        "INSERT INTO time_entries (id, project_id, started_at, ended_at, note) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            parse_id(&entry.project_id),
+            format_datetime(&entry.started_at),
+            entry.ended_at.as_ref().map(format_datetime),
+            entry.note,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid().to_string())
+}
+
+// 計測中の記録
+fn fetch_running_time_entries(conn: &Connection) -> Result<Vec<TimeEntry>, AppError> {
+    let mut stmt = conn.prepare(&format!("{} WHERE ended_at IS NULL", SELECT_TIME_ENTRY))?;
+    let entries = stmt
+        .query_map([], row_to_time_entry)?
+        .collect::<rusqlite::Result<Vec<TimeEntry>>>()?;
+    Ok(entries)
+}
+
+/// 計測中のタイマーが2つにならないように、記録の確認は書き込みのロックを取ったトランザクションの中で行う
+impl Repository<TimeEntry> for SqliteTimeEntryRepository {
+    fn add(&self, new_entry: TimeEntry) -> Result<String, AppError> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        validate_time_entry(&fetch_running_time_entries(&tx)?, &new_entry)?;
+        let id = insert_time_entry(&tx, &new_entry, None)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn restore(&self, entry: TimeEntry) -> Result<(), AppError> {
+        let id = parse_id(&entry.id).ok_or_else(|| AppError::not_found(Entity::TimeEntry, &entry.id))?;
+        let conn = self.conn.lock()?;
+        insert_time_entry(&conn, &entry, Some(id))?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<TimeEntry>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        conn.query_row(&format!("{} WHERE id = ?1", SELECT_TIME_ENTRY), params![id], row_to_time_entry)
+            .optional()
+            .map_err(AppError::from)
+    }
+
+    fn update(&self, updated_entry: TimeEntry) -> Result<(), AppError> {
+        let id = parse_id(&updated_entry.id).ok_or_else(|| AppError::not_found(Entity::TimeEntry, &updated_entry.id))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        validate_time_entry(&fetch_running_time_entries(&tx)?, &updated_entry)?;
+        let updated = tx
+            .execute(
+                "UPDATE time_entries SET project_id = ?1, started_at = ?2, ended_at = ?3, note = ?4 WHERE id = ?5",
+                params![
+                    parse_id(&updated_entry.project_id),
+                    format_datetime(&updated_entry.started_at),
+                    updated_entry.ended_at.as_ref().map(format_datetime),
+                    updated_entry.note,
+                    id,
+                ],
+            )?;
+        if updated == 0 {
+            return Err(AppError::not_found(Entity::TimeEntry, &updated_entry.id));
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn fetch(&self) -> Result<Vec<TimeEntry>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY started_at", SELECT_TIME_ENTRY))?;
+        let entries = stmt
+            .query_map([], row_to_time_entry)?
+            .collect::<rusqlite::Result<Vec<TimeEntry>>>()?;
+        Ok(entries)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::TimeEntry, id))?;
+        let conn = self.conn.lock()?;
+        let deleted = conn
+            .execute("DELETE FROM time_entries WHERE id = ?1", params![parsed_id])?;
+        if deleted == 0 {
+            return Err(AppError::not_found(Entity::TimeEntry, id));
+        }
+        Ok(())
+    }
+}
+
+impl ToSql for LineItemKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let kind = match self {
+            LineItemKind::Hourly => "Hourly",
+            LineItemKind::Fixed => "Fixed",
+        };
+        Ok(kind.into())
+    }
+}
+
+impl FromSql for LineItemKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "Hourly" => Ok(LineItemKind::Hourly),
+            "Fixed" => Ok(LineItemKind::Fixed),
+            other => Err(FromSqlError::Other(format!("unknown line item kind: {}", other).into())),
+        }
+    }
+}
+
+const SELECT_INVOICE: &str = "SELECT id, invoice_number, project_id, client_id, issue_date, payment_due_date, tax_rate, file_path FROM invoices";
+
+fn row_to_invoice(row: &Row) -> rusqlite::Result<Invoice> {
+    let id: i64 = row.get(0)?;
+    let project_id: i64 = row.get(2)?;
+    let client_id: i64 = row.get(3)?;
+    let issue_date: String = row.get(4)?;
+    let payment_due_date: String = row.get(5)?;
+    Ok(Invoice {
+        id: id.to_string(),
+        invoice_number: row.get(1)?,
+        project_id: project_id.to_string(),
+        client_id: client_id.to_string(),
+        issue_date: parse_date("issue_date", &issue_date).map_err(conversion_error(4))?,
+        payment_due_date: parse_date("payment_due_date", &payment_due_date).map_err(conversion_error(5))?,
+        line_items: Vec::new(),
+        tax_rate: row.get(6)?,
+        file_path: row.get(7)?,
+    })
+}
+
+fn fetch_line_items(conn: &Connection, invoice_id: i64) -> rusqlite::Result<Vec<InvoiceLineItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT description, kind, quantity, unit_price FROM invoice_line_items WHERE invoice_id = ?1 ORDER BY position",
+    )?;
+    let line_items = stmt
+        .query_map(params![invoice_id], |row| {
+            Ok(InvoiceLineItem {
+                description: row.get(0)?,
+                kind: row.get(1)?,
+                quantity: row.get(2)?,
+                unit_price: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<InvoiceLineItem>>>()?;
+    Ok(line_items)
+}
+
+fn insert_line_items(tx: &Transaction, invoice_id: i64, line_items: &[InvoiceLineItem]) -> Result<(), AppError> {
+    for (position, item) in line_items.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO invoice_line_items (invoice_id, position, description, kind, quantity, unit_price)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![invoice_id, position as i64, item.description, item.kind, item.quantity, item.unit_price],
+        )?;
+    }
+    Ok(())
+}
+
+pub struct SqliteInvoiceRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteInvoiceRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl Repository<Invoice> for SqliteInvoiceRepository {
+    /// 請求書番号は、他のウィンドウと重ならないように書き込みのロックを取ったトランザクションの中で振る
+    fn add(&self, mut new_invoice: Invoice) -> Result<String, AppError> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let invoice_numbers = tx
+            .prepare("SELECT invoice_number FROM invoices")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        assign_invoice_number(&invoice_numbers, &mut new_invoice)?;
+        tx.execute(
+            "INSERT INTO invoices (invoice_number, project_id, client_id, issue_date, payment_due_date, tax_rate, file_path)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                new_invoice.invoice_number,
+                parse_id(&new_invoice.project_id),
+                parse_id(&new_invoice.client_id),
+                format_date(&new_invoice.issue_date),
+                format_date(&new_invoice.payment_due_date),
+                new_invoice.tax_rate,
+                new_invoice.file_path,
+            ],
+        )?;
+        let invoice_id = tx.last_insert_rowid();
+        insert_line_items(&tx, invoice_id, &new_invoice.line_items)?;
+        tx.commit()?;
+        Ok(invoice_id.to_string())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Invoice>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        let invoice = conn
+            .query_row(&format!("{} WHERE id = ?1", SELECT_INVOICE), params![id], row_to_invoice)
+            .optional()?;
+        match invoice {
+            Some(mut invoice) => {
+                invoice.line_items = fetch_line_items(&conn, id)?;
+                Ok(Some(invoice))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn update(&self, updated_invoice: Invoice) -> Result<(), AppError> {
+        let id = parse_id(&updated_invoice.id).ok_or_else(|| AppError::not_found(Entity::Invoice, &updated_invoice.id))?;
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let updated = tx
+            .execute(
+                "UPDATE invoices SET invoice_number = ?1, project_id = ?2, client_id = ?3, issue_date = ?4,
+                 payment_due_date = ?5, tax_rate = ?6, file_path = ?7 WHERE id = ?8",
+                params![
+                    updated_invoice.invoice_number,
+                    parse_id(&updated_invoice.project_id),
+                    parse_id(&updated_invoice.client_id),
+                    format_date(&updated_invoice.issue_date),
+                    format_date(&updated_invoice.payment_due_date),
+                    updated_invoice.tax_rate,
+                    updated_invoice.file_path,
+                    id,
+                ],
+            )?;
+        if updated == 0 {
+            return Err(AppError::not_found(Entity::Invoice, &updated_invoice.id));
+        }
+        // 明細は入れ替える
+        tx.execute("DELETE FROM invoice_line_items WHERE invoice_id = ?1", params![id])?;
+        insert_line_items(&tx, id, &updated_invoice.line_items)?;
+        tx.commit().map_err(AppError::from)
+    }
+
+    fn fetch(&self) -> Result<Vec<Invoice>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("{} ORDER BY id", SELECT_INVOICE))?;
+        let mut invoices = stmt
+            .query_map([], row_to_invoice)?
+            .collect::<rusqlite::Result<Vec<Invoice>>>()?;
+        for invoice in invoices.iter_mut() {
+            let invoice_id = parse_id(&invoice.id).unwrap_or_default();
+            invoice.line_items = fetch_line_items(&conn, invoice_id)?;
+        }
+        Ok(invoices)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::Invoice, id))?;
+        let conn = self.conn.lock()?;
+        let deleted = conn
+            .execute("DELETE FROM invoices WHERE id = ?1", params![parsed_id])?;
+        if deleted == 0 {
+            return Err(AppError::not_found(Entity::Invoice, id));
+        }
+        Ok(())
+    }
+}
+
+const SELECT_FOLDER_TEMPLATE: &str = "SELECT id, name, directories, files FROM folder_templates";
+
+fn row_to_folder_template(row: &Row) -> rusqlite::Result<FolderTemplate> {
+    let id: i64 = row.get(0)?;
+    let directories: String = row.get(2)?;
+    let files: String = row.get(3)?;
+    Ok(FolderTemplate {
+        id: id.to_string(),
+        name: row.get(1)?,
+        directories: serde_json::from_str::<Vec<String>>(&directories).map_err(|e| conversion_error(2)(AppError::from(e)))?,
+        files: serde_json::from_str::<Vec<TemplateFile>>(&files).map_err(|e| conversion_error(3)(AppError::from(e)))?,
+    })
+}
+
+/// ユーザーが作ったフォルダテンプレートを読み込む。プロジェクトの追加でも使う
+fn fetch_folder_templates(conn: &Connection) -> Result<Vec<FolderTemplate>, AppError> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY id", SELECT_FOLDER_TEMPLATE))?;
+    let templates = stmt
+        .query_map([], row_to_folder_template)?
+        .collect::<rusqlite::Result<Vec<FolderTemplate>>>()?;
+    Ok(templates)
+}
+
+pub struct SqliteFolderTemplateRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteFolderTemplateRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+/// 標準のテンプレートは保存せず、ユーザーが作ったテンプレートだけを持つ
+impl Repository<FolderTemplate> for SqliteFolderTemplateRepository {
+    fn add(&self, new_template: FolderTemplate) -> Result<String, AppError> {
+        let conn = self.conn.lock()?;
+        conn.execute(
+            "INSERT INTO folder_templates (name, directories, files) VALUES (?1, ?2, ?3)",
+            params![
+                new_template.name,
+                serde_json::to_string(&new_template.directories)?,
+                serde_json::to_string(&new_template.files)?,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid().to_string())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<FolderTemplate>, AppError> {
+        let Some(id) = parse_id(id) else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock()?;
+        conn.query_row(&format!("{} WHERE id = ?1", SELECT_FOLDER_TEMPLATE), params![id], row_to_folder_template)
+            .optional()
+            .map_err(AppError::from)
+    }
+
+    fn update(&self, updated_template: FolderTemplate) -> Result<(), AppError> {
+        let id = parse_id(&updated_template.id).ok_or_else(|| AppError::not_found(Entity::FolderTemplate, &updated_template.id))?;
+        let conn = self.conn.lock()?;
+        let updated = conn.execute(
+            "UPDATE folder_templates SET name = ?1, directories = ?2, files = ?3 WHERE id = ?4",
+            params![
+                updated_template.name,
+                serde_json::to_string(&updated_template.directories)?,
+                serde_json::to_string(&updated_template.files)?,
+                id,
+            ],
+        )?;
+        if updated == 0 {
+            return Err(AppError::not_found(Entity::FolderTemplate, &updated_template.id));
+        }
+        Ok(())
+    }
+
+    fn fetch(&self) -> Result<Vec<FolderTemplate>, AppError> {
+        let conn = self.conn.lock()?;
+        fetch_folder_templates(&conn)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let parsed_id = parse_id(id).ok_or_else(|| AppError::not_found(Entity::FolderTemplate, id))?;
+        let conn = self.conn.lock()?;
+        let deleted = conn
+            .execute("DELETE FROM folder_templates WHERE id = ?1", params![parsed_id])?;
+        if deleted == 0 {
+            return Err(AppError::not_found(Entity::FolderTemplate, id));
+        }
+        Ok(())
+    }
+}
+
+pub struct SqliteAuditLogRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteAuditLogRepository {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl AuditLogRepository for SqliteAuditLogRepository {
+    fn fetch(&self) -> Result<Vec<AuditLogEntry>, AppError> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn
+            .prepare("SELECT timestamp, entity, entity_id, action, changes, user FROM audit.audit_log ORDER BY id")?;
+        let entries = stmt
+            .query_map([], |row| {
+                let timestamp: String = row.get(0)?;
+                let changes: String = row.get(4)?;
+                Ok(AuditLogEntry {
+                    timestamp: parse_datetime("timestamp", &timestamp).map_err(conversion_error(0))?,
+                    entity: row.get(1)?,
+                    entity_id: row.get(2)?,
+                    action: row.get(3)?,
+                    changes: serde_json::from_str::<Vec<FieldChange>>(&changes).map_err(|e| conversion_error(4)(AppError::from(e)))?,
+                    user: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<AuditLogEntry>>>()?;
+        Ok(entries)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_support::{sample_client, sample_project, TestDataRoot};
+
+    fn create_repositories(root: &TestDataRoot) -> (SqliteProjectRepository, SqliteClientRepository) {
+        let connection = open_database(root.path()).unwrap();
+        (SqliteProjectRepository::new(connection.clone()), SqliteClientRepository::new(connection))
+    }
+
+    #[test]
+    fn delete_project_removes_row_and_status_history() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let other_id = project_repo.add(sample_project("案件B", sample_client("", "取引先"))).unwrap();
+
+        project_repo.delete(&id).unwrap();
+
+        let ids = project_repo.fetch().unwrap().into_iter().map(|project| project.id).collect::<Vec<String>>();
+        assert_eq!(ids, vec![other_id]);
+        let history_repo = SqliteStatusHistoryRepository::new(project_repo.conn.clone());
+        assert!(history_repo.fetch_by_project(&id).unwrap().is_empty());
+        assert_eq!(project_repo.delete(&id), Err(AppError::not_found(Entity::Project, &id)));
+    }
+
+    #[test]
+    fn delete_client_in_use_is_rejected() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let project_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let client_id = project_repo.get(&project_id).unwrap().unwrap().client.id;
+
+        assert!(matches!(client_repo.delete(&client_id), Err(AppError::Validation(_))));
+
+        project_repo.delete(&project_id).unwrap();
+        client_repo.delete(&client_id).unwrap();
+        assert!(client_repo.fetch().unwrap().is_empty());
+    }
+
+    #[test]
+    fn fetch_loads_contacts_for_every_client() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let mut first = sample_client("", "取引先A");
+        first.contacts = vec![
+            ClientContact { name: "営業".to_string(), ..ClientContact::default() },
+            ClientContact { name: "経理".to_string(), ..ClientContact::default() },
+        ];
+        let first_id = client_repo.add(first).unwrap();
+        let mut second = sample_client("", "取引先B");
+        second.contacts = vec![ClientContact { name: "制作".to_string(), ..ClientContact::default() }];
+        client_repo.add(second).unwrap();
+        client_repo.add(sample_client("", "取引先C")).unwrap();
+        project_repo.add(sample_project("案件A", client_repo.get(&first_id).unwrap().unwrap())).unwrap();
+        project_repo.add(sample_project("案件B", client_repo.get(&first_id).unwrap().unwrap())).unwrap();
+
+        let contact_names = |client: &Client| client.contacts.iter().map(|contact| contact.name.clone()).collect::<Vec<String>>();
+        let clients = client_repo.fetch().unwrap();
+        assert_eq!(clients.iter().map(contact_names).collect::<Vec<_>>(), vec![vec!["営業", "経理"], vec!["制作"], vec![]]);
+        let projects = project_repo.fetch().unwrap();
+        assert!(projects.iter().all(|project| contact_names(&project.client) == vec!["営業", "経理"]));
+    }
+
+    #[test]
+    fn stale_revision_is_rejected() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut first = project_repo.get(&id).unwrap().unwrap();
+        let mut second = first.clone();
+
+        first.title = "先に保存".to_string();
+        project_repo.update(first).unwrap();
+        second.title = "後から保存".to_string();
+        assert_eq!(project_repo.update(second), Err(AppError::conflict(Entity::Project, &id)));
+
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.title, "先に保存");
+        assert_eq!(saved.revision, 1);
+    }
+
+    #[test]
+    fn add_failing_at_folder_creation_leaves_no_rows() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        // プロジェクトのフォルダを置く場所をファイルにして、フォルダの作成を失敗させる
+        let project_directory = root.path().join("project");
+        let _ = std::fs::remove_dir_all(&project_directory);
+        std::fs::write(&project_directory, "").unwrap();
+
+        assert!(project_repo.add(sample_project("案件A", sample_client("", "新しい取引先"))).is_err());
+
+        assert!(project_repo.fetch().unwrap().is_empty());
+        assert!(client_repo.fetch().unwrap().is_empty());
+        let history_repo = SqliteStatusHistoryRepository::new(project_repo.conn.clone());
+        assert!(history_repo.fetch_by_project("1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_failing_at_rename_keeps_rows() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        // 名前を変える先に同じ名前のファイルを置いて、フォルダの名前の変更を失敗させる
+        std::fs::write(root.path().join("project").join("新しいフォルダ"), "").unwrap();
+
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.folder_path_suffix = Some("新しいフォルダ".to_string());
+        project.client = sample_client("", "新しい取引先");
+        assert!(project_repo.update(project).is_err());
+
+        let saved = project_repo.get(&id).unwrap().unwrap();
+        assert_eq!(saved.folder_path_suffix.as_deref(), Some(id.as_str()));
+        assert_eq!(saved.revision, 0);
+        assert_eq!(client_repo.fetch().unwrap().len(), 1);
+        assert!(root.path().join("project").join(&id).is_dir());
+    }
+
+    #[test]
+    fn audit_log_is_moved_out_of_main_database() {
+        let root = TestDataRoot::new();
+        // 監査ログをメインのデータベースに置いていた頃のデータ
+        std::fs::create_dir_all(root.csv_directory()).unwrap();
+        let legacy = Connection::open(get_database_file_path(root.path())).unwrap();
+        legacy
+            .execute_batch(
+                "CREATE TABLE audit_log (id INTEGER PRIMARY KEY AUTOINCREMENT, timestamp TEXT NOT NULL, entity TEXT NOT NULL, entity_id TEXT NOT NULL, action TEXT NOT NULL, changes TEXT NOT NULL, user TEXT NOT NULL);
+                INSERT INTO audit_log (timestamp, entity, entity_id, action, changes, user) VALUES ('2024-01-02T03:04:05+09:00', 'Project', '1', 'Add', '[]', 'user');",
+            )
+            .unwrap();
+        drop(legacy);
+
+        let connection = open_database(root.path()).unwrap();
+        let (project_repo, _) = create_repositories(&root);
+        project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+
+        let entries = SqliteAuditLogRepository::new(connection.clone()).fetch().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.entity_id.as_str()).collect::<Vec<&str>>(), vec!["1", "1", "1"]);
+        let conn = connection.lock().unwrap();
+        let legacy_tables: i64 = conn
+            .query_row("SELECT COUNT(*) FROM main.sqlite_master WHERE name = 'audit_log'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(legacy_tables, 0);
+        assert!(get_audit_database_file_path(root.path()).exists());
+    }
+
+    #[test]
+    fn update_with_history_returns_both_states_and_created_client() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let mut project = project_repo.get(&id).unwrap().unwrap();
+        project.title = "案件B".to_string();
+        project.client = sample_client("", "新しい取引先");
+
+        let updated = project_repo.update_with_history(project).unwrap();
+
+        assert_eq!(updated.before.title, "案件A");
+        assert_eq!(updated.after.title, "案件B");
+        assert_eq!(updated.after.revision, updated.before.revision + 1);
+        let created_client_id = updated.created_client_id.unwrap();
+        assert_eq!(updated.after.client.id, created_client_id);
+        assert_eq!(client_repo.get(&created_client_id).unwrap().unwrap().name, "新しい取引先");
+    }
+
+    #[test]
+    fn add_all_is_undone_with_clients_folders_and_audit_log() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let audit_log = SqliteAuditLogRepository::new(project_repo.conn.clone());
+        // 2件目のフォルダ名になるidのフォルダを先に作り、途中で失敗させる
+        std::fs::create_dir_all(get_project_directory_path("2")).unwrap();
+        let projects = vec![sample_project("案件A", sample_client("", "新規")), sample_project("案件B", sample_client("", "新規"))];
+
+        assert!(project_repo.add_all(projects).is_err());
+
+        assert!(project_repo.fetch().unwrap().is_empty());
+        assert!(client_repo.fetch().unwrap().is_empty());
+        assert!(audit_log.fetch().unwrap().is_empty());
+        assert!(!get_project_directory_path("1").exists());
+    }
+
+    #[test]
+    fn add_all_creates_same_new_client_once() {
+        let root = TestDataRoot::new();
+        let (project_repo, client_repo) = create_repositories(&root);
+        let projects = vec![sample_project("案件A", sample_client("", "新規")), sample_project("案件B", sample_client("", "新規"))];
+
+        let ids = project_repo.add_all(projects).unwrap();
+
+        let clients = client_repo.fetch().unwrap();
+        assert_eq!(clients.len(), 1);
+        assert!(ids.iter().all(|id| project_repo.get(id).unwrap().unwrap().client.id == clients[0].id));
+    }
+
+    #[test]
+    fn deleted_task_and_time_entry_are_restored_with_same_id() {
+        let root = TestDataRoot::new();
+        let (project_repo, _) = create_repositories(&root);
+        let project_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let task_repo = SqliteTaskRepository::new(project_repo.conn.clone());
+        let time_entry_repo = SqliteTimeEntryRepository::new(project_repo.conn.clone());
+        let task_id = task_repo.add(Task { id: String::new(), project_id: project_id.clone(), parent_id: None, title: "作業".to_string(), description: String::new(), due_date: None, done: false, sort_order: 0 }).unwrap();
+        let entry_id = time_entry_repo.add(TimeEntry { id: String::new(), project_id, started_at: chrono::Local::now(), ended_at: None, note: String::new() }).unwrap();
+        let task = task_repo.get(&task_id).unwrap().unwrap();
+        let entry = time_entry_repo.get(&entry_id).unwrap().unwrap();
+
+        task_repo.delete(&task_id).unwrap();
+        time_entry_repo.delete(&entry_id).unwrap();
+        task_repo.restore(task).unwrap();
+        time_entry_repo.restore(entry).unwrap();
+
+        assert_eq!(task_repo.get(&task_id).unwrap().map(|task| task.title), Some("作業".to_string()));
+        assert!(time_entry_repo.get(&entry_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn invoice_number_is_assigned_when_saved() {
+        let root = TestDataRoot::new();
+        let connection = open_database(root.path()).unwrap();
+        let (project_repo, _) = create_repositories(&root);
+        let project_id = project_repo.add(sample_project("案件A", sample_client("", "取引先"))).unwrap();
+        let client_id = project_repo.get(&project_id).unwrap().unwrap().client.id;
+        let invoice_repo = SqliteInvoiceRepository::new(connection);
+        let invoice = Invoice {
+            id: String::new(),
+            invoice_number: String::new(),
+            project_id,
+            client_id,
+            issue_date: crate::repositories::test_support::date("2024-04-30"),
+            payment_due_date: crate::repositories::test_support::date("2024-05-31"),
+            line_items: Vec::new(),
+            tax_rate: 0.1,
+            file_path: None,
+        };
+
+        let first_id = invoice_repo.add(invoice.clone()).unwrap();
+        let second_id = invoice_repo.add(invoice.clone()).unwrap();
+
+        assert_eq!(invoice_repo.get(&first_id).unwrap().unwrap().invoice_number, "INV-2024-0001");
+        assert_eq!(invoice_repo.get(&second_id).unwrap().unwrap().invoice_number, "INV-2024-0002");
+        let duplicate = Invoice { invoice_number: "INV-2024-0002".to_string(), ..invoice };
+        assert!(matches!(invoice_repo.add(duplicate), Err(AppError::Validation(_))));
+    }
+}
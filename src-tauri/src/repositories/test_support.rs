@@ -0,0 +1,75 @@
+//! テスト用のデータフォルダとデータ
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::NaiveDate;
+use tempfile::TempDir;
+
+use crate::domain::businesslogic::data_directory::set_data_root;
+use crate::domain::models::client::{BillingDetails, Client};
+use crate::domain::models::project::{Project, ProjectStatus};
+
+/// データフォルダはプロセス全体で1つなので、データフォルダを使うテストは1つずつ実行する
+static DATA_ROOT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 一時フォルダをデータフォルダにする。dropでフォルダごと消える
+pub struct TestDataRoot {
+    directory: TempDir,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestDataRoot {
+    pub fn new() -> Self {
+        // 他のテストが失敗してロックが壊れていても続ける
+        let guard = DATA_ROOT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = tempfile::tempdir().unwrap();
+        set_data_root(directory.path().to_path_buf());
+        Self { directory, _guard: guard }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.directory.path()
+    }
+
+    pub fn csv_directory(&self) -> std::path::PathBuf {
+        self.path().join("files")
+    }
+}
+
+pub fn date(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+}
+
+pub fn sample_client(id: &str, name: &str) -> Client {
+    Client {
+        id: id.to_string(),
+        name: name.to_string(),
+        contact_person: "担当者".to_string(),
+        email: String::new(),
+        phone: String::new(),
+        postal_code: String::new(),
+        address: String::new(),
+        billing: BillingDetails::default(),
+        notes: String::new(),
+        contacts: Vec::new(),
+    }
+}
+
+/// idは空なので、追加するとrepositoryが振る
+pub fn sample_project(title: &str, client: Client) -> Project {
+    Project {
+        id: String::new(),
+        title: title.to_string(),
+        description: String::new(),
+        order_date: date("2024-04-01"),
+        due_date: date("2024-04-30"),
+        completion_date: None,
+        client,
+        status: ProjectStatus::NotStarted,
+        folder_path_suffix: None,
+        deleted_at: None,
+        archived_at: None,
+        revision: 0,
+        template_id: None,
+    }
+}
@@ -0,0 +1,199 @@
+use crate::domain::error::AppError;
+
+/// 取り消し処理
+type UndoAction<'a> = Box<dyn FnOnce() -> Result<(), AppError> + 'a>;
+
+/// 複数の書き込み(csvの行、クライアント、プロジェクトフォルダなど)をひとまとめにする。
+/// 書き込みが成功するたびに取り消し方を登録しておき、途中で失敗したら逆順に取り消す。
+/// 最初のrunより後の失敗しうる処理は、runかcheckを通して取り消しの結果をエラーに含める。
+pub struct UnitOfWork<'a> {
+    undo_actions: Vec<(String, UndoAction<'a>)>,
+    committed: bool,
+}
+
+impl<'a> UnitOfWork<'a> {
+    pub fn new() -> Self {
+        Self {
+            undo_actions: Vec::new(),
+            committed: false,
+        }
+    }
+
+    /// 書き込みを実行し、成功したら取り消し方を登録する。失敗したらそれまでの書き込みを取り消す
+    pub fn run<T>(
+        &mut self,
+        description: &str,
+        action: impl FnOnce() -> Result<T, AppError>,
+        undo: impl FnOnce(&T) -> Result<(), AppError> + 'a,
+    ) -> Result<T, AppError>
+    where
+        T: Clone + 'a,
+    {
+        let result = match action() {
+            Ok(result) => result,
+            Err(e) => return Err(self.rollback(e)),
+        };
+        let value = result.clone();
+        self.on_rollback(description, move || undo(&value));
+        Ok(result)
+    }
+
+    /// 取り消し方を登録しない処理の結果を確かめる。失敗していれば、それまでの書き込みを取り消す
+    pub fn check<T>(&mut self, result: Result<T, AppError>) -> Result<T, AppError> {
+        result.map_err(|e| self.rollback(e))
+    }
+
+    /// 既に行った書き込みの取り消し方を登録する
+    pub fn on_rollback(&mut self, description: &str, undo: impl FnOnce() -> Result<(), AppError> + 'a) {
+        self.undo_actions.push((description.to_string(), Box::new(undo)));
+    }
+
+    /// 登録した書き込みを逆順に取り消し、errorを返す。
+    /// 取り消せなかったものがあれば、元のエラーと一緒にRollbackFailedとして返す
+    pub fn rollback(&mut self, error: AppError) -> AppError {
+        let failures = self.run_undo_actions();
+        if failures.is_empty() {
+            error
+        } else {
+            AppError::rollback_failed(error, failures)
+        }
+    }
+
+    /// すべての書き込みを確定する。以降は取り消さない
+    pub fn commit(mut self) {
+        self.committed = true;
+        self.undo_actions.clear();
+    }
+
+    /// 登録した取り消し処理を逆順に実行し、取り消せなかったものを返す
+    fn run_undo_actions(&mut self) -> Vec<String> {
+        let mut failures = Vec::new();
+        while let Some((description, undo)) = self.undo_actions.pop() {
+            if let Err(e) = undo() {
+                failures.push(format!("{} ({})", description, e));
+            }
+        }
+        failures
+    }
+}
+
+impl Drop for UnitOfWork<'_> {
+    /// runとcheckを通らずに抜けた時(panicなど)の最後の手段として取り消す。
+    /// ここでは呼び出し元にエラーを返せないので、失敗した処理はrunかcheckで扱う
+    fn drop(&mut self) {
+        if !self.committed {
+            self.run_undo_actions();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// steps個の書き込みを順に行い、failing_stepで失敗させる。書き込みと取り消しはvaluesに記録する
+    fn run_steps(values: &RefCell<Vec<usize>>, steps: usize, failing_step: usize) -> Result<(), AppError> {
+        let mut unit_of_work = UnitOfWork::new();
+        for step in 1..=steps {
+            unit_of_work.run(
+                &format!("step {}", step),
+                || {
+                    if step == failing_step {
+                        return Err(AppError::io(format!("step {} failed", step)));
+                    }
+                    values.borrow_mut().push(step);
+                    Ok(step)
+                },
+                |step| {
+                    values.borrow_mut().retain(|value| value != step);
+                    Ok(())
+                },
+            )?;
+        }
+        unit_of_work.commit();
+        Ok(())
+    }
+
+    #[test]
+    fn failure_at_any_step_undoes_earlier_steps() {
+        for failing_step in [2, 3, 5] {
+            let values = RefCell::new(Vec::new());
+            let result = run_steps(&values, 5, failing_step);
+            assert_eq!(result, Err(AppError::io(format!("step {} failed", failing_step))));
+            assert!(values.borrow().is_empty(), "step {}", failing_step);
+        }
+    }
+
+    #[test]
+    fn commit_keeps_all_steps() {
+        let values = RefCell::new(Vec::new());
+        run_steps(&values, 3, 0).unwrap();
+        assert_eq!(*values.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn undo_runs_in_reverse_order() {
+        let order = RefCell::new(Vec::new());
+        let mut unit_of_work = UnitOfWork::new();
+        for step in 1..=3 {
+            unit_of_work.run(&format!("step {}", step), || Ok(step), |step| {
+                order.borrow_mut().push(*step);
+                Ok(())
+            }).unwrap();
+        }
+        let error = unit_of_work.rollback(AppError::io("failed"));
+        assert_eq!(error, AppError::io("failed"));
+        assert_eq!(*order.borrow(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn check_rolls_back_on_error() {
+        let undone = RefCell::new(false);
+        let mut unit_of_work = UnitOfWork::new();
+        unit_of_work.run("step 1", || Ok(()), |_| {
+            *undone.borrow_mut() = true;
+            Ok(())
+        }).unwrap();
+        assert_eq!(unit_of_work.check(Ok::<i32, AppError>(1)), Ok(1));
+        assert!(!*undone.borrow());
+
+        let result = unit_of_work.check(Err::<(), AppError>(AppError::validation("invalid")));
+        assert_eq!(result, Err(AppError::validation("invalid")));
+        assert!(*undone.borrow());
+    }
+
+    #[test]
+    fn failed_undo_is_reported_with_the_original_error() {
+        let undone = RefCell::new(Vec::new());
+        let mut unit_of_work = UnitOfWork::new();
+        unit_of_work.run("step 1", || Ok(()), |_| {
+            undone.borrow_mut().push(1);
+            Ok(())
+        }).unwrap();
+        unit_of_work.run("step 2", || Ok(()), |_| Err(AppError::io("undo failed"))).unwrap();
+
+        let result = unit_of_work.run("step 3", || Err::<(), AppError>(AppError::io("step 3 failed")), |_| Ok(()));
+
+        let error = result.unwrap_err();
+        assert_eq!(error, AppError::rollback_failed(AppError::io("step 3 failed"), vec!["step 2 (undo failed)".to_string()]));
+        assert_eq!(error.code(), "ROLLBACK_FAILED");
+        // 取り消せなかったものがあっても、残りは取り消す
+        assert_eq!(*undone.borrow(), vec![1]);
+    }
+
+    #[test]
+    fn drop_without_commit_rolls_back() {
+        let undone = RefCell::new(false);
+        {
+            let mut unit_of_work = UnitOfWork::new();
+            unit_of_work.run("step 1", || Ok(()), |_| {
+                *undone.borrow_mut() = true;
+                Ok(())
+            }).unwrap();
+        }
+        assert!(*undone.borrow());
+    }
+}
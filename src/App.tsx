@@ -1,10 +1,50 @@
 import "./App.css";
 
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api";
+import { listen } from "@tauri-apps/api/event";
+import Alert from "@mui/material/Alert";
+
 import ProjectLists from "./pages/Projects/ProjectsList";
+import { AppError, BACKGROUND_ERROR_EVENT } from "./AppError";
 
 function App() {
+  // 起動時にデータを開けなかった時のエラー
+  const [startupError, setStartupError] = useState<AppError | null>(null);
+
+  useEffect(() => {
+    (async () => {
+      const error = (await invoke("fetch_startup_error")) as AppError | null;
+      setStartupError(error);
+    })();
+  }, []);
+
+  // バックグラウンドの処理のエラー。最後に届いたものだけ表示する
+  const [backgroundError, setBackgroundError] = useState<AppError | null>(null);
+
+  useEffect(() => {
+    const unlisten = listen<AppError>(BACKGROUND_ERROR_EVENT, (event) => {
+      setBackgroundError(event.payload);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
   return (
     <div className="container">
+      {startupError && (
+        <Alert severity="error" sx={{ whiteSpace: "pre-wrap", mb: 2 }}>
+          データを開けませんでした。
+          {"\n"}
+          {startupError.message}
+        </Alert>
+      )}
+      {backgroundError && (
+        <Alert severity="warning" sx={{ whiteSpace: "pre-wrap", mb: 2 }} onClose={() => setBackgroundError(null)}>
+          {backgroundError.message}
+        </Alert>
+      )}
       <ProjectLists />
     </div>
   );
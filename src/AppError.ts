@@ -0,0 +1,32 @@
+// Rustのコマンドが返すエラー(domain/error.rsのAppError)
+export type AppErrorCode =
+  | "NOT_FOUND"
+  | "DUPLICATE_FOLDER"
+  | "VALIDATION"
+  | "IO"
+  | "PARSE"
+  | "CONFLICT"
+  | "ROLLBACK_FAILED";
+
+export type AppError = {
+  code: AppErrorCode;
+  message: string;
+  details: Record<string, unknown> | null;
+};
+
+// バックグラウンドの処理(検索の索引やフォルダの監視など)が失敗した時に届くイベント。中身はAppError
+export const BACKGROUND_ERROR_EVENT = "background-error";
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === "object" && e !== null && "code" in e && "message" in e;
+}
+
+// 画面に表示するメッセージ。AppError以外のエラーはそのまま文字列にする
+export function errorMessage(e: unknown): string {
+  return isAppError(e) ? e.message : String(e);
+}
+
+// 他の画面で先に更新されていた時のエラーかどうか
+export function isConflictError(e: unknown): boolean {
+  return isAppError(e) && e.code === "CONFLICT";
+}
@@ -99,7 +99,7 @@ export default function ProjectModal(prop: ProjectModalProps) {
 
   const changeOrderDate = (newDate: Date | null) => {
     const orderDate = newDate ?? new Date();
-    const orderDateStr = orderDate.toLocaleDateString();
+    const orderDateStr = new Calendar(orderDate).format("Y-m-d");
     setProject((prevProject) => ({
       ...prevProject,
       order_date: orderDateStr,
@@ -108,7 +108,7 @@ export default function ProjectModal(prop: ProjectModalProps) {
 
   const changeDueDate = (newDate: Date | null) => {
     const dueDate = newDate ?? new Date();
-    const dueDateStr = dueDate.toLocaleDateString();
+    const dueDateStr = new Calendar(dueDate).format("Y-m-d");
     setProject((prevProject) => ({
       ...prevProject,
       due_date: dueDateStr,
@@ -117,7 +117,7 @@ export default function ProjectModal(prop: ProjectModalProps) {
 
   const changeCompletionDate = (newDate: Date | null) => {
     const dueDate = newDate ?? new Date();
-    const dueDateStr = dueDate.toLocaleDateString();
+    const dueDateStr = new Calendar(dueDate).format("Y-m-d");
     setProject((prevProject) => ({
       ...prevProject,
       completion_date: dueDateStr,
@@ -9,6 +9,7 @@ export type TableData = {
   CompanyName: string;
   ContactName: string;
   ProjectFolderPath: string;
+  Revision: number;
 };
 
 /**
@@ -16,25 +17,34 @@ export type TableData = {
  */
 export type Project = {
   id: string;
+  revision?: number; // 保存するたびに増える。更新時は読み込んだ値をそのまま返す
   title: string;
   description: string;
   // category: string; // Web design, Illustration, etc.
-  order_date: string; // 実際にはより適切な日付型を使用する
+  order_date: string; // ISO-8601 (YYYY-MM-DD)
   due_date: string;
   completion_date?: string; // Option<String> in Rust is equivalent to an optional field in TypeScript
   client: Client;
   status: ProjectStatus;
   folder_path?: string; // todo: Select a library for parsing paths
   folder_path_suffix?: string;
+  deleted_at?: string; // ゴミ箱に入れた日時
+  archived_at?: string; // アーカイブした日時
+  template_id?: string; // フォルダテンプレートのid。省略時は標準のテンプレート
+  task_completion_percentage?: number; // タスクの完了率(0〜100)
 };
 
 /**
  * rustから受け取るStatusデータ
  */
 export enum ProjectStatus {
+  NotStarted = "NotStarted",
   InProgress = "InProgress",
-  Completed = "Completed",
   OnHold = "OnHold",
+  Delivered = "Delivered",
+  Completed = "Completed",
+  Invoiced = "Invoiced",
+  Cancelled = "Cancelled",
 }
 
 /**
@@ -44,4 +54,235 @@ export type Client = {
   id: string;
   name: string;
   contact_person: string;
+  email?: string;
+  phone?: string;
+  postal_code?: string;
+  address?: string;
+  billing?: BillingDetails;
+  notes?: string;
+  contacts?: ClientContact[];
+};
+
+/**
+ * 請求書の宛先
+ */
+export type BillingDetails = {
+  billing_name: string;
+  billing_address: string;
+  billing_email: string;
+};
+
+/**
+ * クライアントの担当者
+ */
+export type ClientContact = {
+  name: string;
+  role: string;
+  email: string;
+  phone: string;
+};
+
+/**
+ * search_projectsに渡す検索条件。指定しない項目では絞り込まない
+ */
+export type ProjectSearchQuery = {
+  text?: string;
+  statuses?: ProjectStatus[];
+  client_id?: string;
+  order_date_from?: string; // YYYY-MM-DD
+  order_date_to?: string;
+  due_date_from?: string;
+  due_date_to?: string;
+  overdue_only?: boolean;
+  sort_key?: "Title" | "ClientName" | "OrderDate" | "DueDate" | "Status";
+  sort_order?: "Asc" | "Desc";
+  page?: number; // 0から始まる
+  page_size?: number; // 省略時は全件
+};
+
+export type ProjectSearchResult = {
+  projects: Project[];
+  total_count: number;
+  page: number;
+  page_size?: number;
+};
+
+/**
+ * search_filesの結果。プロジェクトのフォルダの中で一致したファイル
+ */
+export type FileSearchHit = {
+  project_id: string;
+  relative_path: string; // 例: documents/見積書.pdf
+  snippet: string;
+};
+
+/**
+ * プロジェクトを追加する時に作るフォルダの構成。id: "default"は標準のテンプレート
+ * パスとファイルの中身の{{project_title}}, {{client_name}}, {{contact_person}}, {{project_id}}, {{order_date}}, {{due_date}}は置き換えられる
+ */
+export type FolderTemplate = {
+  id: string; // 新規作成時は空文字
+  name: string;
+  directories: string[]; // 例: documents/contracts
+  files: TemplateFile[];
+};
+
+export type TemplateFile = {
+  path: string; // 例: documents/README.txt
+  contents: string;
+};
+
+/**
+ * list_project_filesの結果。プロジェクトのフォルダの中のファイルとフォルダ
+ */
+export type ProjectFileEntry = {
+  name: string;
+  relative_path: string; // 例: documents/見積書.pdf
+  kind: "File" | "Directory";
+  size: number; // フォルダは0
+  modified?: string;
+  children: ProjectFileEntry[];
+};
+
+/**
+ * check_project_foldersの結果。"project-folders-changed"イベントでも同じ形で届く
+ */
+export type ProjectFolderReport = {
+  missing: MissingProjectFolder[];
+  unlinked_folders: string[]; // どのプロジェクトのものでもないフォルダ
+};
+
+export type MissingProjectFolder = {
+  project_id: string;
+  title: string;
+  folder_path_suffix: string;
+  rename_candidate?: string; // 名前が変えられたと思われるフォルダ
+};
+
+/**
+ * reconcile_project_folderに渡す直し方
+ */
+export type FolderReconcileAction =
+  | { Relink: { folder_name: string } }
+  | "Recreate";
+
+/**
+ * archive_projectでアーカイブした後のフォルダの扱い
+ */
+export type ArchiveProjectOption = "KeepFolder" | "RemoveFolder";
+
+/**
+ * list_backupsの結果。新しい順に並ぶ
+ */
+export type Backup = {
+  id: string;
+  created_at: string;
+  reason: "Scheduled" | "Exit" | "Manual" | "BeforeRestore";
+  schema_version: number;
+  includes_project_folders: boolean;
+  size: number; // zipファイルの大きさ(バイト)
+};
+
+/**
+ * get_backup_settings・set_backup_settingsで使う自動バックアップの設定
+ */
+export type BackupSettings = {
+  enabled: boolean;
+  interval_hours: number;
+  on_exit: boolean;
+  keep_count: number;
+  include_project_folders: boolean;
+};
+
+/**
+ * fetch_audit_logの結果。プロジェクトとクライアントの変更の記録
+ */
+export type AuditLogEntry = {
+  timestamp: string;
+  entity: AuditEntity;
+  entity_id: string;
+  action: "Add" | "Update" | "Delete" | "RenameFolder";
+  changes: FieldChange[];
+  user: string; // 変更したOSのユーザー
+};
+
+export type AuditEntity = "Project" | "Client";
+
+export type FieldChange = {
+  field: string; // 例: due_date, billing.billing_name
+  before?: string; // 追加の時はなし
+  after?: string; // 削除の時はなし
+};
+
+/**
+ * fetch_audit_logの絞り込み条件。指定しなかった項目では絞り込まない
+ */
+export type AuditLogQuery = {
+  entity?: AuditEntity;
+  entity_id?: string;
+  date_from?: string; // YYYY-MM-DD
+  date_to?: string;
+};
+
+/**
+ * undo・redo・fetch_undo_redo_stateの結果
+ */
+export type UndoRedoState = {
+  can_undo: boolean;
+  can_redo: boolean;
+  entity?: AuditEntity; // 書き戻したデータ。何もしなかった時はなし
+  entity_id?: string;
+};
+
+/**
+ * ファイルから取り込む時の、項目と列の見出しの対応。指定しなかった項目は読み込まない
+ */
+export type ImportColumnMapping = {
+  title?: string; // 必須
+  description?: string;
+  order_date?: string; // 必須
+  due_date?: string; // 必須
+  completion_date?: string;
+  status?: string; // 省略したら未着手
+  client_name?: string; // 必須。同じ名前のクライアントがなければ作る
+  contact_person?: string;
+  folder_path_suffix?: string; // 省略したらプロジェクトのid
+};
+
+/**
+ * preview_importとimport_projectsに渡す取り込みの指定
+ */
+export type ImportRequest = {
+  file_path: string;
+  sheet_name?: string; // 省略したら最初のシート。csvでは使わない
+  mapping: ImportColumnMapping;
+  template_id?: string;
+};
+
+/**
+ * read_import_fileの結果
+ */
+export type ImportFileInfo = {
+  sheet_names: string[]; // csvの時は空
+  columns: string[];
+  row_count: number;
+};
+
+export type ImportPreview = {
+  rows: ImportRowPreview[];
+  valid_count: number;
+  error_count: number; // 1以上ならimport_projectsは何もしない
+  new_clients: string[];
+};
+
+export type ImportRowPreview = {
+  row_number: number; // ファイルでの行番号
+  project?: Project; // エラーがあればなし
+  new_client: boolean;
+  errors: string[];
+};
+
+export type ImportSummary = {
+  project_ids: string[];
+  created_clients: Client[];
 };
@@ -14,6 +14,7 @@ import CircularProgress from "@mui/material/CircularProgress";
 import { Box } from "@mui/material";
 
 import { Client, Project, ProjectStatus, TableData } from "./Projects.type";
+import { errorMessage, isConflictError } from "../../AppError";
 
 import ProjectModal from "./ProjectModal";
 import ClientsListModal from "../Clients/ClientsListModal";
@@ -45,7 +46,7 @@ export default function ProjectLists() {
       await invoke("add_project", { newProject: project });
       await fetchData();
     } catch (e: any) {
-      alert("登録に失敗しました。" + e);
+      alert("登録に失敗しました。" + errorMessage(e));
       console.error(e);
     } finally {
       setLoading(false);
@@ -58,7 +59,13 @@ export default function ProjectLists() {
       await invoke("update_project", { project: project });
       await fetchData();
     } catch (e: any) {
-      alert("登録に失敗しました。" + e);
+      if (isConflictError(e)) {
+        // 他の画面で先に更新されていたので、最新の内容を読み込み直す
+        alert("他の画面で更新されていたため保存できませんでした。最新の内容を読み込みます。");
+        await fetchData();
+        return;
+      }
+      alert("登録に失敗しました。" + errorMessage(e));
       console.error(e);
     } finally {
       setLoading(false);
@@ -161,6 +168,7 @@ export function convertProjectsToTableData(projects: Project[]): TableData[] {
     CompanyName: project.client.name,
     ContactName: project.client.contact_person,
     ProjectFolderPath: project.folder_path ?? "",
+    Revision: project.revision ?? 0,
   }));
 }
 
@@ -188,6 +196,7 @@ function convertTableDataToProject(tableData: TableData): Project {
     client: client,
     status: status,
     folder_path: tableData.ProjectFolderPath,
+    revision: tableData.Revision,
     folder_path_suffix: tableData.ProjectFolderPath
       ? [...tableData.ProjectFolderPath.split("/")].pop()
       : undefined,